@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex, MutexGuard};
+
+use futures_util::future::{AbortHandle, Abortable};
+
+use crate::error::CommandError;
+
+/// Abort handles of the commands currently running under a client-supplied id,
+/// tagged with the registration they belong to.
+static IN_FLIGHT: LazyLock<Mutex<HashMap<String, (u64, AbortHandle)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static NEXT_REGISTRATION: AtomicU64 = AtomicU64::new(1);
+
+fn in_flight() -> MutexGuard<'static, HashMap<String, (u64, AbortHandle)>> {
+    IN_FLIGHT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Unregisters the id however the command finishes, including when its future is
+/// dropped. A cancelled id can be reused right away, so only this registration's
+/// own entry is removed.
+struct Registration<'a> {
+    request_id: &'a str,
+    generation: u64,
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = in_flight();
+        if in_flight
+            .get(self.request_id)
+            .is_some_and(|(generation, _)| *generation == self.generation)
+        {
+            in_flight.remove(self.request_id);
+        }
+    }
+}
+
+/// Runs `future` so that `cancel_request(request_id)` can stop it, in which case the
+/// result is a `Cancelled` error. Without an id the future simply runs to completion.
+pub async fn cancellable<T>(
+    request_id: Option<&str>,
+    future: impl Future<Output = Result<T, CommandError>>,
+) -> Result<T, CommandError> {
+    let Some(request_id) = request_id else {
+        return future.await;
+    };
+
+    let (handle, abort_registration) = AbortHandle::new_pair();
+    let generation = NEXT_REGISTRATION.fetch_add(1, Ordering::Relaxed);
+    {
+        let mut in_flight = in_flight();
+        if in_flight.contains_key(request_id) {
+            return Err(CommandError::invalid_request(format!(
+                "Request {} is already in flight",
+                request_id
+            )));
+        }
+        in_flight.insert(request_id.to_string(), (generation, handle));
+    }
+    let _registration = Registration {
+        request_id,
+        generation,
+    };
+
+    Abortable::new(future, abort_registration)
+        .await
+        .unwrap_or_else(|_| {
+            Err(CommandError::Cancelled {
+                request_id: request_id.to_string(),
+            })
+        })
+}
+
+/// Aborts the command running under `request_id`. Returns whether one was found;
+/// a request that already finished is not an error.
+#[tauri::command]
+pub fn cancel_request(request_id: String) -> bool {
+    match in_flight().remove(&request_id) {
+        Some((_, handle)) => {
+            handle.abort();
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use std::time::Duration;
+
+    #[test]
+    fn cancel_request_aborts_the_registered_future() {
+        let result = tauri::async_runtime::block_on(async {
+            let pending = cancellable(Some("cancel-test"), async {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+                Ok(())
+            });
+            let cancel = async {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                assert!(cancel_request("cancel-test".to_string()));
+            };
+            let (result, ()) = futures_util::future::join(pending, cancel).await;
+            result
+        });
+
+        let error = result.unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Cancelled);
+        assert_eq!(error.to_string(), "Request cancel-test was cancelled");
+        assert!(!cancel_request("cancel-test".to_string()));
+    }
+
+    #[test]
+    fn a_cancelled_future_does_not_unregister_a_reused_id() {
+        tauri::async_runtime::block_on(async {
+            let never = || async {
+                std::future::pending::<()>().await;
+                Ok(())
+            };
+            let mut first = Box::pin(cancellable(Some("reuse-test"), never()));
+            assert!(futures_util::poll!(&mut first).is_pending());
+            assert!(cancel_request("reuse-test".to_string()));
+
+            let mut second = Box::pin(cancellable(Some("reuse-test"), never()));
+            assert!(futures_util::poll!(&mut second).is_pending());
+            drop(first);
+
+            assert!(cancel_request("reuse-test".to_string()));
+        });
+    }
+
+    #[test]
+    fn finished_requests_are_unregistered() {
+        let result =
+            tauri::async_runtime::block_on(cancellable(Some("done-test"), async { Ok(7) }));
+        assert_eq!(result, Ok(7));
+        assert!(!cancel_request("done-test".to_string()));
+
+        let result = tauri::async_runtime::block_on(cancellable(None, async {
+            Err::<(), _>("failed".into())
+        }));
+        assert_eq!(result.unwrap_err().to_string(), "failed");
+    }
+}
@@ -0,0 +1,442 @@
+use std::fmt;
+
+use reqwest::{Response, StatusCode};
+use serde::{Serialize, Serializer};
+
+use crate::providers::provider::error_message;
+use crate::providers::retry::is_retryable_status;
+
+/// Stable identifier the frontend switches on instead of matching message text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    MissingApiKey,
+    Unauthorized,
+    NotFound,
+    RateLimited,
+    QuotaExceeded,
+    InvalidRequest,
+    /// The provider reported that the work itself failed, e.g. a failed prediction.
+    ProviderFailed,
+    ProviderUnavailable,
+    Timeout,
+    Network,
+    BudgetExceeded,
+    Cancelled,
+    Internal,
+}
+
+/// The machine-readable parts of a provider's error body.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProviderErrorDetails {
+    /// e.g. Anthropic's `overloaded_error` or Google's `RESOURCE_EXHAUSTED`.
+    pub error_type: Option<String>,
+    /// e.g. OpenAI's `insufficient_quota`.
+    pub error_code: Option<String>,
+    /// The request field the provider rejected, when it says.
+    pub param: Option<String>,
+    pub body: serde_json::Value,
+}
+
+impl ProviderErrorDetails {
+    /// Reads the common shapes: `{"error": {"type", "code", "param"}}` (OpenAI,
+    /// Anthropic, OpenRouter), `{"error": {"status", "code"}}` (Google) and problem
+    /// details `{"type", "title", "detail"}` (Replicate). `None` for non-JSON bodies.
+    pub fn parse(body: &str) -> Option<Self> {
+        let body: serde_json::Value = serde_json::from_str(body).ok()?;
+        if !body.is_object() {
+            return None;
+        }
+
+        let field = |pointers: &[&str]| {
+            pointers
+                .iter()
+                .filter_map(|pointer| body.pointer(pointer))
+                .find_map(|value| match value {
+                    serde_json::Value::String(text) => Some(text.clone()),
+                    serde_json::Value::Number(number) => Some(number.to_string()),
+                    _ => None,
+                })
+        };
+
+        Some(ProviderErrorDetails {
+            error_type: field(&["/error/type", "/error/status", "/type"]),
+            error_code: field(&["/error/code", "/code"]),
+            param: field(&["/error/param"]),
+            body: body.clone(),
+        })
+    }
+}
+
+/// The error every command returns. It serializes to
+/// `{code, message, provider, status, retryable, details}`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandError {
+    MissingApiKey {
+        provider: String,
+    },
+    /// The provider answered with a non-2xx status, or reported a failure inside a
+    /// successful response (a stream error event, a failed prediction), in which
+    /// case there is no `status`.
+    Provider {
+        provider: String,
+        status: Option<u16>,
+        message: String,
+        details: Option<Box<ProviderErrorDetails>>,
+    },
+    /// No response arrived: DNS, connection, TLS or timeout failures.
+    Network {
+        provider: Option<String>,
+        message: String,
+        timeout: bool,
+    },
+    BudgetExceeded {
+        message: String,
+    },
+    Cancelled {
+        request_id: String,
+    },
+    /// Arguments the command rejected before calling a provider.
+    InvalidRequest {
+        message: String,
+    },
+    /// File system, parse and other internal failures.
+    Other {
+        message: String,
+    },
+}
+
+impl CommandError {
+    pub fn missing_api_key(provider: &str) -> Self {
+        CommandError::MissingApiKey {
+            provider: provider.to_string(),
+        }
+    }
+
+    pub fn provider_status(provider: &str, status: StatusCode, body: &str) -> Self {
+        CommandError::Provider {
+            provider: provider.to_string(),
+            status: Some(status.as_u16()),
+            message: error_message(body),
+            details: ProviderErrorDetails::parse(body).map(Box::new),
+        }
+    }
+
+    /// An error event inside a stream, such as `{"error": {"type", "message"}}`.
+    /// OpenRouter puts the upstream HTTP status in `error.code`, which is kept.
+    pub fn provider_event(provider: &str, event: &str) -> Self {
+        let details = ProviderErrorDetails::parse(event);
+        let status = details
+            .as_ref()
+            .and_then(|details| details.error_code.as_deref()?.parse::<u16>().ok())
+            .filter(|status| (400..600).contains(status));
+        CommandError::Provider {
+            provider: provider.to_string(),
+            status,
+            message: error_message(event),
+            details: details.map(Box::new),
+        }
+    }
+
+    /// Work the provider accepted but reports as failed, e.g. a failed prediction.
+    pub fn provider_failed(provider: &str, message: impl Into<String>) -> Self {
+        CommandError::Provider {
+            provider: provider.to_string(),
+            status: None,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        CommandError::InvalidRequest {
+            message: message.into(),
+        }
+    }
+
+    /// Reads the body of a failed response into a `Provider` error.
+    pub async fn from_response(provider: &str, response: Response) -> Self {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        CommandError::provider_status(provider, status, &body)
+    }
+
+    /// A failed download from a URL that belongs to no provider, reported under the
+    /// URL's host.
+    pub async fn from_download(response: Response) -> Self {
+        let host = response.url().host_str().unwrap_or("download").to_string();
+        CommandError::from_response(&host, response).await
+    }
+
+    /// A request that failed before any response, as `Failed to <action>: <error>`.
+    pub fn network(provider: Option<&str>, action: &str, error: reqwest::Error) -> Self {
+        CommandError::Network {
+            provider: provider.map(str::to_string),
+            message: format!("Failed to {}: {}", action, error),
+            timeout: error.is_timeout(),
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            CommandError::MissingApiKey { .. } => ErrorCode::MissingApiKey,
+            CommandError::Provider {
+                status: Some(status),
+                details,
+                ..
+            } => match status {
+                401 | 403 => ErrorCode::Unauthorized,
+                404 => ErrorCode::NotFound,
+                408 => ErrorCode::Timeout,
+                429 if is_quota_error(details.as_deref()) => ErrorCode::QuotaExceeded,
+                429 => ErrorCode::RateLimited,
+                402 => ErrorCode::QuotaExceeded,
+                500.. => ErrorCode::ProviderUnavailable,
+                _ => ErrorCode::InvalidRequest,
+            },
+            CommandError::Provider {
+                status: None,
+                details,
+                ..
+            } => match details
+                .as_ref()
+                .and_then(|details| details.error_type.as_deref())
+            {
+                _ if is_quota_error(details.as_deref()) => ErrorCode::QuotaExceeded,
+                Some("overloaded_error" | "api_error" | "server_error") => {
+                    ErrorCode::ProviderUnavailable
+                }
+                Some("rate_limit_error") => ErrorCode::RateLimited,
+                _ => ErrorCode::ProviderFailed,
+            },
+            CommandError::Network { timeout: true, .. } => ErrorCode::Timeout,
+            CommandError::Network { .. } => ErrorCode::Network,
+            CommandError::BudgetExceeded { .. } => ErrorCode::BudgetExceeded,
+            CommandError::Cancelled { .. } => ErrorCode::Cancelled,
+            CommandError::InvalidRequest { .. } => ErrorCode::InvalidRequest,
+            CommandError::Other { .. } => ErrorCode::Internal,
+        }
+    }
+
+    pub fn provider(&self) -> Option<&str> {
+        match self {
+            CommandError::MissingApiKey { provider } | CommandError::Provider { provider, .. } => {
+                Some(provider)
+            }
+            CommandError::Network { provider, .. } => provider.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            CommandError::Provider { status, .. } => *status,
+            _ => None,
+        }
+    }
+
+    /// Whether trying again later can succeed without the user changing anything.
+    pub fn retryable(&self) -> bool {
+        match self {
+            CommandError::Provider {
+                status: Some(status),
+                details,
+                ..
+            } => {
+                StatusCode::from_u16(*status).is_ok_and(is_retryable_status)
+                    && !is_quota_error(details.as_deref())
+            }
+            CommandError::Provider { status: None, .. } => matches!(
+                self.code(),
+                ErrorCode::ProviderUnavailable | ErrorCode::RateLimited
+            ),
+            CommandError::Network { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+/// A 429 that will not clear by waiting, such as an exhausted OpenAI balance.
+fn is_quota_error(details: Option<&ProviderErrorDetails>) -> bool {
+    details.is_some_and(|details| {
+        [&details.error_code, &details.error_type]
+            .into_iter()
+            .flatten()
+            .any(|value| value == "insufficient_quota")
+    })
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::MissingApiKey { provider } => write!(
+                f,
+                "{} API key not configured. Please add it in Settings.",
+                provider
+            ),
+            CommandError::Provider {
+                provider,
+                status: Some(status),
+                message,
+                ..
+            } => {
+                let status = StatusCode::from_u16(*status)
+                    .map(|status| status.to_string())
+                    .unwrap_or_else(|_| status.to_string());
+                write!(f, "{} API error ({}): {}", provider, status, message)
+            }
+            CommandError::Provider {
+                provider,
+                status: None,
+                message,
+                ..
+            } => write!(f, "{} error: {}", provider, message),
+            CommandError::Cancelled { request_id } => {
+                write!(f, "Request {} was cancelled", request_id)
+            }
+            CommandError::Network { message, .. }
+            | CommandError::BudgetExceeded { message }
+            | CommandError::InvalidRequest { message }
+            | CommandError::Other { message } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl Serialize for CommandError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Wire<'a> {
+            code: ErrorCode,
+            message: String,
+            provider: Option<&'a str>,
+            status: Option<u16>,
+            retryable: bool,
+            details: Option<&'a ProviderErrorDetails>,
+        }
+
+        Wire {
+            code: self.code(),
+            message: self.to_string(),
+            provider: self.provider(),
+            status: self.status(),
+            retryable: self.retryable(),
+            details: match self {
+                CommandError::Provider { details, .. } => details.as_deref(),
+                _ => None,
+            },
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Lets `?` lift the plain-text errors of helpers that have not been typed.
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Other { message }
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        CommandError::from(message.to_string())
+    }
+}
+
+impl From<CommandError> for String {
+    fn from(error: CommandError) -> Self {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn provider_errors_serialize_with_code_status_and_details() {
+        let error = CommandError::provider_status(
+            "OpenAI",
+            StatusCode::TOO_MANY_REQUESTS,
+            r#"{"error":{"message":"Rate limit reached","type":"requests","code":"rate_limit_exceeded"}}"#,
+        );
+        assert_eq!(
+            error.to_string(),
+            "OpenAI API error (429 Too Many Requests): Rate limit reached"
+        );
+
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["code"], "rate_limited");
+        assert_eq!(json["provider"], "OpenAI");
+        assert_eq!(json["status"], 429);
+        assert_eq!(json["retryable"], true);
+        assert_eq!(json["details"]["error_code"], "rate_limit_exceeded");
+        assert_eq!(json["details"]["error_type"], "requests");
+    }
+
+    #[test]
+    fn classifies_quota_auth_and_plain_errors() {
+        let quota = CommandError::provider_status(
+            "OpenAI",
+            StatusCode::TOO_MANY_REQUESTS,
+            r#"{"error":{"message":"You exceeded your current quota","code":"insufficient_quota"}}"#,
+        );
+        assert_eq!(quota.code(), ErrorCode::QuotaExceeded);
+        assert!(!quota.retryable());
+
+        let overloaded = CommandError::provider_status(
+            "Anthropic",
+            StatusCode::from_u16(529).unwrap(),
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+        );
+        assert_eq!(overloaded.code(), ErrorCode::ProviderUnavailable);
+        assert!(overloaded.retryable());
+
+        let unauthorized =
+            CommandError::provider_status("Replicate", StatusCode::UNAUTHORIZED, "Unauthorized");
+        assert_eq!(unauthorized.code(), ErrorCode::Unauthorized);
+        assert_eq!(unauthorized.status(), Some(401));
+
+        assert_eq!(
+            CommandError::missing_api_key("Gemini").code(),
+            ErrorCode::MissingApiKey
+        );
+        assert_eq!(
+            CommandError::from("Failed to read file").code(),
+            ErrorCode::Internal
+        );
+        assert_eq!(
+            CommandError::invalid_request("max_tokens must be greater than zero").code(),
+            ErrorCode::InvalidRequest
+        );
+    }
+
+    #[test]
+    fn classifies_failures_reported_inside_a_response() {
+        let overloaded = CommandError::provider_event(
+            "Anthropic",
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+        );
+        assert_eq!(overloaded.to_string(), "Anthropic error: Overloaded");
+        assert_eq!(overloaded.code(), ErrorCode::ProviderUnavailable);
+        assert_eq!(overloaded.status(), None);
+        assert!(overloaded.retryable());
+
+        let upstream = CommandError::provider_event(
+            "OpenRouter",
+            r#"{"error":{"code":502,"message":"Provider returned error"}}"#,
+        );
+        assert_eq!(upstream.status(), Some(502));
+        assert_eq!(upstream.code(), ErrorCode::ProviderUnavailable);
+
+        let failed = CommandError::provider_failed("Replicate", "CUDA out of memory");
+        assert_eq!(failed.code(), ErrorCode::ProviderFailed);
+        assert_eq!(failed.provider(), Some("Replicate"));
+        assert!(!failed.retryable());
+        assert_eq!(
+            serde_json::to_value(&failed).unwrap()["status"],
+            serde_json::Value::Null
+        );
+    }
+}
@@ -6,7 +6,12 @@ use chrono::Utc;
 use reqwest::header::{HeaderMap, HeaderValue};
 use tauri::Manager;
 
-use crate::path_utils::{sanitize_extension, sanitize_filename, sanitize_relative_path};
+use crate::cancel::cancellable;
+use crate::error::CommandError;
+use crate::http;
+use crate::path_utils::{
+    mime_type_for_path, sanitize_extension, sanitize_filename, sanitize_relative_path,
+};
 use crate::settings::load_settings;
 
 fn resolve_destination_folder(
@@ -67,105 +72,83 @@ pub async fn download_and_save_file(
     url: String,
     filename: Option<String>,
     destination_folder: Option<String>,
-) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    let mut request = client.get(&url);
-
-    if url.starts_with("https://api.replicate.com/") {
-        let settings = load_settings(app_handle.clone()).await?;
-        if let Some(api_key) = settings.replicate_api_key {
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                "Authorization",
-                HeaderValue::from_str(&format!("Bearer {}", api_key))
-                    .map_err(|e| format!("Failed to create auth header: {}", e))?,
-            );
-            request = request.headers(headers);
-
-            if cfg!(debug_assertions) {
-                println!("Added Authorization header for Replicate file download");
+    request_id: Option<String>,
+) -> Result<String, CommandError> {
+    cancellable(request_id.as_deref(), async {
+        let http = http::shared_client(&app_handle);
+        let mut request = http.client().get(&url);
+
+        if url.starts_with("https://api.replicate.com/") {
+            let settings = load_settings(app_handle.clone()).await?;
+            if let Some(api_key) = settings.replicate_api_key {
+                let mut headers = HeaderMap::new();
+                headers.insert(
+                    "Authorization",
+                    HeaderValue::from_str(&format!("Bearer {}", api_key))
+                        .map_err(|e| format!("Failed to create auth header: {}", e))?,
+                );
+                request = request.headers(headers);
+
+                if cfg!(debug_assertions) {
+                    println!("Added Authorization header for Replicate file download");
+                }
+            } else {
+                return Err(CommandError::missing_api_key("Replicate"));
             }
-        } else {
-            return Err(
-                "Replicate API key not configured but required for file download".to_string(),
-            );
         }
-    }
 
-    let response = request
-        .send()
-        .await
-        .map_err(|e| format!("Failed to download file: {}", e))?;
-
-    let status = response.status();
-    if !status.is_success() {
-        let error_body = response.text().await.unwrap_or_default();
-        return Err(format!(
-            "Download failed with status: {} - {}",
-            status, error_body
-        ));
-    }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| CommandError::network(None, "download file", e))?;
 
-    let bytes = response
-        .bytes()
-        .await
-        .map_err(|e| format!("Failed to read file bytes: {}", e))?;
+        if !response.status().is_success() {
+            return Err(CommandError::from_download(response).await);
+        }
 
-    let dest_folder = resolve_destination_folder(&app_handle, destination_folder)?;
+        let bytes = http.read_body(response).await?;
 
-    if !dest_folder.exists() {
-        fs::create_dir_all(&dest_folder)
-            .map_err(|e| format!("Failed to create destination folder: {}", e))?;
-    }
+        let dest_folder = resolve_destination_folder(&app_handle, destination_folder)?;
 
-    let file_name = if let Some(name) = filename {
-        sanitize_filename(&name)
-    } else {
-        let timestamp = Utc::now().timestamp();
+        if !dest_folder.exists() {
+            fs::create_dir_all(&dest_folder)
+                .map_err(|e| format!("Failed to create destination folder: {}", e))?;
+        }
 
-        let raw_extension = url
-            .split('?')
-            .next()
-            .and_then(|s| s.split('.').last())
-            .unwrap_or("png");
-        let extension = sanitize_extension(raw_extension);
-        let extension = if extension.is_empty() {
-            "png".to_string()
+        let file_name = if let Some(name) = filename {
+            sanitize_filename(&name)
         } else {
-            extension
+            let timestamp = Utc::now().timestamp();
+
+            let raw_extension = url
+                .split('?')
+                .next()
+                .and_then(|s| s.split('.').last())
+                .unwrap_or("png");
+            let extension = sanitize_extension(raw_extension);
+            let extension = if extension.is_empty() {
+                "png".to_string()
+            } else {
+                extension
+            };
+
+            sanitize_filename(&format!("noder-output-{}.{}", timestamp, extension))
         };
 
-        sanitize_filename(&format!("noder-output-{}.{}", timestamp, extension))
-    };
-
-    let file_path = dest_folder.join(&file_name);
+        let file_path = dest_folder.join(&file_name);
 
-    fs::write(&file_path, &bytes).map_err(|e| format!("Failed to write file: {}", e))?;
+        fs::write(&file_path, &bytes).map_err(|e| format!("Failed to write file: {}", e))?;
 
-    Ok(file_path.to_string_lossy().to_string())
+        Ok(file_path.to_string_lossy().to_string())
+    })
+    .await
 }
 
 #[tauri::command]
-pub async fn read_file_as_base64(file_path: String) -> Result<String, String> {
+pub async fn read_file_as_base64(file_path: String) -> Result<String, CommandError> {
     let bytes = fs::read(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
 
-    let mime_type = if file_path.to_lowercase().ends_with(".png") {
-        "image/png"
-    } else if file_path.to_lowercase().ends_with(".jpg")
-        || file_path.to_lowercase().ends_with(".jpeg")
-    {
-        "image/jpeg"
-    } else if file_path.to_lowercase().ends_with(".gif") {
-        "image/gif"
-    } else if file_path.to_lowercase().ends_with(".webp") {
-        "image/webp"
-    } else if file_path.to_lowercase().ends_with(".mp4") {
-        "video/mp4"
-    } else if file_path.to_lowercase().ends_with(".webm") {
-        "video/webm"
-    } else {
-        "application/octet-stream"
-    };
+    let mime_type = mime_type_for_path(&file_path);
 
     let base64_data = general_purpose::STANDARD.encode(&bytes);
 
@@ -177,7 +160,7 @@ pub async fn save_uploaded_file(
     app_handle: tauri::AppHandle,
     filename: String,
     data: String,
-) -> Result<String, String> {
+) -> Result<String, CommandError> {
     let base64_data = if data.starts_with("data:") {
         data.split(',').nth(1).ok_or("Invalid data URL format")?
     } else {
@@ -0,0 +1,371 @@
+use std::fs;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use futures_util::future::{self, Either};
+use futures_util::{TryStream, TryStreamExt};
+use reqwest::{Body, Certificate, NoProxy, Proxy, RequestBuilder, Response};
+use tauri::Manager;
+
+use crate::error::CommandError;
+use crate::settings::AppSettings;
+
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 15;
+const DEFAULT_READ_TIMEOUT_SECS: u64 = 300;
+
+/// Local model servers (Ollama, LM Studio) must stay reachable behind a proxy.
+const ALWAYS_NO_PROXY: &str = "localhost,127.0.0.1,::1";
+
+/// The HTTP client every backend request goes through, built from the network
+/// settings. Cloning is cheap and shares the connection pool.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    read_timeout: Duration,
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        HttpClient {
+            client: base_builder(DEFAULT_CONNECT_TIMEOUT_SECS)
+                .build()
+                .unwrap_or_default(),
+            read_timeout: Duration::from_secs(DEFAULT_READ_TIMEOUT_SECS),
+        }
+    }
+}
+
+fn base_builder(connect_timeout_secs: u64) -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(connect_timeout_secs))
+        .pool_idle_timeout(Duration::from_secs(90))
+        .tcp_keepalive(Duration::from_secs(60))
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+impl HttpClient {
+    pub fn from_settings(settings: &AppSettings) -> Result<Self, CommandError> {
+        let mut builder = base_builder(
+            settings
+                .http_connect_timeout_secs
+                .unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS),
+        );
+
+        if let Some(proxy_url) = non_empty(&settings.http_proxy) {
+            let mut proxy =
+                Proxy::all(proxy_url).map_err(|e| format!("Invalid proxy URL: {}", e))?;
+            if let Some(username) = non_empty(&settings.http_proxy_username) {
+                proxy = proxy.basic_auth(
+                    username,
+                    settings.http_proxy_password.as_deref().unwrap_or_default(),
+                );
+            }
+            let no_proxy = match non_empty(&settings.http_no_proxy) {
+                Some(hosts) => format!("{},{}", ALWAYS_NO_PROXY, hosts),
+                None => ALWAYS_NO_PROXY.to_string(),
+            };
+            builder = builder.proxy(proxy.no_proxy(NoProxy::from_string(&no_proxy)));
+        }
+
+        if let Some(path) = non_empty(&settings.http_ca_bundle_path) {
+            let pem =
+                fs::read(path).map_err(|e| format!("Failed to read CA bundle {}: {}", path, e))?;
+            let certificates = Certificate::from_pem_bundle(&pem)
+                .map_err(|e| format!("Invalid CA bundle {}: {}", path, e))?;
+            if certificates.is_empty() {
+                return Err(format!("No certificates found in CA bundle {}", path).into());
+            }
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        Ok(HttpClient {
+            client: builder
+                .build()
+                .map_err(|e| format!("Failed to create HTTP client: {}", e))?,
+            read_timeout: Duration::from_secs(
+                settings
+                    .http_read_timeout_secs
+                    .unwrap_or(DEFAULT_READ_TIMEOUT_SECS),
+            ),
+        })
+    }
+
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// Bounds a request whose response is read in one piece. Streamed responses are
+    /// bounded per chunk by `next_chunk` instead, so long generations are not cut off.
+    pub fn with_timeout(&self, request: RequestBuilder) -> RequestBuilder {
+        request.timeout(self.read_timeout)
+    }
+
+    /// Waits for the response to `send`, failing when no headers arrive within the
+    /// read timeout. Streamed requests carry no whole-request timeout and
+    /// `next_chunk` only starts once headers are in, so this is what bounds a
+    /// server that accepts the connection and then stalls.
+    pub async fn await_response<T, E>(
+        &self,
+        provider: Option<&str>,
+        action: &str,
+        send: impl Future<Output = Result<T, E>>,
+        on_error: impl FnOnce(E) -> CommandError,
+    ) -> Result<T, CommandError> {
+        match tokio::time::timeout(self.read_timeout, send).await {
+            Ok(result) => result.map_err(on_error),
+            Err(_) => Err(CommandError::Network {
+                provider: provider.map(str::to_string),
+                timeout: true,
+                message: format!(
+                    "Failed to {}: no response within {}s",
+                    action,
+                    self.read_timeout.as_secs()
+                ),
+            }),
+        }
+    }
+
+    /// The next body chunk, failing when nothing arrives within the read timeout.
+    pub async fn next_chunk(&self, response: &mut Response) -> Result<Option<Bytes>, CommandError> {
+        match tokio::time::timeout(self.read_timeout, response.chunk()).await {
+            Ok(chunk) => chunk.map_err(|e| CommandError::Network {
+                provider: None,
+                timeout: e.is_timeout(),
+                message: format!("Failed to read stream: {}", e),
+            }),
+            Err(_) => Err(CommandError::Network {
+                provider: None,
+                timeout: true,
+                message: format!(
+                    "Failed to read stream: no data received for {}s",
+                    self.read_timeout.as_secs()
+                ),
+            }),
+        }
+    }
+
+    /// Wraps an upload body so `UploadWatch::until_stalled` can tell when it stops
+    /// being sent. Uploads are bounded this way rather than by `with_timeout`, so a
+    /// large file on a slow link is not cut off while it is still moving.
+    pub fn watch_upload<S>(&self, stream: S) -> (Body, UploadWatch)
+    where
+        S: TryStream + Send + Sync + 'static,
+        S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+        Bytes: From<S::Ok>,
+    {
+        let watch = UploadWatch {
+            last_progress: Arc::new(Mutex::new(Instant::now())),
+            timeout: self.read_timeout,
+        };
+        let progress = watch.clone();
+        let body = Body::wrap_stream(stream.inspect_ok(move |_| progress.touch()));
+        (body, watch)
+    }
+
+    /// Reads a whole body through `next_chunk`, for downloads of unknown size.
+    pub async fn read_body(&self, mut response: Response) -> Result<Vec<u8>, CommandError> {
+        let mut body = Vec::new();
+        while let Some(chunk) = self.next_chunk(&mut response).await? {
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+}
+
+/// Tracks when an upload body last handed a chunk to the connection.
+#[derive(Debug, Clone)]
+pub struct UploadWatch {
+    last_progress: Arc<Mutex<Instant>>,
+    timeout: Duration,
+}
+
+impl UploadWatch {
+    fn touch(&self) {
+        *self.last_progress.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    fn idle(&self) -> Duration {
+        self.last_progress
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .elapsed()
+    }
+
+    /// Runs the request carrying the watched body, failing when no chunk has been
+    /// sent, or after the last one no response has arrived, for the read timeout.
+    pub async fn until_stalled<T>(
+        self,
+        send: impl Future<Output = Result<T, CommandError>>,
+    ) -> Result<T, CommandError> {
+        self.touch();
+        let stalled = async {
+            loop {
+                let idle = self.idle();
+                if idle >= self.timeout {
+                    return;
+                }
+                tokio::time::sleep(self.timeout - idle).await;
+            }
+        };
+
+        match future::select(pin!(send), pin!(stalled)).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(CommandError::Network {
+                provider: None,
+                timeout: true,
+                message: format!(
+                    "Upload stalled: no progress for {}s",
+                    self.timeout.as_secs()
+                ),
+            }),
+        }
+    }
+}
+
+/// Managed state holding the current client; replaced when network settings change.
+#[derive(Debug, Default)]
+pub struct HttpState {
+    client: RwLock<HttpClient>,
+    /// Why the saved network settings could not be applied at startup. Cleared
+    /// once settings that produce a client are saved.
+    config_error: RwLock<Option<String>>,
+}
+
+impl HttpState {
+    pub fn new(client: HttpClient) -> Self {
+        HttpState {
+            client: RwLock::new(client),
+            config_error: RwLock::default(),
+        }
+    }
+
+    /// Falls back to the default client so the app still starts, and keeps the
+    /// reason for the frontend to show.
+    pub fn unconfigured(error: String) -> Self {
+        HttpState {
+            client: RwLock::default(),
+            config_error: RwLock::new(Some(error)),
+        }
+    }
+}
+
+/// The app's shared client, or a default one if the state is not managed yet.
+pub fn shared_client(app_handle: &tauri::AppHandle) -> HttpClient {
+    app_handle
+        .try_state::<HttpState>()
+        .map(|state| {
+            state
+                .client
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone()
+        })
+        .unwrap_or_default()
+}
+
+/// Replaces the shared client, e.g. after the network settings were saved.
+pub fn install(app_handle: &tauri::AppHandle, client: HttpClient) {
+    if let Some(state) = app_handle.try_state::<HttpState>() {
+        *state.client.write().unwrap_or_else(|e| e.into_inner()) = client;
+        *state
+            .config_error
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+/// Why requests are using the default client instead of the saved network
+/// settings, if they are.
+#[tauri::command]
+pub fn network_config_error(app_handle: tauri::AppHandle) -> Option<String> {
+    app_handle.try_state::<HttpState>().and_then(|state| {
+        state
+            .config_error
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::default_app_settings;
+
+    #[test]
+    fn builds_with_proxy_and_timeouts() {
+        let mut settings = default_app_settings();
+        settings.http_proxy = Some("http://proxy.corp.example:8080".to_string());
+        settings.http_proxy_username = Some("jdoe".to_string());
+        settings.http_proxy_password = Some("secret".to_string());
+        settings.http_no_proxy = Some("*.internal.example".to_string());
+        settings.http_read_timeout_secs = Some(42);
+
+        let client = HttpClient::from_settings(&settings).unwrap();
+        assert_eq!(client.read_timeout, Duration::from_secs(42));
+    }
+
+    #[test]
+    fn uploads_fail_once_the_body_stops_moving() {
+        let client = HttpClient {
+            read_timeout: Duration::from_millis(20),
+            ..HttpClient::default()
+        };
+        let chunks = futures_util::stream::iter(vec![Ok::<_, std::io::Error>(Bytes::new())]);
+        let (_body, watch) = client.watch_upload(chunks);
+
+        let error = tauri::async_runtime::block_on(
+            watch.until_stalled(std::future::pending::<Result<(), CommandError>>()),
+        )
+        .unwrap_err();
+        assert_eq!(error.code(), crate::error::ErrorCode::Timeout);
+        assert!(error.to_string().starts_with("Upload stalled"));
+    }
+
+    #[test]
+    fn waiting_for_response_headers_is_bounded() {
+        let client = HttpClient {
+            read_timeout: Duration::from_millis(20),
+            ..HttpClient::default()
+        };
+
+        let error = tauri::async_runtime::block_on(client.await_response(
+            Some("Anthropic"),
+            "stream messages",
+            std::future::pending::<Result<(), CommandError>>(),
+            |e| e,
+        ))
+        .unwrap_err();
+        assert_eq!(error.code(), crate::error::ErrorCode::Timeout);
+        assert_eq!(error.provider(), Some("Anthropic"));
+        assert!(error.retryable());
+    }
+
+    #[test]
+    fn rejects_invalid_proxy_and_missing_ca_bundle() {
+        let mut settings = default_app_settings();
+        settings.http_proxy = Some("not a url".to_string());
+        assert!(HttpClient::from_settings(&settings)
+            .unwrap_err()
+            .to_string()
+            .starts_with("Invalid proxy URL"));
+
+        let mut settings = default_app_settings();
+        settings.http_ca_bundle_path = Some("/nonexistent/ca.pem".to_string());
+        assert!(HttpClient::from_settings(&settings)
+            .unwrap_err()
+            .to_string()
+            .starts_with("Failed to read CA bundle"));
+    }
+}
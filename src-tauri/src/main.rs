@@ -2,7 +2,6 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use chrono::Utc;
-use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::sync::{Arc, Mutex};
@@ -10,86 +9,17 @@ use std::thread;
 use std::time::Duration;
 use tauri::{generate_context, generate_handler, Builder, Emitter, Manager, State};
 
+mod cancel;
+mod error;
 mod file_commands;
+mod http;
 mod path_utils;
+mod providers;
 mod settings;
 mod updates;
 
+use error::CommandError;
 use path_utils::sanitize_workflow_id;
-use settings::load_settings;
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct AnthropicRequest {
-    model: String,
-    messages: Vec<Message>,
-    max_tokens: Option<i32>,
-    temperature: Option<f32>,
-    system: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct Message {
-    role: String,
-    content: String,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct AnthropicResponse {
-    content: Vec<Content>,
-    id: String,
-    model: String,
-    role: String,
-    stop_reason: Option<String>,
-    stop_sequence: Option<String>,
-    usage: Usage,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct Content {
-    text: String,
-    #[serde(rename = "type")]
-    content_type: String,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct Usage {
-    input_tokens: i32,
-    output_tokens: i32,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct OpenAIChatMessage {
-    role: String,
-    content: String,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct OpenAIChatRequest {
-    model: String,
-    messages: Vec<OpenAIChatMessage>,
-    temperature: f32,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct OpenAIChatChoice {
-    message: OpenAIChatMessage,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct OpenAIChatResponse {
-    choices: Vec<OpenAIChatChoice>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct OpenAIModel {
-    id: String,
-    owned_by: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct OpenAIModelsResponse {
-    data: Vec<OpenAIModel>,
-}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct Workflow {
@@ -120,55 +50,6 @@ struct WhatsAppReceivedMessage {
     timestamp: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct ReplicatePrediction {
-    id: String,
-    status: String,
-    output: Option<serde_json::Value>,
-    error: Option<String>,
-    logs: Option<String>,
-    #[serde(default)]
-    metrics: Option<serde_json::Value>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct ReplicateModel {
-    owner: String,
-    name: String,
-    description: Option<String>,
-    visibility: String,
-    github_url: Option<String>,
-    paper_url: Option<String>,
-    license_url: Option<String>,
-    run_count: i64,
-    cover_image_url: Option<String>,
-    default_example: Option<serde_json::Value>,
-    latest_version: Option<serde_json::Value>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct ReplicateModelsResponse {
-    next: Option<String>,
-    previous: Option<String>,
-    results: Vec<ReplicateModel>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct ReplicateFileUpload {
-    id: String,
-    name: String,
-    content_type: String,
-    size: i64,
-    urls: ReplicateFileUrls,
-    created_at: String,
-    expires_at: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct ReplicateFileUrls {
-    get: String,
-}
-
 #[derive(Debug, Clone)]
 struct WhatsAppState(Arc<Mutex<WhatsAppStatus>>);
 
@@ -182,167 +63,12 @@ fn mask_phone_number(input: &str) -> String {
     }
 }
 
-#[tauri::command]
-async fn anthropic_request(
-    api_key: String,
-    model: String,
-    system_prompt: String,
-    user_content: String,
-    temperature: f32,
-) -> Result<String, String> {
-    let client = reqwest::Client::new();
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "x-api-key",
-        HeaderValue::from_str(&api_key).map_err(|e| e.to_string())?,
-    );
-    headers.insert("anthropic-version", HeaderValue::from_static("2023-06-01"));
-    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-
-    let messages = vec![Message {
-        role: "user".to_string(),
-        content: user_content,
-    }];
-
-    let request_body = AnthropicRequest {
-        model,
-        messages,
-        max_tokens: Some(1024),
-        temperature: Some(temperature),
-        system: Some(system_prompt),
-    };
-
-    let response = client
-        .post("https://api.anthropic.com/v1/messages")
-        .headers(headers)
-        .json(&request_body)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if !response.status().is_success() {
-        let error_text = response.text().await.map_err(|e| e.to_string())?;
-        return Err(format!("API request failed: {}", error_text));
-    }
-
-    let response_data: AnthropicResponse = response.json().await.map_err(|e| e.to_string())?;
-
-    // Return the first text content
-    if let Some(content) = response_data.content.first() {
-        Ok(content.text.clone())
-    } else {
-        Err("No content in response".to_string())
-    }
-}
-
-#[tauri::command]
-async fn openai_list_models(app_handle: tauri::AppHandle) -> Result<Vec<OpenAIModel>, String> {
-    let settings = load_settings(app_handle).await?;
-    let api_key = settings
-        .openai_api_key
-        .ok_or("OpenAI API key not configured. Please add it in Settings.")?;
-
-    let client = reqwest::Client::new();
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "Authorization",
-        HeaderValue::from_str(&format!("Bearer {}", api_key)).map_err(|e| e.to_string())?,
-    );
-
-    let response = client
-        .get("https://api.openai.com/v1/models")
-        .headers(headers)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch models: {}", e))?;
-
-    if !response.status().is_success() {
-        let error_text = response.text().await.map_err(|e| e.to_string())?;
-        return Err(format!("OpenAI API error: {}", error_text));
-    }
-
-    let models: OpenAIModelsResponse = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse models response: {}", e))?;
-
-    Ok(models.data)
-}
-
-#[tauri::command]
-async fn openai_chat_completion(
-    app_handle: tauri::AppHandle,
-    model: String,
-    system_prompt: String,
-    user_content: String,
-    temperature: Option<f32>,
-) -> Result<String, String> {
-    let settings = load_settings(app_handle).await?;
-    let api_key = settings
-        .openai_api_key
-        .ok_or("OpenAI API key not configured. Please add it in Settings.")?;
-
-    let client = reqwest::Client::new();
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "Authorization",
-        HeaderValue::from_str(&format!("Bearer {}", api_key)).map_err(|e| e.to_string())?,
-    );
-    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-
-    let prompt = if system_prompt.trim().is_empty() {
-        "You are a helpful assistant".to_string()
-    } else {
-        system_prompt
-    };
-
-    let request_body = OpenAIChatRequest {
-        model,
-        messages: vec![
-            OpenAIChatMessage {
-                role: "system".to_string(),
-                content: prompt,
-            },
-            OpenAIChatMessage {
-                role: "user".to_string(),
-                content: user_content,
-            },
-        ],
-        temperature: temperature.unwrap_or(0.7),
-    };
-
-    let response = client
-        .post("https://api.openai.com/v1/chat/completions")
-        .headers(headers)
-        .json(&request_body)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to create completion: {}", e))?;
-
-    if !response.status().is_success() {
-        let error_text = response.text().await.map_err(|e| e.to_string())?;
-        return Err(format!("OpenAI API error: {}", error_text));
-    }
-
-    let response_data: OpenAIChatResponse = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse OpenAI response: {}", e))?;
-
-    response_data
-        .choices
-        .first()
-        .map(|choice| choice.message.content.clone())
-        .ok_or("No content in OpenAI response".to_string())
-}
-
 #[tauri::command]
 fn save_workflow(
     app_handle: tauri::AppHandle,
     name: String,
     data: serde_json::Value,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let app_data = app_handle
         .path()
         .app_data_dir()
@@ -355,7 +81,9 @@ fn save_workflow(
 
     let trimmed_name = name.trim();
     if trimmed_name.is_empty() {
-        return Err("Workflow name cannot be empty".to_string());
+        return Err(CommandError::invalid_request(
+            "Workflow name cannot be empty",
+        ));
     }
 
     let safe_id = sanitize_workflow_id(trimmed_name);
@@ -371,7 +99,7 @@ fn save_workflow(
 }
 
 #[tauri::command]
-fn list_workflows(app_handle: tauri::AppHandle) -> Result<Vec<Workflow>, String> {
+fn list_workflows(app_handle: tauri::AppHandle) -> Result<Vec<Workflow>, CommandError> {
     let app_data = app_handle
         .path()
         .app_data_dir()
@@ -395,7 +123,7 @@ fn list_workflows(app_handle: tauri::AppHandle) -> Result<Vec<Workflow>, String>
 }
 
 #[tauri::command]
-fn load_workflow(app_handle: tauri::AppHandle, id: String) -> Result<Workflow, String> {
+fn load_workflow(app_handle: tauri::AppHandle, id: String) -> Result<Workflow, CommandError> {
     let app_data = app_handle
         .path()
         .app_data_dir()
@@ -412,7 +140,7 @@ fn rename_workflow(
     app_handle: tauri::AppHandle,
     id: String,
     new_name: String,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let app_data = app_handle
         .path()
         .app_data_dir()
@@ -421,7 +149,9 @@ fn rename_workflow(
     let safe_old_id = sanitize_workflow_id(id.trim());
     let trimmed_name = new_name.trim();
     if trimmed_name.is_empty() {
-        return Err("Workflow name cannot be empty".to_string());
+        return Err(CommandError::invalid_request(
+            "Workflow name cannot be empty",
+        ));
     }
     let safe_new_id = sanitize_workflow_id(trimmed_name);
     let old_path = workflows_dir.join(format!("{}.json", safe_old_id));
@@ -439,7 +169,7 @@ fn rename_workflow(
 }
 
 #[tauri::command]
-fn delete_workflow(app_handle: tauri::AppHandle, id: String) -> Result<(), String> {
+fn delete_workflow(app_handle: tauri::AppHandle, id: String) -> Result<(), CommandError> {
     let app_data = app_handle
         .path()
         .app_data_dir()
@@ -451,7 +181,7 @@ fn delete_workflow(app_handle: tauri::AppHandle, id: String) -> Result<(), Strin
 }
 
 #[tauri::command]
-fn create_workflow(app_handle: tauri::AppHandle) -> Result<Workflow, String> {
+fn create_workflow(app_handle: tauri::AppHandle) -> Result<Workflow, CommandError> {
     let app_data = app_handle
         .path()
         .app_data_dir()
@@ -493,7 +223,7 @@ async fn send_whatsapp_message(
     message: String,
     app_handle: tauri::AppHandle,
     state: State<'_, WhatsAppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     // Check if WhatsApp is connected
     let whatsapp_status = state
         .0
@@ -507,7 +237,7 @@ async fn send_whatsapp_message(
             whatsapp_status.status
         );
         println!("{}", err);
-        return Err(err);
+        return Err(err.into());
     }
 
     // Format phone number (remove any non-numeric characters)
@@ -628,7 +358,7 @@ async fn send_whatsapp_message(
             })?;
             println!("Error from WhatsApp service: {}", error);
             fs::remove_file(&error_path).ok();
-            return Err(error);
+            return Err(error.into());
         }
     }
 
@@ -647,7 +377,7 @@ async fn send_whatsapp_message(
             }
         }
         fs::remove_file(&message_path).ok();
-        return Err("Timeout while sending message".to_string());
+        return Err("Timeout while sending message".into());
     }
 
     println!("Message sent successfully");
@@ -658,7 +388,7 @@ async fn send_whatsapp_message(
 async fn get_whatsapp_status(
     app: tauri::AppHandle,
     state: State<'_, WhatsAppState>,
-) -> Result<WhatsAppStatus, String> {
+) -> Result<WhatsAppStatus, CommandError> {
     let app_data = app
         .path()
         .app_data_dir()
@@ -703,7 +433,7 @@ async fn get_whatsapp_status(
 async fn init_whatsapp(
     app_handle: tauri::AppHandle,
     state: State<'_, WhatsAppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let app_data = app_handle
         .path()
         .app_data_dir()
@@ -785,7 +515,7 @@ async fn listen_whatsapp_messages(
     command: String,
     app_handle: tauri::AppHandle,
     _state: State<'_, WhatsAppState>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let data_dir = app_handle
         .path()
         .app_data_dir()
@@ -845,7 +575,10 @@ async fn listen_whatsapp_messages(
 }
 
 #[tauri::command]
-async fn stop_whatsapp_listener(id: String, app_handle: tauri::AppHandle) -> Result<(), String> {
+async fn stop_whatsapp_listener(
+    id: String,
+    app_handle: tauri::AppHandle,
+) -> Result<(), CommandError> {
     let data_dir = app_handle
         .path()
         .app_data_dir()
@@ -860,452 +593,6 @@ async fn stop_whatsapp_listener(id: String, app_handle: tauri::AppHandle) -> Res
     Ok(())
 }
 
-#[tauri::command]
-async fn replicate_create_prediction(
-    app_handle: tauri::AppHandle,
-    model: String,
-    input: serde_json::Value,
-) -> Result<ReplicatePrediction, String> {
-    // Load settings to get API key
-    let settings = load_settings(app_handle).await?;
-    let api_key = settings
-        .replicate_api_key
-        .ok_or("Replicate API key not configured. Please add it in Settings.")?;
-
-    let client = reqwest::Client::new();
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "Authorization",
-        HeaderValue::from_str(&format!("Bearer {}", api_key)).map_err(|e| e.to_string())?,
-    );
-    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-
-    // Parse model string to determine endpoint
-    // Format can be: "owner/model", "owner/model:version", or just "version_id"
-    let endpoint = if model.contains('/') {
-        // owner/model or owner/model:version format
-        let parts: Vec<&str> = model.splitn(2, ':').collect();
-        let model_path = parts[0];
-
-        if parts.len() == 2 {
-            // Has version specified, use general predictions endpoint
-            format!("https://api.replicate.com/v1/predictions")
-        } else {
-            // No version, use model-specific endpoint (official models only)
-            format!(
-                "https://api.replicate.com/v1/models/{}/predictions",
-                model_path
-            )
-        }
-    } else {
-        // Just a version ID, use general endpoint
-        format!("https://api.replicate.com/v1/predictions")
-    };
-
-    // Build request body
-    let request_body = if model.contains(':') || !model.contains('/') {
-        // Include version in body
-        serde_json::json!({
-            "version": model,
-            "input": input
-        })
-    } else {
-        // No version, just input (for official models)
-        serde_json::json!({
-            "input": input
-        })
-    };
-
-    println!("Creating prediction at: {}", endpoint);
-    if cfg!(debug_assertions) {
-        println!("Request body prepared for model: {}", model);
-    }
-
-    let response = client
-        .post(&endpoint)
-        .headers(headers)
-        .json(&request_body)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to create prediction: {}", e))?;
-
-    let status = response.status();
-    let response_text = response
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
-
-    if !status.is_success() {
-        return Err(format!(
-            "Replicate API error ({}): {}",
-            status, response_text
-        ));
-    }
-
-    let prediction: ReplicatePrediction = serde_json::from_str(&response_text).map_err(|e| {
-        format!(
-            "Failed to parse prediction response: {} - Response: {}",
-            e, response_text
-        )
-    })?;
-
-    Ok(prediction)
-}
-
-#[tauri::command]
-async fn replicate_get_prediction(
-    app_handle: tauri::AppHandle,
-    prediction_id: String,
-) -> Result<ReplicatePrediction, String> {
-    // Load settings to get API key
-    let settings = load_settings(app_handle).await?;
-    let api_key = settings
-        .replicate_api_key
-        .ok_or("Replicate API key not configured")?;
-
-    let client = reqwest::Client::new();
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "Authorization",
-        HeaderValue::from_str(&format!("Bearer {}", api_key)).map_err(|e| e.to_string())?,
-    );
-
-    let url = format!("https://api.replicate.com/v1/predictions/{}", prediction_id);
-
-    let response = client
-        .get(&url)
-        .headers(headers)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to get prediction: {}", e))?;
-
-    if !response.status().is_success() {
-        let error_text = response.text().await.map_err(|e| e.to_string())?;
-        return Err(format!("Replicate API error: {}", error_text));
-    }
-
-    let prediction: ReplicatePrediction = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse prediction: {}", e))?;
-
-    Ok(prediction)
-}
-
-#[tauri::command]
-async fn replicate_cancel_prediction(
-    app_handle: tauri::AppHandle,
-    prediction_id: String,
-) -> Result<ReplicatePrediction, String> {
-    // Load settings to get API key
-    let settings = load_settings(app_handle).await?;
-    let api_key = settings
-        .replicate_api_key
-        .ok_or("Replicate API key not configured")?;
-
-    let client = reqwest::Client::new();
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "Authorization",
-        HeaderValue::from_str(&format!("Bearer {}", api_key)).map_err(|e| e.to_string())?,
-    );
-
-    let url = format!(
-        "https://api.replicate.com/v1/predictions/{}/cancel",
-        prediction_id
-    );
-
-    let response = client
-        .post(&url)
-        .headers(headers)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to cancel prediction: {}", e))?;
-
-    if !response.status().is_success() {
-        let error_text = response.text().await.map_err(|e| e.to_string())?;
-        return Err(format!("Replicate API error: {}", error_text));
-    }
-
-    let prediction: ReplicatePrediction = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse prediction: {}", e))?;
-
-    Ok(prediction)
-}
-
-#[tauri::command]
-async fn replicate_get_model(
-    app_handle: tauri::AppHandle,
-    owner: String,
-    model_name: String,
-) -> Result<ReplicateModel, String> {
-    // Load settings to get API key
-    let settings = load_settings(app_handle).await?;
-    let api_key = settings
-        .replicate_api_key
-        .ok_or("Replicate API key not configured. Please add it in Settings.")?;
-
-    let client = reqwest::Client::new();
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "Authorization",
-        HeaderValue::from_str(&format!("Bearer {}", api_key)).map_err(|e| e.to_string())?,
-    );
-
-    let url = format!(
-        "https://api.replicate.com/v1/models/{}/{}",
-        owner, model_name
-    );
-
-    let response = client
-        .get(&url)
-        .headers(headers)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to get model: {}", e))?;
-
-    if !response.status().is_success() {
-        let error_text = response.text().await.map_err(|e| e.to_string())?;
-        return Err(format!("Replicate API error: {}", error_text));
-    }
-
-    let model: ReplicateModel = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse model: {}", e))?;
-
-    Ok(model)
-}
-
-#[tauri::command]
-async fn replicate_list_models(
-    app_handle: tauri::AppHandle,
-    collection_slug: Option<String>,
-) -> Result<ReplicateModelsResponse, String> {
-    // Load settings to get API key
-    let settings = load_settings(app_handle).await?;
-    let api_key = settings
-        .replicate_api_key
-        .ok_or("Replicate API key not configured. Please add it in Settings.")?;
-
-    let client = reqwest::Client::new();
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "Authorization",
-        HeaderValue::from_str(&format!("Bearer {}", api_key)).map_err(|e| e.to_string())?,
-    );
-
-    // Use collection endpoint if collection_slug is provided, otherwise use general models endpoint
-    if let Some(slug) = collection_slug {
-        // Collection endpoint - returns all models in one response
-        let url = format!("https://api.replicate.com/v1/collections/{}", slug);
-
-        let response = client
-            .get(&url)
-            .headers(headers)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to list models: {}", e))?;
-
-        if !response.status().is_success() {
-            let error_text = response.text().await.map_err(|e| e.to_string())?;
-            return Err(format!("Replicate API error: {}", error_text));
-        }
-
-        let response_text = response.text().await.map_err(|e| e.to_string())?;
-
-        // Collection endpoint returns { models: [...] }
-        let collection_data: serde_json::Value = serde_json::from_str(&response_text)
-            .map_err(|e| format!("Failed to parse collection response: {}", e))?;
-
-        let models = collection_data["models"]
-            .as_array()
-            .ok_or("Collection response missing models array")?;
-
-        let parsed_models: Vec<ReplicateModel> = models
-            .iter()
-            .filter_map(|m| serde_json::from_value(m.clone()).ok())
-            .collect();
-
-        Ok(ReplicateModelsResponse {
-            next: None,
-            previous: None,
-            results: parsed_models,
-        })
-    } else {
-        // General models endpoint - paginated, fetch multiple pages
-        let mut all_models: Vec<ReplicateModel> = Vec::new();
-        let mut next_url: Option<String> = Some("https://api.replicate.com/v1/models".to_string());
-        let max_pages = 20; // Limit to ~2000 models to avoid timeout
-        let mut page_count = 0;
-
-        while let Some(url) = next_url {
-            if page_count >= max_pages {
-                break;
-            }
-
-            let response = client
-                .get(&url)
-                .headers(headers.clone())
-                .send()
-                .await
-                .map_err(|e| format!("Failed to list models: {}", e))?;
-
-            if !response.status().is_success() {
-                let error_text = response.text().await.map_err(|e| e.to_string())?;
-                return Err(format!("Replicate API error: {}", error_text));
-            }
-
-            let response_text = response.text().await.map_err(|e| e.to_string())?;
-
-            let page_response: ReplicateModelsResponse = serde_json::from_str(&response_text)
-                .map_err(|e| format!("Failed to parse models response: {}", e))?;
-
-            all_models.extend(page_response.results);
-            next_url = page_response.next;
-            page_count += 1;
-        }
-
-        Ok(ReplicateModelsResponse {
-            next: None,
-            previous: None,
-            results: all_models,
-        })
-    }
-}
-
-#[tauri::command]
-async fn replicate_upload_file(
-    app_handle: tauri::AppHandle,
-    file_path: String,
-    filename: String,
-    content_type: String,
-) -> Result<ReplicateFileUpload, String> {
-    // Load settings to get API key
-    let settings = load_settings(app_handle).await?;
-    let api_key = settings
-        .replicate_api_key
-        .ok_or("Replicate API key not configured")?;
-
-    // Read the file
-    let file_bytes = fs::read(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
-
-    // Create multipart form
-    let client = reqwest::Client::new();
-
-    // Build the multipart form manually with proper boundaries
-    let boundary = format!("----WebKitFormBoundary{}", chrono::Utc::now().timestamp());
-
-    let mut body = Vec::new();
-
-    // Add content field
-    body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
-    body.extend_from_slice(
-        format!(
-            "Content-Disposition: form-data; name=\"content\"; filename=\"{}\"\r\n",
-            filename
-        )
-        .as_bytes(),
-    );
-    body.extend_from_slice(format!("Content-Type: {}\r\n\r\n", content_type).as_bytes());
-    body.extend_from_slice(&file_bytes);
-    body.extend_from_slice(b"\r\n");
-
-    // Add metadata field (empty object)
-    body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
-    body.extend_from_slice(b"Content-Disposition: form-data; name=\"metadata\"\r\n");
-    body.extend_from_slice(b"Content-Type: application/json\r\n\r\n");
-    body.extend_from_slice(b"{}\r\n");
-
-    // Add final boundary
-    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "Authorization",
-        HeaderValue::from_str(&format!("Bearer {}", api_key)).map_err(|e| e.to_string())?,
-    );
-    headers.insert(
-        CONTENT_TYPE,
-        HeaderValue::from_str(&format!("multipart/form-data; boundary={}", boundary))
-            .map_err(|e| e.to_string())?,
-    );
-
-    let response = client
-        .post("https://api.replicate.com/v1/files")
-        .headers(headers)
-        .body(body)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to upload file: {}", e))?;
-
-    let status = response.status();
-    let response_text = response
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
-
-    if !status.is_success() {
-        return Err(format!(
-            "Replicate API error ({}): {}",
-            status, response_text
-        ));
-    }
-
-    let file_upload: ReplicateFileUpload = serde_json::from_str(&response_text).map_err(|e| {
-        format!(
-            "Failed to parse file upload response: {} - Response: {}",
-            e, response_text
-        )
-    })?;
-
-    if cfg!(debug_assertions) {
-        println!("File uploaded successfully:");
-        println!("  ID: {}", file_upload.id);
-        println!("  URL (urls.get): {}", file_upload.urls.get);
-        println!("  Name: {}", file_upload.name);
-    }
-
-    Ok(file_upload)
-}
-
-#[tauri::command]
-async fn replicate_delete_file(
-    app_handle: tauri::AppHandle,
-    file_id: String,
-) -> Result<(), String> {
-    // Load settings to get API key
-    let settings = load_settings(app_handle).await?;
-    let api_key = settings
-        .replicate_api_key
-        .ok_or("Replicate API key not configured")?;
-
-    let client = reqwest::Client::new();
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "Authorization",
-        HeaderValue::from_str(&format!("Bearer {}", api_key)).map_err(|e| e.to_string())?,
-    );
-
-    let url = format!("https://api.replicate.com/v1/files/{}", file_id);
-
-    let response = client
-        .delete(&url)
-        .headers(headers)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to delete file: {}", e))?;
-
-    if !response.status().is_success() {
-        let error_text = response.text().await.map_err(|e| e.to_string())?;
-        return Err(format!("Replicate API error: {}", error_text));
-    }
-
-    Ok(())
-}
-
 fn main() {
     let whatsapp_status = WhatsAppStatus {
         status: "initializing".to_string(),
@@ -1327,6 +614,25 @@ fn main() {
         .setup(|app| {
             let handle = app.handle().clone();
 
+            // Build the shared HTTP client from the saved proxy and timeout settings
+            // before anything can send a request. Settings that cannot be loaded or no
+            // longer produce a client, such as a deleted CA bundle, fall back to the
+            // default client so Settings stays reachable; the frontend reads the
+            // reason through `network_config_error`.
+            let configured =
+                tauri::async_runtime::block_on(settings::load_settings(handle.clone()))
+                    .and_then(|settings| http::HttpClient::from_settings(&settings));
+            app.manage(match configured {
+                Ok(client) => http::HttpState::new(client),
+                Err(e) => {
+                    eprintln!("Failed to configure HTTP client: {}", e);
+                    http::HttpState::unconfigured(e.to_string())
+                }
+            });
+
+            // Delete Replicate uploads left behind by runs that never cleaned up
+            providers::replicate_files::start_cleanup(handle.clone());
+
             // Run init_whatsapp asynchronously
             tauri::async_runtime::spawn(async move {
                 let state = handle.state::<WhatsAppState>();
@@ -1338,9 +644,35 @@ fn main() {
             Ok(())
         })
         .invoke_handler(generate_handler![
-            anthropic_request,
-            openai_list_models,
-            openai_chat_completion,
+            providers::anthropic::anthropic_request,
+            providers::anthropic::anthropic_stream,
+            providers::fal::fal_queue_submit,
+            providers::fal::fal_queue_status,
+            providers::fal::fal_queue_result,
+            providers::fal::fal_queue_cancel,
+            providers::fal::fal_upload_file,
+            providers::gemini::gemini_list_models,
+            providers::gemini::gemini_generate_content,
+            providers::gemini::gemini_stream_generate_content,
+            providers::openai::openai_list_models,
+            providers::openai::openai_chat_completion,
+            providers::openai::openai_chat_stream,
+            providers::openrouter::openrouter_list_models,
+            providers::openrouter::openrouter_chat,
+            providers::ollama::ollama_list_models,
+            providers::ollama::ollama_chat,
+            providers::ollama::ollama_generate,
+            providers::ollama::ollama_pull_model,
+            providers::registry::list_providers,
+            providers::registry::generate,
+            providers::ledger::usage_summary,
+            providers::ledger::list_usage_entries,
+            providers::budget::override_budget,
+            providers::budget::clear_budget_override,
+            providers::cache::response_cache_stats,
+            providers::cache::list_response_cache_entries,
+            providers::cache::clear_response_cache,
+            cancel::cancel_request,
             save_workflow,
             list_workflows,
             load_workflow,
@@ -1354,13 +686,24 @@ fn main() {
             stop_whatsapp_listener,
             settings::save_settings,
             settings::load_settings,
-            replicate_create_prediction,
-            replicate_get_prediction,
-            replicate_cancel_prediction,
-            replicate_get_model,
-            replicate_list_models,
-            replicate_upload_file,
-            replicate_delete_file,
+            http::network_config_error,
+            providers::replicate::replicate_create_prediction,
+            providers::replicate::replicate_get_prediction,
+            providers::replicate::replicate_run_prediction,
+            providers::replicate::replicate_stream_prediction,
+            providers::replicate::replicate_cancel_prediction,
+            providers::replicate::replicate_get_model,
+            providers::replicate::replicate_list_models,
+            providers::replicate::replicate_list_deployments,
+            providers::replicate::replicate_get_deployment,
+            providers::replicate_schema::replicate_list_model_versions,
+            providers::replicate_schema::replicate_get_model_schema,
+            providers::replicate_catalog::replicate_search_models,
+            providers::replicate_catalog::replicate_sync_models,
+            providers::replicate_catalog::replicate_model_catalog_status,
+            providers::replicate::replicate_upload_file,
+            providers::replicate::replicate_delete_file,
+            providers::replicate_files::replicate_list_files,
             updates::fetch_github_release,
             updates::fetch_update_manifest,
             updates::download_update,
@@ -0,0 +1,537 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::attachments::{attach_to_conversation, ChatAttachment};
+use super::budget::ensure_within_budget;
+use super::cache::cached;
+use super::chat::{
+    build_conversation, split_alternating_conversation, ChatMessage, ContentBlock, MessageContent,
+};
+use super::ledger::{self, CallContext, LedgerEntry};
+use super::provider::{
+    AuthScheme, Capability, GenerateRequest, GenerateResponse, Provider, ProviderClient,
+    ProviderContext,
+};
+use super::stream::{emit_delta, finish_stream, ChatStreamResult, SseParser, TokenUsage};
+use crate::cancel::cancellable;
+use crate::error::CommandError;
+use crate::settings::{load_settings, AppSettings};
+
+const ANTHROPIC_MESSAGES_URL: &str = "https://api.anthropic.com/v1/messages";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Used when the caller does not pass `max_tokens`, which the Messages API requires.
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct AnthropicRequest {
+    model: String,
+    messages: Vec<Message>,
+    max_tokens: u32,
+    temperature: Option<f32>,
+    system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<AnthropicMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<AnthropicTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<AnthropicToolChoice>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Message {
+    role: String,
+    content: MessageContent,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnthropicTool {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    input_schema: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicToolChoice {
+    Auto,
+    Any,
+    Tool { name: String },
+    None,
+}
+
+/// Optional sampling and request parameters shared by `anthropic_request` and
+/// `anthropic_stream`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AnthropicOptions {
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(default)]
+    pub top_k: Option<u32>,
+    #[serde(default)]
+    pub stop_sequences: Option<Vec<String>>,
+    #[serde(default)]
+    pub metadata: Option<AnthropicMetadata>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnthropicMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_id: Option<String>,
+}
+
+/// `stop_reason` is `max_tokens` when the output was truncated.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnthropicMessageResult {
+    pub id: String,
+    pub model: String,
+    pub text: String,
+    pub content: Vec<ContentBlock>,
+    pub stop_reason: Option<String>,
+    pub stop_sequence: Option<String>,
+    pub usage: AnthropicUsage,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct AnthropicResponse {
+    content: Vec<ContentBlock>,
+    id: String,
+    model: String,
+    role: String,
+    stop_reason: Option<String>,
+    stop_sequence: Option<String>,
+    usage: AnthropicUsage,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct AnthropicUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_input_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_input_tokens: Option<u32>,
+}
+
+impl From<&AnthropicUsage> for TokenUsage {
+    fn from(usage: &AnthropicUsage) -> Self {
+        TokenUsage {
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicStreamEvent {
+    MessageStart {
+        message: AnthropicStreamMessage,
+    },
+    ContentBlockDelta {
+        delta: AnthropicContentDelta,
+    },
+    MessageDelta {
+        delta: AnthropicMessageDelta,
+        usage: Option<AnthropicDeltaUsage>,
+    },
+    Error,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicStreamMessage {
+    usage: Option<AnthropicDeltaUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicContentDelta {
+    TextDelta {
+        text: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicMessageDelta {
+    stop_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicDeltaUsage {
+    input_tokens: Option<u32>,
+    output_tokens: Option<u32>,
+}
+
+fn build_request(
+    model: String,
+    conversation: Vec<ChatMessage>,
+    temperature: f32,
+    options: AnthropicOptions,
+) -> Result<AnthropicRequest, CommandError> {
+    if options.max_tokens == Some(0) {
+        return Err(CommandError::invalid_request(
+            "max_tokens must be greater than zero",
+        ));
+    }
+
+    let (system, turns) = split_alternating_conversation("Anthropic", conversation)
+        .map_err(CommandError::invalid_request)?;
+
+    let messages = turns
+        .into_iter()
+        .map(|message| Message {
+            role: message.role.as_str().to_string(),
+            content: message.content,
+        })
+        .collect();
+
+    Ok(AnthropicRequest {
+        model,
+        messages,
+        max_tokens: options.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+        temperature: Some(temperature),
+        system,
+        top_p: options.top_p,
+        top_k: options.top_k,
+        stop_sequences: options.stop_sequences.filter(|stops| !stops.is_empty()),
+        metadata: options.metadata,
+        stream: None,
+        tools: None,
+        tool_choice: None,
+    })
+}
+
+fn response_text(content: &[ContentBlock]) -> String {
+    content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+pub struct AnthropicProvider;
+
+#[async_trait]
+impl Provider for AnthropicProvider {
+    fn id(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn name(&self) -> &'static str {
+        "Anthropic"
+    }
+
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::Text]
+    }
+
+    fn auth_scheme(&self) -> AuthScheme {
+        AuthScheme::Header("x-api-key")
+    }
+
+    fn extra_headers(&self) -> &'static [(&'static str, &'static str)] {
+        &[("anthropic-version", ANTHROPIC_VERSION)]
+    }
+
+    fn api_key(&self, settings: &AppSettings) -> Option<String> {
+        settings.anthropic_api_key.clone()
+    }
+
+    async fn generate(
+        &self,
+        context: &ProviderContext,
+        model: &str,
+        request: &GenerateRequest,
+    ) -> Result<GenerateResponse, CommandError> {
+        let conversation = request.conversation(context.client.http()).await?;
+        let request_body = build_request(
+            model.to_string(),
+            conversation,
+            request.temperature.unwrap_or(1.0),
+            AnthropicOptions::default(),
+        )?;
+
+        let response_data: AnthropicResponse = context
+            .client
+            .send_json(
+                context
+                    .client
+                    .post(ANTHROPIC_MESSAGES_URL)
+                    .json(&request_body),
+                "create message",
+            )
+            .await?;
+
+        Ok(GenerateResponse {
+            usage: Some(TokenUsage::from(&response_data.usage)),
+            stop_reason: response_data.stop_reason,
+            ..GenerateResponse::text(response_text(&response_data.content))
+        })
+    }
+}
+
+/// The client the Anthropic commands send through, authenticated with the key
+/// from Settings.
+async fn anthropic_client(
+    app_handle: &tauri::AppHandle,
+    request_id: Option<&str>,
+) -> Result<ProviderClient, CommandError> {
+    let settings = load_settings(app_handle.clone()).await?;
+    let context = ProviderContext::new(&AnthropicProvider, settings)?;
+    Ok(context.client.for_app(app_handle, request_id))
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn anthropic_request(
+    app_handle: tauri::AppHandle,
+    model: String,
+    system_prompt: Option<String>,
+    user_content: Option<String>,
+    temperature: f32,
+    messages: Option<Vec<ChatMessage>>,
+    tools: Option<Vec<AnthropicTool>>,
+    tool_choice: Option<AnthropicToolChoice>,
+    attachments: Option<Vec<ChatAttachment>>,
+    options: Option<AnthropicOptions>,
+    call_context: Option<CallContext>,
+    request_id: Option<String>,
+) -> Result<AnthropicMessageResult, CommandError> {
+    cancellable(request_id.as_deref(), async {
+        let client = anthropic_client(&app_handle, request_id.as_deref()).await?;
+        let mut conversation = build_conversation(system_prompt, messages, user_content);
+        attach_to_conversation(&mut conversation, attachments, client.http()).await?;
+
+        let mut request_body = build_request(
+            model,
+            conversation,
+            temperature,
+            options.unwrap_or_default(),
+        )?;
+        request_body.tools = tools.filter(|tools| !tools.is_empty());
+        request_body.tool_choice = tool_choice;
+
+        cached(
+            &app_handle,
+            "anthropic",
+            &request_body.model,
+            &request_body,
+            async {
+                ensure_within_budget(&app_handle, "anthropic", call_context.as_ref()).await?;
+                let response_data: AnthropicResponse = client
+                    .send_json(
+                        client.post(ANTHROPIC_MESSAGES_URL).json(&request_body),
+                        "send request",
+                    )
+                    .await?;
+
+                let content: Vec<ContentBlock> = response_data
+                    .content
+                    .into_iter()
+                    .filter(|block| !matches!(block, ContentBlock::Unsupported))
+                    .collect();
+
+                ledger::record(
+                    &app_handle,
+                    LedgerEntry::new("anthropic", &response_data.model, call_context.as_ref())
+                        .with_usage(Some(&TokenUsage::from(&response_data.usage))),
+                );
+
+                Ok(AnthropicMessageResult {
+                    id: response_data.id,
+                    model: response_data.model,
+                    text: response_text(&content),
+                    content,
+                    stop_reason: response_data.stop_reason,
+                    stop_sequence: response_data.stop_sequence,
+                    usage: response_data.usage,
+                })
+            },
+        )
+        .await
+    })
+    .await
+}
+
+/// Streams a message over SSE, emitting `chat-stream` deltas tagged with `request_id`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn anthropic_stream(
+    app_handle: tauri::AppHandle,
+    request_id: String,
+    model: String,
+    system_prompt: Option<String>,
+    user_content: Option<String>,
+    temperature: f32,
+    messages: Option<Vec<ChatMessage>>,
+    attachments: Option<Vec<ChatAttachment>>,
+    options: Option<AnthropicOptions>,
+    call_context: Option<CallContext>,
+) -> Result<ChatStreamResult, CommandError> {
+    let result = cancellable(Some(&request_id), async {
+        ensure_within_budget(&app_handle, "anthropic", call_context.as_ref()).await?;
+        let client = anthropic_client(&app_handle, Some(&request_id)).await?;
+        let mut conversation = build_conversation(system_prompt, messages, user_content);
+        attach_to_conversation(&mut conversation, attachments, client.http()).await?;
+        let mut request_body = build_request(
+            model,
+            conversation,
+            temperature,
+            options.unwrap_or_default(),
+        )?;
+        request_body.stream = Some(true);
+        let done = stream_messages(&app_handle, &client, &request_id, &request_body).await?;
+        ledger::record(
+            &app_handle,
+            LedgerEntry::new("anthropic", &request_body.model, call_context.as_ref())
+                .with_usage(done.usage.as_ref()),
+        );
+        Ok(done)
+    })
+    .await;
+    finish_stream(&app_handle, &request_id, result)
+}
+
+async fn stream_messages(
+    app_handle: &tauri::AppHandle,
+    client: &ProviderClient,
+    request_id: &str,
+    request_body: &AnthropicRequest,
+) -> Result<ChatStreamResult, CommandError> {
+    let mut response = client
+        .send(
+            client.post(ANTHROPIC_MESSAGES_URL).json(request_body),
+            "send request",
+        )
+        .await?;
+
+    let mut parser = SseParser::default();
+    let mut result = ChatStreamResult::default();
+    let mut usage: Option<TokenUsage> = None;
+
+    loop {
+        let chunk = client.http().next_chunk(&mut response).await?;
+        let events = match &chunk {
+            Some(bytes) => parser.push(bytes),
+            None => parser.finish().into_iter().collect(),
+        };
+
+        for event in events {
+            let parsed: AnthropicStreamEvent = serde_json::from_str(&event.data)
+                .map_err(|e| format!("Failed to parse stream event: {}", e))?;
+
+            match parsed {
+                AnthropicStreamEvent::MessageStart { message } => {
+                    if let Some(start_usage) = message.usage {
+                        usage = Some(TokenUsage {
+                            input_tokens: start_usage.input_tokens.unwrap_or(0),
+                            output_tokens: start_usage.output_tokens.unwrap_or(0),
+                        });
+                    }
+                }
+                AnthropicStreamEvent::ContentBlockDelta {
+                    delta: AnthropicContentDelta::TextDelta { text },
+                } => {
+                    emit_delta(app_handle, request_id, &text);
+                    result.text.push_str(&text);
+                }
+                AnthropicStreamEvent::MessageDelta {
+                    delta,
+                    usage: delta_usage,
+                } => {
+                    result.stop_reason = delta.stop_reason;
+                    // Input tokens are only reported by `message_start`; without them
+                    // the call is left unpriced.
+                    if let (Some(usage), Some(output_tokens)) =
+                        (usage.as_mut(), delta_usage.and_then(|u| u.output_tokens))
+                    {
+                        usage.output_tokens = output_tokens;
+                    }
+                }
+                AnthropicStreamEvent::Error => {
+                    return Err(CommandError::provider_event(client.name(), &event.data));
+                }
+                _ => {}
+            }
+        }
+
+        if chunk.is_none() {
+            break;
+        }
+    }
+
+    result.usage = usage;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::chat::ChatRole;
+
+    #[test]
+    fn build_request_applies_options() {
+        let request = build_request(
+            "claude-sonnet-4-5".to_string(),
+            vec![ChatMessage::new(ChatRole::User, "Hi")],
+            0.5,
+            AnthropicOptions {
+                max_tokens: Some(8192),
+                top_k: Some(40),
+                stop_sequences: Some(vec!["END".to_string()]),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let body = serde_json::to_value(&request).unwrap();
+        assert_eq!(body["max_tokens"], 8192);
+        assert_eq!(body["top_k"], 40);
+        assert_eq!(body["stop_sequences"], serde_json::json!(["END"]));
+        assert!(body.get("top_p").is_none());
+        assert!(body.get("metadata").is_none());
+    }
+
+    #[test]
+    fn build_request_defaults_max_tokens() {
+        let conversation = vec![ChatMessage::new(ChatRole::User, "Hi")];
+
+        let request = build_request(
+            "claude-sonnet-4-5".to_string(),
+            conversation.clone(),
+            1.0,
+            AnthropicOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(request.max_tokens, DEFAULT_MAX_TOKENS);
+
+        assert!(build_request(
+            "claude-sonnet-4-5".to_string(),
+            conversation,
+            1.0,
+            AnthropicOptions {
+                max_tokens: Some(0),
+                ..Default::default()
+            },
+        )
+        .is_err());
+    }
+}
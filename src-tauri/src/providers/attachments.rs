@@ -0,0 +1,252 @@
+use std::path::Path;
+
+use base64::{engine::general_purpose, Engine as _};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Response;
+use serde::{Deserialize, Serialize};
+
+use super::chat::{ChatMessage, ChatRole, ContentBlock, MediaSource, MessageContent};
+use crate::error::CommandError;
+use crate::http::HttpClient;
+use crate::path_utils::mime_type_for_path;
+
+const MAX_ATTACHMENT_BYTES: usize = 32 * 1024 * 1024;
+
+const SUPPORTED_IMAGE_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// A file to send alongside the newest user turn: either a local path (as returned by
+/// `save_uploaded_file` or `download_and_save_file`) or a remote URL.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum ChatAttachment {
+    Path {
+        path: String,
+        #[serde(default)]
+        media_type: Option<String>,
+    },
+    Url {
+        url: String,
+        #[serde(default)]
+        media_type: Option<String>,
+    },
+}
+
+async fn load_attachment(
+    attachment: &ChatAttachment,
+    http: &HttpClient,
+) -> Result<ContentBlock, CommandError> {
+    let (bytes, media_type, name) = match attachment {
+        ChatAttachment::Path { path, media_type } => {
+            let size = tokio::fs::metadata(path)
+                .await
+                .map_err(|e| format!("Failed to read attachment {}: {}", path, e))?
+                .len();
+            if size > MAX_ATTACHMENT_BYTES as u64 {
+                return Err(too_large(size));
+            }
+            let bytes = tokio::fs::read(path)
+                .await
+                .map_err(|e| format!("Failed to read attachment {}: {}", path, e))?;
+            let media_type = media_type
+                .clone()
+                .unwrap_or_else(|| mime_type_for_path(path).to_string());
+            let name = Path::new(path)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| name.to_string());
+            (bytes, media_type, name)
+        }
+        ChatAttachment::Url { url, media_type } => {
+            let response = http
+                .with_timeout(http.client().get(url))
+                .send()
+                .await
+                .map_err(|e| {
+                    CommandError::network(None, &format!("download attachment {}", url), e)
+                })?;
+
+            if !response.status().is_success() {
+                return Err(CommandError::from_download(response).await);
+            }
+
+            let header_type = response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.split(';').next().unwrap_or(value).trim().to_string());
+            let path_part = url.split('?').next().unwrap_or(url);
+            let media_type = media_type
+                .clone()
+                .or(header_type)
+                .filter(|value| value != "application/octet-stream")
+                .unwrap_or_else(|| mime_type_for_path(path_part).to_string());
+            let name = path_part
+                .rsplit('/')
+                .next()
+                .filter(|name| !name.is_empty())
+                .map(|name| name.to_string());
+            (read_limited(http, response).await?, media_type, name)
+        }
+    };
+
+    if bytes.len() > MAX_ATTACHMENT_BYTES {
+        return Err(too_large(bytes.len() as u64));
+    }
+
+    encode_attachment(&bytes, media_type, name)
+}
+
+fn too_large(size: u64) -> CommandError {
+    CommandError::invalid_request(format!(
+        "Attachment is too large ({} bytes, limit {} bytes)",
+        size, MAX_ATTACHMENT_BYTES
+    ))
+}
+
+/// Reads a download no further than the attachment limit, so an oversized or
+/// endless body is rejected without being held in memory.
+async fn read_limited(http: &HttpClient, mut response: Response) -> Result<Vec<u8>, CommandError> {
+    if let Some(length) = response
+        .content_length()
+        .filter(|length| *length > MAX_ATTACHMENT_BYTES as u64)
+    {
+        return Err(too_large(length));
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = http.next_chunk(&mut response).await? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() > MAX_ATTACHMENT_BYTES {
+            return Err(CommandError::invalid_request(format!(
+                "Attachment is too large (over {} bytes)",
+                MAX_ATTACHMENT_BYTES
+            )));
+        }
+    }
+    Ok(bytes)
+}
+
+fn encode_attachment(
+    bytes: &[u8],
+    media_type: String,
+    name: Option<String>,
+) -> Result<ContentBlock, CommandError> {
+    let data = general_purpose::STANDARD.encode(bytes);
+
+    if SUPPORTED_IMAGE_TYPES.contains(&media_type.as_str()) {
+        Ok(ContentBlock::Image {
+            source: MediaSource::Base64 { media_type, data },
+        })
+    } else if media_type == "application/pdf" {
+        Ok(ContentBlock::Document {
+            source: MediaSource::Base64 { media_type, data },
+            title: name,
+        })
+    } else {
+        Err(CommandError::invalid_request(format!(
+            "Unsupported attachment type '{}'. Use PNG, JPEG, GIF, WebP or PDF files.",
+            media_type
+        )))
+    }
+}
+
+/// Encodes each attachment as an image/document block and places them ahead of the
+/// text of the newest user turn.
+pub async fn attach_to_conversation(
+    conversation: &mut [ChatMessage],
+    attachments: Option<Vec<ChatAttachment>>,
+    http: &HttpClient,
+) -> Result<(), CommandError> {
+    let attachments = attachments.unwrap_or_default();
+    if attachments.is_empty() {
+        return Ok(());
+    }
+
+    let target = conversation
+        .iter_mut()
+        .rev()
+        .find(|message| message.role != ChatRole::System)
+        .filter(|message| message.role == ChatRole::User)
+        .ok_or_else(|| {
+            CommandError::invalid_request(
+                "Attachments require the conversation to end with a user message",
+            )
+        })?;
+
+    let mut blocks = Vec::with_capacity(attachments.len() + 1);
+    for attachment in &attachments {
+        blocks.push(load_attachment(attachment, http).await?);
+    }
+
+    match std::mem::replace(&mut target.content, MessageContent::Blocks(Vec::new())) {
+        MessageContent::Text(text) => {
+            if !text.is_empty() {
+                blocks.push(ContentBlock::Text { text });
+            }
+        }
+        MessageContent::Blocks(existing) => blocks.extend(existing),
+    }
+    target.content = MessageContent::Blocks(blocks);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn encodes_images_and_pdfs_as_base64_blocks() {
+        let image = encode_attachment(b"png", "image/png".to_string(), None).unwrap();
+        assert_eq!(
+            image,
+            ContentBlock::Image {
+                source: MediaSource::Base64 {
+                    media_type: "image/png".to_string(),
+                    data: "cG5n".to_string(),
+                },
+            }
+        );
+
+        let document = encode_attachment(
+            b"%PDF",
+            "application/pdf".to_string(),
+            Some("brief.pdf".to_string()),
+        )
+        .unwrap();
+        assert_eq!(document.type_name(), "document");
+
+        assert!(encode_attachment(b"", "video/mp4".to_string(), None).is_err());
+    }
+
+    #[test]
+    fn attachments_precede_text_in_the_last_user_turn() {
+        let path =
+            std::env::temp_dir().join(format!("noder-attachment-test-{}.png", std::process::id()));
+        fs::write(&path, b"png").unwrap();
+        let mut conversation = vec![
+            ChatMessage::new(ChatRole::System, "Critique images"),
+            ChatMessage::new(ChatRole::User, "What is this?"),
+        ];
+
+        let result = tauri::async_runtime::block_on(attach_to_conversation(
+            &mut conversation,
+            Some(vec![ChatAttachment::Path {
+                path: path.to_string_lossy().to_string(),
+                media_type: None,
+            }]),
+            &HttpClient::default(),
+        ));
+        fs::remove_file(&path).ok();
+        result.unwrap();
+
+        match &conversation[1].content {
+            MessageContent::Blocks(blocks) => {
+                assert_eq!(blocks[0].type_name(), "image");
+                assert_eq!(blocks[1].type_name(), "text");
+            }
+            other => panic!("expected blocks, got {:?}", other),
+        }
+    }
+}
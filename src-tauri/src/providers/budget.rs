@@ -0,0 +1,417 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::ledger::{self, CallContext, LedgerEntry};
+use crate::error::CommandError;
+use crate::settings::{load_settings, AppSettings};
+
+const DEFAULT_OVERRIDE_MINUTES: i64 = 60;
+
+/// Compute time assumed for a job on a model with no recorded jobs yet.
+const RESERVED_JOB_SECONDS: f64 = 60.0;
+/// Reservations for jobs nobody polled to completion stop counting after this long.
+const RESERVATION_TTL_HOURS: i64 = 6;
+
+/// While set and in the future, limits are not enforced.
+static OVERRIDE_UNTIL: Mutex<Option<DateTime<Utc>>> = Mutex::new(None);
+
+/// Estimated spend of jobs that have started but are not in the ledger yet. Jobs
+/// are only recorded once a poll sees them finish, so without these a batch of
+/// concurrent predictions would all pass the check before any of them is billed.
+static RESERVATIONS: Mutex<Vec<Reservation>> = Mutex::new(Vec::new());
+static NEXT_RESERVATION: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone)]
+struct Reservation {
+    id: u64,
+    /// Carries the provider, run and estimated cost the limits are checked against,
+    /// and the job id once the job has been created.
+    entry: LedgerEntry,
+}
+
+/// Holds a job's estimated cost against the limits while it is being created.
+/// Dropping it releases the estimate; `hold` keeps it until the finished job is
+/// recorded in the ledger.
+#[derive(Debug)]
+pub struct BudgetReservation {
+    id: Option<u64>,
+}
+
+impl BudgetReservation {
+    pub fn hold(mut self, job_id: &str) {
+        let Some(id) = self.id.take() else {
+            return;
+        };
+        let mut reservations = RESERVATIONS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(reservation) = reservations.iter_mut().find(|r| r.id == id) {
+            reservation.entry.job_id = Some(job_id.to_string());
+        }
+    }
+
+    /// The cost reserved for the job, `None` when limits are off or the model
+    /// cannot be priced.
+    pub fn estimated_cost(&self) -> Option<f64> {
+        let id = self.id?;
+        RESERVATIONS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|r| r.id == id)
+            .and_then(|r| r.entry.estimated_cost)
+    }
+}
+
+impl Drop for BudgetReservation {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            RESERVATIONS
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .retain(|r| r.id != id);
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetScope {
+    Daily,
+    Run,
+    Provider,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetExceeded {
+    pub scope: BudgetScope,
+    pub limit: f64,
+    pub spent: f64,
+    /// The provider for `Provider` limits, the run id for `Run` limits.
+    pub subject: Option<String>,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = match (self.scope, &self.subject) {
+            (BudgetScope::Daily, _) => "Daily spending limit".to_string(),
+            (BudgetScope::Run, _) => "Spending limit for this workflow run".to_string(),
+            (BudgetScope::Provider, Some(provider)) => {
+                format!("Daily spending limit for {}", provider)
+            }
+            (BudgetScope::Provider, None) => "Provider spending limit".to_string(),
+        };
+        write!(
+            f,
+            "{} of ${:.2} reached (${:.2} spent). Raise it in Settings or override the budget to continue.",
+            what, self.limit, self.spent
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BudgetOverride {
+    pub until: String,
+}
+
+fn is_local(provider: &str) -> bool {
+    matches!(provider, "ollama" | "lmstudio")
+}
+
+fn override_active(now: DateTime<Utc>) -> bool {
+    OVERRIDE_UNTIL
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_some_and(|until| until > now)
+}
+
+/// Compares what the ledger has recorded against each configured limit. A limit is
+/// reached once spending meets it, so the call that would go over is refused.
+fn check_limits(
+    entries: &[LedgerEntry],
+    settings: &AppSettings,
+    provider: &str,
+    call_context: Option<&CallContext>,
+    today: &str,
+) -> Result<(), BudgetExceeded> {
+    let spent_where = |include: &dyn Fn(&LedgerEntry) -> bool| -> f64 {
+        entries
+            .iter()
+            .filter(|entry| include(entry))
+            .filter_map(|entry| entry.estimated_cost)
+            .sum()
+    };
+    let is_today = |entry: &LedgerEntry| entry.timestamp.starts_with(today);
+
+    if let Some(limit) = settings.daily_spend_limit {
+        let spent = spent_where(&is_today);
+        if spent >= limit {
+            return Err(BudgetExceeded {
+                scope: BudgetScope::Daily,
+                limit,
+                spent,
+                subject: None,
+            });
+        }
+    }
+
+    if let (Some(limit), Some(run_id)) = (
+        settings.run_spend_limit,
+        call_context.and_then(|context| context.run_id.as_ref()),
+    ) {
+        let spent = spent_where(&|entry| entry.context.run_id.as_ref() == Some(run_id));
+        if spent >= limit {
+            return Err(BudgetExceeded {
+                scope: BudgetScope::Run,
+                limit,
+                spent,
+                subject: Some(run_id.clone()),
+            });
+        }
+    }
+
+    if let Some(limit) = settings
+        .provider_daily_spend_limits
+        .as_ref()
+        .and_then(|limits| limits.get(provider))
+        .copied()
+    {
+        let spent = spent_where(&|entry| is_today(entry) && entry.provider == provider);
+        if spent >= limit {
+            return Err(BudgetExceeded {
+                scope: BudgetScope::Provider,
+                limit,
+                spent,
+                subject: Some(provider.to_string()),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// What a job on `model` is expected to cost: the average of its recorded jobs, or
+/// `RESERVED_JOB_SECONDS` of compute when there are none. `None` for providers that
+/// do not bill by compute time.
+fn estimate_job_cost(entries: &[LedgerEntry], provider: &str, model: &str) -> Option<f64> {
+    let costs: Vec<f64> = entries
+        .iter()
+        .filter(|entry| {
+            entry.provider == provider && entry.model == model && entry.job_id.is_some()
+        })
+        .filter_map(|entry| entry.estimated_cost)
+        .collect();
+    if !costs.is_empty() {
+        return Some(costs.iter().sum::<f64>() / costs.len() as f64);
+    }
+    ledger::estimate_cost(
+        &LedgerEntry::new(provider, model, None).with_job("", Some(RESERVED_JOB_SECONDS)),
+    )
+}
+
+/// Checks the limits against the ledger plus outstanding reservations and, for a
+/// job on `model`, reserves its estimated cost in the same step.
+async fn check_budget(
+    app_handle: &tauri::AppHandle,
+    provider: &str,
+    model: Option<&str>,
+    call_context: Option<&CallContext>,
+) -> Result<Option<u64>, CommandError> {
+    let now = Utc::now();
+    if is_local(provider) || override_active(now) {
+        return Ok(None);
+    }
+
+    let settings = load_settings(app_handle.clone()).await?;
+    if settings.daily_spend_limit.is_none()
+        && settings.run_spend_limit.is_none()
+        && settings.provider_daily_spend_limits.is_none()
+    {
+        return Ok(None);
+    }
+
+    let mut entries = ledger::load_entries(app_handle)?;
+    let today = now.format("%Y-%m-%d").to_string();
+
+    let mut reservations = RESERVATIONS.lock().unwrap_or_else(|e| e.into_inner());
+    let cutoff = (now - Duration::hours(RESERVATION_TTL_HOURS)).to_rfc3339();
+    reservations.retain(|r| r.entry.timestamp >= cutoff);
+    let reserved = model.map(|model| {
+        let mut entry = LedgerEntry::new(provider, model, call_context);
+        entry.estimated_cost = estimate_job_cost(&entries, provider, model);
+        entry
+    });
+    entries.extend(reservations.iter().map(|r| r.entry.clone()));
+
+    check_limits(&entries, &settings, provider, call_context, &today).map_err(|e| {
+        CommandError::BudgetExceeded {
+            message: e.to_string(),
+        }
+    })?;
+
+    Ok(reserved.map(|entry| {
+        let id = NEXT_RESERVATION.fetch_add(1, Ordering::Relaxed);
+        reservations.push(Reservation { id, entry });
+        id
+    }))
+}
+
+/// Refuses the call with a `CommandError::BudgetExceeded` when a spending limit has been
+/// reached. Local providers cost nothing and are never blocked.
+pub async fn ensure_within_budget(
+    app_handle: &tauri::AppHandle,
+    provider: &str,
+    call_context: Option<&CallContext>,
+) -> Result<(), CommandError> {
+    check_budget(app_handle, provider, None, call_context).await?;
+    Ok(())
+}
+
+/// `ensure_within_budget` for calls that start a billed job, reserving its
+/// estimated cost until the job is recorded.
+pub async fn reserve_within_budget(
+    app_handle: &tauri::AppHandle,
+    provider: &str,
+    model: &str,
+    call_context: Option<&CallContext>,
+) -> Result<BudgetReservation, CommandError> {
+    let id = check_budget(app_handle, provider, Some(model), call_context).await?;
+    Ok(BudgetReservation { id })
+}
+
+/// Releases a held reservation once the job's real cost is in the ledger.
+pub fn settle(provider: &str, job_id: &str) {
+    RESERVATIONS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .retain(|r| r.entry.provider != provider || r.entry.job_id.as_deref() != Some(job_id));
+}
+
+/// Lifts all spending limits for `minutes` (an hour by default).
+#[tauri::command]
+pub fn override_budget(minutes: Option<i64>) -> Result<BudgetOverride, CommandError> {
+    let minutes = minutes.unwrap_or(DEFAULT_OVERRIDE_MINUTES);
+    if minutes <= 0 {
+        return Err(CommandError::invalid_request(
+            "Override duration must be positive",
+        ));
+    }
+
+    let until = Utc::now() + Duration::minutes(minutes);
+    *OVERRIDE_UNTIL.lock().unwrap_or_else(|e| e.into_inner()) = Some(until);
+    Ok(BudgetOverride {
+        until: until.to_rfc3339(),
+    })
+}
+
+#[tauri::command]
+pub fn clear_budget_override() {
+    *OVERRIDE_UNTIL.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::default_app_settings;
+    use std::collections::HashMap;
+
+    fn spend(timestamp: &str, provider: &str, run_id: Option<&str>, cost: f64) -> LedgerEntry {
+        let mut entry = LedgerEntry::new(
+            provider,
+            "model",
+            Some(&CallContext {
+                run_id: run_id.map(str::to_string),
+                ..Default::default()
+            }),
+        );
+        entry.timestamp = timestamp.to_string();
+        entry.estimated_cost = Some(cost);
+        entry
+    }
+
+    #[test]
+    fn limits_count_only_matching_spend() {
+        let entries = vec![
+            spend("2024-05-01T09:00:00+00:00", "replicate", Some("run-1"), 4.0),
+            spend("2024-05-02T09:00:00+00:00", "replicate", Some("run-2"), 1.5),
+            spend("2024-05-02T10:00:00+00:00", "anthropic", Some("run-2"), 0.5),
+        ];
+        let run_2 = CallContext {
+            run_id: Some("run-2".to_string()),
+            ..Default::default()
+        };
+        let today = "2024-05-02";
+
+        let mut settings = default_app_settings();
+        settings.daily_spend_limit = Some(2.5);
+        assert!(check_limits(&entries, &settings, "replicate", None, today).is_ok());
+        settings.daily_spend_limit = Some(2.0);
+        let exceeded = check_limits(&entries, &settings, "replicate", None, today).unwrap_err();
+        assert_eq!(exceeded.scope, BudgetScope::Daily);
+        assert_eq!(exceeded.spent, 2.0);
+
+        let mut settings = default_app_settings();
+        settings.run_spend_limit = Some(2.0);
+        assert!(check_limits(&entries, &settings, "fal", Some(&run_2), today).is_err());
+        assert!(check_limits(&entries, &settings, "fal", None, today).is_ok());
+
+        let mut settings = default_app_settings();
+        settings.provider_daily_spend_limits =
+            Some(HashMap::from([("replicate".to_string(), 1.5)]));
+        let exceeded = check_limits(&entries, &settings, "replicate", None, today).unwrap_err();
+        assert_eq!(exceeded.subject.as_deref(), Some("replicate"));
+        assert!(check_limits(&entries, &settings, "anthropic", None, today).is_ok());
+    }
+
+    #[test]
+    fn job_estimates_average_recorded_jobs_of_the_model() {
+        let mut first = spend("2024-05-01T09:00:00+00:00", "replicate", None, 0.5);
+        first.job_id = Some("a".to_string());
+        let mut second = first.clone();
+        second.estimated_cost = Some(1.5);
+        let entries = vec![first, second];
+
+        assert_eq!(estimate_job_cost(&entries, "replicate", "model"), Some(1.0));
+        assert_eq!(
+            estimate_job_cost(&entries, "replicate", "other"),
+            Some(RESERVED_JOB_SECONDS * 0.0014)
+        );
+        assert_eq!(estimate_job_cost(&entries, "anthropic", "claude"), None);
+    }
+
+    #[test]
+    fn reservations_release_on_drop_or_when_the_job_settles() {
+        let reserve = || {
+            let id = NEXT_RESERVATION.fetch_add(1, Ordering::Relaxed);
+            RESERVATIONS.lock().unwrap().push(Reservation {
+                id,
+                entry: LedgerEntry::new("fal", "model", None),
+            });
+            BudgetReservation { id: Some(id) }
+        };
+        let count = || RESERVATIONS.lock().unwrap().len();
+        let before = count();
+
+        drop(reserve());
+        assert_eq!(count(), before);
+
+        reserve().hold("job-1");
+        assert_eq!(count(), before + 1);
+        settle("fal", "job-1");
+        assert_eq!(count(), before);
+    }
+
+    #[test]
+    fn refusals_name_the_limit_that_was_reached() {
+        let message = BudgetExceeded {
+            scope: BudgetScope::Provider,
+            limit: 10.0,
+            spent: 10.4,
+            subject: Some("replicate".to_string()),
+        }
+        .to_string();
+        assert!(message.starts_with("Daily spending limit for replicate of $10.00"));
+    }
+}
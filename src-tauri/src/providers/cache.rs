@@ -0,0 +1,724 @@
+use std::fs;
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::Manager;
+
+use crate::error::CommandError;
+use crate::http::HttpClient;
+use crate::path_utils::sanitize_extension;
+use crate::settings::{load_settings, AppSettings};
+
+const CACHE_DIR: &str = "response-cache";
+const MEDIA_DIR: &str = "media";
+const DEFAULT_TTL_HOURS: u64 = 24 * 7;
+const DEFAULT_MAX_MB: u64 = 1024;
+
+/// Serializes access to the cache directory so pruning cannot race a write.
+static CACHE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Identifies a generation by everything that determines its output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    pub digest: String,
+    pub provider: String,
+    pub model: String,
+}
+
+impl CacheKey {
+    /// Hashes provider, model and input with object keys sorted, so a request built
+    /// in a different field order hits the same entry. The seed is part of the input.
+    pub fn new(provider: &str, model: &str, input: &serde_json::Value) -> Self {
+        let mut canonical = String::new();
+        write_canonical(
+            &serde_json::json!({ "provider": provider, "model": model, "input": input }),
+            &mut canonical,
+        );
+
+        CacheKey {
+            digest: format!("{:x}", Sha256::digest(canonical.as_bytes())),
+            provider: provider.to_string(),
+            model: model.to_string(),
+        }
+    }
+}
+
+fn write_canonical(value: &serde_json::Value, out: &mut String) {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (index, key) in keys.into_iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(&map[key], out);
+            }
+            out.push('}');
+        }
+        serde_json::Value::Array(items) => {
+            out.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+/// Whether the input pins its output: a non-negative seed or a zero temperature,
+/// at any depth. Anything else samples afresh on every run and is never cached.
+pub fn is_deterministic(input: &serde_json::Value) -> bool {
+    match input {
+        serde_json::Value::Object(map) => map.iter().any(|(key, value)| match key.as_str() {
+            "seed" => value.as_u64().is_some(),
+            "temperature" => value.as_f64() == Some(0.0),
+            _ => is_deterministic(value),
+        }),
+        serde_json::Value::Array(items) => items.iter().any(is_deterministic),
+        _ => false,
+    }
+}
+
+/// A stored result. `value` is what the command returned, serialized.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CacheEntry {
+    pub key: String,
+    pub provider: String,
+    pub model: String,
+    pub created_at: String,
+    pub last_used_at: String,
+    /// Bytes on disk, including downloaded media.
+    pub size_bytes: u64,
+    /// Local copies of the output's media, which outlive the provider's expiring URLs.
+    #[serde(default)]
+    pub media_paths: Vec<String>,
+    pub value: serde_json::Value,
+}
+
+impl CacheEntry {
+    /// The provider id without the endpoint suffix that OpenAI-compatible servers
+    /// add to their keys (`lmstudio:http://localhost:1234/v1`).
+    pub fn provider_id(&self) -> &str {
+        self.provider.split(':').next().unwrap_or_default()
+    }
+
+    fn matches(&self, provider: Option<&str>, key: Option<&str>) -> bool {
+        provider.is_none_or(|provider| provider == self.provider_id())
+            && key.is_none_or(|key| key == self.key)
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct CacheStats {
+    pub enabled: bool,
+    pub entries: usize,
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+    pub ttl_hours: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CacheLimits {
+    ttl_hours: u64,
+    max_bytes: u64,
+}
+
+impl CacheLimits {
+    fn from_settings(settings: &AppSettings) -> Self {
+        CacheLimits {
+            ttl_hours: settings
+                .response_cache_ttl_hours
+                .unwrap_or(DEFAULT_TTL_HOURS),
+            max_bytes: settings.response_cache_max_mb.unwrap_or(DEFAULT_MAX_MB) * 1024 * 1024,
+        }
+    }
+
+    fn is_expired(&self, entry: &CacheEntry, now: DateTime<Utc>) -> bool {
+        DateTime::parse_from_rfc3339(&entry.created_at)
+            .map(|created| created + Duration::hours(self.ttl_hours as i64) <= now)
+            .unwrap_or(true)
+    }
+}
+
+fn cache_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app_handle
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to get app cache directory: {}", e))?
+        .join(CACHE_DIR);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create cache directory: {}", e))?;
+    Ok(dir)
+}
+
+fn entry_path(dir: &Path, digest: &str) -> PathBuf {
+    dir.join(format!("{}.json", digest))
+}
+
+fn media_dir(dir: &Path, digest: &str) -> PathBuf {
+    dir.join(MEDIA_DIR).join(digest)
+}
+
+fn read_entries(dir: &Path) -> Result<Vec<CacheEntry>, String> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let files = fs::read_dir(dir).map_err(|e| format!("Failed to read cache directory: {}", e))?;
+
+    // An entry cut short by a crash is skipped here and replaced on the next miss.
+    Ok(files
+        .filter_map(|file| file.ok())
+        .map(|file| file.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .filter_map(|content| serde_json::from_str(&content).ok())
+        .collect())
+}
+
+fn write_entry(dir: &Path, entry: &CacheEntry) -> Result<(), String> {
+    let content = serde_json::to_string(entry)
+        .map_err(|e| format!("Failed to serialize cache entry: {}", e))?;
+    fs::write(entry_path(dir, &entry.key), content)
+        .map_err(|e| format!("Failed to write cache entry: {}", e))
+}
+
+fn remove_entry(dir: &Path, digest: &str) {
+    let _ = fs::remove_file(entry_path(dir, digest));
+    let _ = fs::remove_dir_all(media_dir(dir, digest));
+}
+
+/// Most recently used first.
+fn list_entries(
+    dir: &Path,
+    provider: Option<&str>,
+    limit: Option<usize>,
+) -> Result<Vec<CacheEntry>, String> {
+    let mut entries = read_entries(dir)?;
+    entries.retain(|entry| entry.matches(provider, None));
+    entries.sort_by_key(|entry| DateTime::parse_from_rfc3339(&entry.last_used_at).ok());
+    entries.reverse();
+    entries.truncate(limit.unwrap_or(usize::MAX));
+    Ok(entries)
+}
+
+fn remove_entries(dir: &Path, provider: Option<&str>, key: Option<&str>) -> Result<usize, String> {
+    let removed: Vec<CacheEntry> = read_entries(dir)?
+        .into_iter()
+        .filter(|entry| entry.matches(provider, key))
+        .collect();
+    for entry in &removed {
+        remove_entry(dir, &entry.key);
+    }
+    Ok(removed.len())
+}
+
+/// Returns the live entry for `digest` and marks it used. Expired entries and ones
+/// whose media was deleted behind the cache's back are dropped as misses.
+fn lookup(
+    dir: &Path,
+    digest: &str,
+    limits: CacheLimits,
+    now: DateTime<Utc>,
+) -> Result<Option<CacheEntry>, String> {
+    let path = entry_path(dir, digest);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read cache entry: {}", e))?;
+
+    let mut entry: CacheEntry = match serde_json::from_str(&content) {
+        Ok(entry) => entry,
+        Err(_) => {
+            remove_entry(dir, digest);
+            return Ok(None);
+        }
+    };
+    if limits.is_expired(&entry, now) || !entry.media_paths.iter().all(|p| Path::new(p).exists()) {
+        remove_entry(dir, digest);
+        return Ok(None);
+    }
+
+    entry.last_used_at = now.to_rfc3339();
+    write_entry(dir, &entry)?;
+    Ok(Some(entry))
+}
+
+/// Drops expired entries, then the least recently used ones until the cache fits
+/// in `max_bytes`. Returns how many were removed.
+fn prune(dir: &Path, limits: CacheLimits, now: DateTime<Utc>) -> Result<usize, String> {
+    let (expired, mut live): (Vec<_>, Vec<_>) = read_entries(dir)?
+        .into_iter()
+        .partition(|entry| limits.is_expired(entry, now));
+    for entry in &expired {
+        remove_entry(dir, &entry.key);
+    }
+
+    live.sort_by_key(|entry| DateTime::parse_from_rfc3339(&entry.last_used_at).ok());
+    let mut total: u64 = live.iter().map(|entry| entry.size_bytes).sum();
+    let mut evicted = 0;
+    for entry in &live {
+        if total <= limits.max_bytes {
+            break;
+        }
+        remove_entry(dir, &entry.key);
+        total -= entry.size_bytes;
+        evicted += 1;
+    }
+
+    Ok(expired.len() + evicted)
+}
+
+/// The opt-in store of deterministic results, under the app cache directory.
+pub struct ResponseCache {
+    dir: PathBuf,
+    limits: CacheLimits,
+}
+
+impl ResponseCache {
+    /// `None` unless the cache is turned on in settings.
+    pub fn new(app_handle: &tauri::AppHandle, settings: &AppSettings) -> Option<Self> {
+        if !settings.response_cache_enabled.unwrap_or(false) {
+            return None;
+        }
+        match cache_dir(app_handle) {
+            Ok(dir) => Some(ResponseCache {
+                dir,
+                limits: CacheLimits::from_settings(settings),
+            }),
+            Err(e) => {
+                eprintln!("Response cache unavailable: {}", e);
+                None
+            }
+        }
+    }
+
+    pub async fn load(app_handle: &tauri::AppHandle) -> Result<Option<Self>, CommandError> {
+        let settings = load_settings(app_handle.clone()).await?;
+        Ok(ResponseCache::new(app_handle, &settings))
+    }
+
+    /// `None` when `input` is not deterministic.
+    pub fn key(&self, provider: &str, model: &str, input: &impl Serialize) -> Option<CacheKey> {
+        let input = serde_json::to_value(input).ok()?;
+        is_deterministic(&input).then(|| CacheKey::new(provider, model, &input))
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<CacheEntry> {
+        let _guard = CACHE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        lookup(&self.dir, &key.digest, self.limits, Utc::now()).unwrap_or_else(|e| {
+            eprintln!("Failed to read response cache: {}", e);
+            None
+        })
+    }
+
+    /// The cached result for `key`, if there is one that still deserializes as `T`.
+    pub fn get_value<T: DeserializeOwned>(&self, key: &CacheKey) -> Option<T> {
+        self.get(key)
+            .and_then(|entry| serde_json::from_value(entry.value).ok())
+    }
+
+    /// Stores a result. Like the usage ledger, caching must never fail the call it
+    /// saves, so errors are only logged.
+    pub fn put(&self, key: &CacheKey, value: &impl Serialize, media_paths: Vec<String>) {
+        let value = match serde_json::to_value(value) {
+            Ok(value) => value,
+            Err(e) => {
+                eprintln!("Failed to serialize cached response: {}", e);
+                return;
+            }
+        };
+        let media_bytes: u64 = media_paths
+            .iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum();
+        let now = Utc::now();
+        let entry = CacheEntry {
+            key: key.digest.clone(),
+            provider: key.provider.clone(),
+            model: key.model.clone(),
+            created_at: now.to_rfc3339(),
+            last_used_at: now.to_rfc3339(),
+            size_bytes: value.to_string().len() as u64 + media_bytes,
+            media_paths,
+            value,
+        };
+
+        let _guard = CACHE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) =
+            write_entry(&self.dir, &entry).and_then(|_| prune(&self.dir, self.limits, now))
+        {
+            eprintln!("Failed to write response cache: {}", e);
+        }
+    }
+
+    /// Downloads every URL in `output` into the entry's media folder. Files that fail
+    /// to download are left out; the result is still worth caching as text. `None`
+    /// when the media together would not fit in the cache, which is then skipped.
+    pub async fn download_media(
+        &self,
+        http: &HttpClient,
+        key: &CacheKey,
+        output: &serde_json::Value,
+    ) -> Option<Vec<String>> {
+        let mut urls = Vec::new();
+        collect_urls(output, &mut urls);
+        if urls.is_empty() {
+            return Some(Vec::new());
+        }
+
+        let folder = media_dir(&self.dir, &key.digest);
+        if let Err(e) = fs::create_dir_all(&folder) {
+            eprintln!("Failed to create cache media folder: {}", e);
+            return Some(Vec::new());
+        }
+
+        let mut paths = Vec::new();
+        let mut budget = self.limits.max_bytes;
+        for (index, url) in urls.iter().enumerate() {
+            let path = folder.join(media_file_name(index, url));
+            match download(http, url, &path, budget).await {
+                Ok(Some(size)) => {
+                    budget -= size;
+                    paths.push(path.to_string_lossy().to_string());
+                }
+                Ok(None) => {
+                    let _ = fs::remove_dir_all(&folder);
+                    return None;
+                }
+                Err(e) => {
+                    let _ = fs::remove_file(&path);
+                    eprintln!("Failed to cache media from {}: {}", url, e);
+                }
+            }
+        }
+        Some(paths)
+    }
+}
+
+fn collect_urls(value: &serde_json::Value, urls: &mut Vec<String>) {
+    match value {
+        serde_json::Value::String(text)
+            if text.starts_with("https://") || text.starts_with("http://") =>
+        {
+            urls.push(text.clone())
+        }
+        serde_json::Value::Array(items) => items.iter().for_each(|item| collect_urls(item, urls)),
+        serde_json::Value::Object(map) => map.values().for_each(|item| collect_urls(item, urls)),
+        _ => {}
+    }
+}
+
+fn media_file_name(index: usize, url: &str) -> String {
+    let extension = url
+        .split('?')
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, extension)| sanitize_extension(extension))
+        .unwrap_or_default();
+    if extension.is_empty() {
+        format!("output-{}", index)
+    } else {
+        format!("output-{}.{}", index, extension)
+    }
+}
+
+/// Streams `url` into `path`, returning its size, or `None` once it passes `limit`
+/// bytes. Outputs such as videos can be far larger than is worth holding in memory.
+async fn download(
+    http: &HttpClient,
+    url: &str,
+    path: &Path,
+    limit: u64,
+) -> Result<Option<u64>, CommandError> {
+    let mut response = http
+        .await_response(None, "download media", http.client().get(url).send(), |e| {
+            CommandError::network(None, "download media", e)
+        })
+        .await?;
+    if !response.status().is_success() {
+        return Err(CommandError::from_download(response).await);
+    }
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit)
+    {
+        return Ok(None);
+    }
+
+    let mut file =
+        fs::File::create(path).map_err(|e| format!("Failed to create cached media: {}", e))?;
+    let mut size: u64 = 0;
+    while let Some(chunk) = http.next_chunk(&mut response).await? {
+        size += chunk.len() as u64;
+        if size > limit {
+            return Ok(None);
+        }
+        file.write_all(&chunk)
+            .map_err(|e| format!("Failed to write cached media: {}", e))?;
+    }
+    Ok(Some(size))
+}
+
+/// Returns the cached result for `input` when there is one, otherwise runs
+/// `generate` and caches what it returns. A pass-through when the cache is off or
+/// the input is not deterministic.
+pub async fn cached<T, F>(
+    app_handle: &tauri::AppHandle,
+    provider: &str,
+    model: &str,
+    input: &impl Serialize,
+    generate: F,
+) -> Result<T, CommandError>
+where
+    T: Serialize + DeserializeOwned,
+    F: Future<Output = Result<T, CommandError>>,
+{
+    let Some(cache) = ResponseCache::load(app_handle).await? else {
+        return generate.await;
+    };
+    let Some(key) = cache.key(provider, model, input) else {
+        return generate.await;
+    };
+
+    if let Some(value) = cache.get_value(&key) {
+        return Ok(value);
+    }
+
+    let value = generate.await?;
+    cache.put(&key, &value, Vec::new());
+    Ok(value)
+}
+
+#[tauri::command]
+pub async fn response_cache_stats(
+    app_handle: tauri::AppHandle,
+) -> Result<CacheStats, CommandError> {
+    let settings = load_settings(app_handle.clone()).await?;
+    let limits = CacheLimits::from_settings(&settings);
+    let entries = read_entries(&cache_dir(&app_handle)?)?;
+
+    Ok(CacheStats {
+        enabled: settings.response_cache_enabled.unwrap_or(false),
+        entries: entries.len(),
+        total_bytes: entries.iter().map(|entry| entry.size_bytes).sum(),
+        max_bytes: limits.max_bytes,
+        ttl_hours: limits.ttl_hours,
+    })
+}
+
+/// Most recently used entries first, optionally limited to one provider.
+#[tauri::command]
+pub async fn list_response_cache_entries(
+    app_handle: tauri::AppHandle,
+    provider: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<CacheEntry>, CommandError> {
+    Ok(list_entries(
+        &cache_dir(&app_handle)?,
+        provider.as_deref(),
+        limit,
+    )?)
+}
+
+/// Removes one entry by key, every entry of a provider, or everything. Returns how
+/// many entries were removed.
+#[tauri::command]
+pub async fn clear_response_cache(
+    app_handle: tauri::AppHandle,
+    provider: Option<String>,
+    key: Option<String>,
+) -> Result<usize, CommandError> {
+    let dir = cache_dir(&app_handle)?;
+    let _guard = CACHE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(remove_entries(&dir, provider.as_deref(), key.as_deref())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_ignore_field_order_but_not_seed() {
+        let a = CacheKey::new(
+            "replicate",
+            "flux",
+            &serde_json::json!({ "prompt": "cat", "seed": 1 }),
+        );
+        let b = CacheKey::new(
+            "replicate",
+            "flux",
+            &serde_json::from_str(r#"{"seed":1,"prompt":"cat"}"#).unwrap(),
+        );
+        let c = CacheKey::new(
+            "replicate",
+            "flux",
+            &serde_json::json!({ "prompt": "cat", "seed": 2 }),
+        );
+
+        assert_eq!(a, b);
+        assert_ne!(a.digest, c.digest);
+        assert_eq!(a.digest.len(), 64);
+    }
+
+    #[test]
+    fn only_seeded_or_zero_temperature_inputs_are_deterministic() {
+        assert!(is_deterministic(
+            &serde_json::json!({ "input": { "seed": 42 } })
+        ));
+        assert!(is_deterministic(
+            &serde_json::json!({ "generationConfig": { "temperature": 0.0 } })
+        ));
+        assert!(!is_deterministic(
+            &serde_json::json!({ "temperature": 0.7 })
+        ));
+        assert!(!is_deterministic(&serde_json::json!({ "seed": -1 })));
+        assert!(!is_deterministic(&serde_json::json!({ "prompt": "cat" })));
+    }
+
+    #[test]
+    fn lookup_expires_entries_and_prune_evicts_least_recently_used() {
+        let dir = std::env::temp_dir().join(format!("noder-cache-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let limits = CacheLimits {
+            ttl_hours: 1,
+            max_bytes: 150,
+        };
+        let now = Utc::now();
+        let entry = |key: &str, age_minutes: i64| CacheEntry {
+            key: key.to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            created_at: (now - Duration::minutes(age_minutes)).to_rfc3339(),
+            last_used_at: (now - Duration::minutes(age_minutes)).to_rfc3339(),
+            size_bytes: 100,
+            media_paths: Vec::new(),
+            value: serde_json::json!("hello"),
+        };
+
+        write_entry(&dir, &entry("stale", 90)).unwrap();
+        write_entry(&dir, &entry("older", 30)).unwrap();
+        write_entry(&dir, &entry("newer", 10)).unwrap();
+
+        assert_eq!(lookup(&dir, "stale", limits, now).unwrap(), None);
+        assert!(!entry_path(&dir, "stale").exists());
+
+        assert_eq!(prune(&dir, limits, now).unwrap(), 1);
+        let remaining = read_entries(&dir).unwrap();
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].key, "newer");
+    }
+
+    #[test]
+    fn media_downloads_stop_at_the_cache_budget() {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/video.mp4", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buffer = [0u8; 1024];
+                let _ = stream.read(&mut buffer);
+                let body = "x".repeat(100);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        let dir =
+            std::env::temp_dir().join(format!("noder-cache-media-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("output-0.mp4");
+        let http = HttpClient::default();
+
+        let fits = tauri::async_runtime::block_on(download(&http, &url, &path, 150)).unwrap();
+        let written = fs::metadata(&path).map(|metadata| metadata.len()).ok();
+        let too_large = tauri::async_runtime::block_on(download(&http, &url, &path, 50)).unwrap();
+        fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(fits, Some(100));
+        assert_eq!(written, Some(100));
+        assert_eq!(too_large, None);
+    }
+
+    #[test]
+    fn list_and_clear_match_the_provider_id_of_endpoint_keys() {
+        let dir =
+            std::env::temp_dir().join(format!("noder-cache-filter-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let now = Utc::now();
+        let entry = |key: &str, provider: &str, age_minutes: i64| CacheEntry {
+            key: key.to_string(),
+            provider: provider.to_string(),
+            model: "llama-3".to_string(),
+            created_at: (now - Duration::minutes(age_minutes)).to_rfc3339(),
+            last_used_at: (now - Duration::minutes(age_minutes)).to_rfc3339(),
+            size_bytes: 100,
+            media_paths: Vec::new(),
+            value: serde_json::json!("hello"),
+        };
+
+        write_entry(&dir, &entry("a", "openai", 30)).unwrap();
+        write_entry(&dir, &entry("b", "lmstudio:http://localhost:1234/v1", 20)).unwrap();
+        write_entry(&dir, &entry("c", "lmstudio:http://10.0.0.2:1234/v1", 10)).unwrap();
+        write_entry(
+            &dir,
+            &entry(
+                "d",
+                "openai_compatible:https://gateway.example/v1#0a1b2c3d4e5f",
+                5,
+            ),
+        )
+        .unwrap();
+
+        let keys = |entries: Vec<CacheEntry>| -> Vec<String> {
+            entries.into_iter().map(|entry| entry.key).collect()
+        };
+        assert_eq!(
+            keys(list_entries(&dir, Some("lmstudio"), None).unwrap()),
+            ["c", "b"]
+        );
+        assert_eq!(
+            keys(list_entries(&dir, Some("openai_compatible"), None).unwrap()),
+            ["d"]
+        );
+        assert_eq!(
+            keys(list_entries(&dir, Some("openai"), None).unwrap()),
+            ["a"]
+        );
+        assert_eq!(list_entries(&dir, None, Some(2)).unwrap().len(), 2);
+
+        assert_eq!(
+            remove_entries(&dir, Some("lmstudio"), Some("b")).unwrap(),
+            1
+        );
+        assert_eq!(remove_entries(&dir, Some("lmstudio"), None).unwrap(), 1);
+        let remaining = keys(list_entries(&dir, None, None).unwrap());
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(remaining, ["d", "a"]);
+    }
+}
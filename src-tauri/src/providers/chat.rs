@@ -0,0 +1,390 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+}
+
+impl ChatRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChatRole::System => "system",
+            ChatRole::User => "user",
+            ChatRole::Assistant => "assistant",
+        }
+    }
+}
+
+/// Typed content block, following Anthropic's wire format. Providers without
+/// tool support only accept `text` blocks.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content: Option<MessageContent>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        is_error: Option<bool>,
+    },
+    Image {
+        source: MediaSource,
+    },
+    Document {
+        source: MediaSource,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MediaSource {
+    Base64 { media_type: String, data: String },
+    Url { url: String },
+}
+
+impl MediaSource {
+    /// Renders the source as something a `data:`/`https:` URL field accepts.
+    pub fn to_url(&self) -> String {
+        match self {
+            MediaSource::Base64 { media_type, data } => {
+                format!("data:{};base64,{}", media_type, data)
+            }
+            MediaSource::Url { url } => url.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+impl MessageContent {
+    /// Flattens text blocks into a single string, rejecting blocks that cannot be
+    /// represented as plain text.
+    pub fn into_text(self) -> Result<String, String> {
+        match self {
+            MessageContent::Text(text) => Ok(text),
+            MessageContent::Blocks(blocks) => blocks
+                .into_iter()
+                .map(|block| match block {
+                    ContentBlock::Text { text } => Ok(text),
+                    other => Err(format!(
+                        "Content block '{}' is not supported here",
+                        other.type_name()
+                    )),
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(|parts| parts.join("\n")),
+        }
+    }
+
+    fn has_block(&self, predicate: impl Fn(&ContentBlock) -> bool) -> bool {
+        match self {
+            MessageContent::Text(_) => false,
+            MessageContent::Blocks(blocks) => blocks.iter().any(predicate),
+        }
+    }
+}
+
+impl ContentBlock {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ContentBlock::Text { .. } => "text",
+            ContentBlock::ToolUse { .. } => "tool_use",
+            ContentBlock::ToolResult { .. } => "tool_result",
+            ContentBlock::Image { .. } => "image",
+            ContentBlock::Document { .. } => "document",
+            ContentBlock::Unsupported => "unsupported",
+        }
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        MessageContent::Text(text)
+    }
+}
+
+impl From<&str> for MessageContent {
+    fn from(text: &str) -> Self {
+        MessageContent::Text(text.to_string())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: MessageContent,
+}
+
+impl ChatMessage {
+    pub fn new(role: ChatRole, content: impl Into<MessageContent>) -> Self {
+        ChatMessage {
+            role,
+            content: content.into(),
+        }
+    }
+}
+
+/// Assembles a conversation from the legacy single-prompt arguments and an optional
+/// history. `system_prompt` is placed first and `user_content` is appended as the
+/// newest user turn, so callers can send either, both, or a full history.
+pub fn build_conversation(
+    system_prompt: Option<String>,
+    messages: Option<Vec<ChatMessage>>,
+    user_content: Option<String>,
+) -> Vec<ChatMessage> {
+    let mut conversation = Vec::new();
+
+    if let Some(prompt) = system_prompt.filter(|prompt| !prompt.trim().is_empty()) {
+        conversation.push(ChatMessage::new(ChatRole::System, prompt));
+    }
+    conversation.extend(messages.unwrap_or_default());
+    if let Some(content) = user_content.filter(|content| !content.is_empty()) {
+        conversation.push(ChatMessage::new(ChatRole::User, content));
+    }
+
+    conversation
+}
+
+/// Anthropic and Gemini take system text as a separate field and require the
+/// remaining turns to start with `user` and strictly alternate with `assistant`.
+pub fn split_alternating_conversation(
+    provider: &str,
+    conversation: Vec<ChatMessage>,
+) -> Result<(Option<String>, Vec<ChatMessage>), String> {
+    let mut system_parts = Vec::new();
+    let mut turns: Vec<ChatMessage> = Vec::new();
+
+    for message in conversation {
+        match message.role {
+            ChatRole::System if turns.is_empty() => system_parts.push(message.content.into_text()?),
+            ChatRole::System => {
+                return Err(format!(
+                    "{} only supports system messages at the start of the conversation",
+                    provider
+                ))
+            }
+            role => {
+                let expected = match turns.last() {
+                    None
+                    | Some(ChatMessage {
+                        role: ChatRole::Assistant,
+                        ..
+                    }) => ChatRole::User,
+                    Some(_) => ChatRole::Assistant,
+                };
+                if role == ChatRole::User
+                    && message
+                        .content
+                        .has_block(|block| matches!(block, ContentBlock::ToolUse { .. }))
+                {
+                    return Err(
+                        "tool_use blocks are only allowed in assistant messages".to_string()
+                    );
+                }
+                if role == ChatRole::Assistant
+                    && message
+                        .content
+                        .has_block(|block| matches!(block, ContentBlock::ToolResult { .. }))
+                {
+                    return Err("tool_result blocks are only allowed in user messages".to_string());
+                }
+                if role != expected {
+                    return Err(format!(
+                        "{} requires alternating user/assistant messages starting with user; message {} is '{}' but '{}' was expected",
+                        provider,
+                        turns.len() + 1,
+                        role.as_str(),
+                        expected.as_str()
+                    ));
+                }
+                turns.push(message);
+            }
+        }
+    }
+
+    if turns.is_empty() {
+        return Err("Conversation must contain at least one user message".to_string());
+    }
+
+    let system = if system_parts.is_empty() {
+        None
+    } else {
+        Some(system_parts.join("\n\n"))
+    };
+
+    Ok((system, turns))
+}
+
+/// OpenAI-style APIs accept system messages anywhere and do not enforce
+/// alternation, but a request still needs at least one user message to respond to.
+pub fn require_user_message(conversation: &[ChatMessage]) -> Result<(), String> {
+    if !conversation
+        .iter()
+        .any(|message| message.role == ChatRole::User)
+    {
+        return Err("Conversation must contain at least one user message".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_conversation_keeps_single_prompt_shape() {
+        let conversation = build_conversation(
+            Some("Be brief".to_string()),
+            None,
+            Some("Hello".to_string()),
+        );
+
+        assert_eq!(
+            conversation,
+            vec![
+                ChatMessage::new(ChatRole::System, "Be brief"),
+                ChatMessage::new(ChatRole::User, "Hello"),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_merges_leading_system_messages() {
+        let (system, turns) = split_alternating_conversation(
+            "Anthropic",
+            vec![
+                ChatMessage::new(ChatRole::System, "One"),
+                ChatMessage::new(ChatRole::System, "Two"),
+                ChatMessage::new(ChatRole::User, "Hi"),
+                ChatMessage::new(ChatRole::Assistant, "Hello"),
+                ChatMessage::new(ChatRole::User, "More"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(system.as_deref(), Some("One\n\nTwo"));
+        assert_eq!(turns.len(), 3);
+    }
+
+    #[test]
+    fn split_rejects_non_alternating_turns() {
+        let error = split_alternating_conversation(
+            "Anthropic",
+            vec![
+                ChatMessage::new(ChatRole::User, "Hi"),
+                ChatMessage::new(ChatRole::User, "Again"),
+            ],
+        )
+        .unwrap_err();
+
+        assert!(error.contains("message 2 is 'user'"));
+        assert!(split_alternating_conversation(
+            "Anthropic",
+            vec![ChatMessage::new(ChatRole::Assistant, "Hi")]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn split_rejects_late_system_messages() {
+        let result = split_alternating_conversation(
+            "Anthropic",
+            vec![
+                ChatMessage::new(ChatRole::User, "Hi"),
+                ChatMessage::new(ChatRole::System, "Late"),
+            ],
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn split_checks_tool_block_roles() {
+        let tool_use = ContentBlock::ToolUse {
+            id: "toolu_1".to_string(),
+            name: "add_node".to_string(),
+            input: serde_json::json!({ "type": "text" }),
+        };
+        let tool_result = ContentBlock::ToolResult {
+            tool_use_id: "toolu_1".to_string(),
+            content: Some("done".into()),
+            is_error: None,
+        };
+
+        assert!(split_alternating_conversation(
+            "Anthropic",
+            vec![
+                ChatMessage::new(ChatRole::User, "Add a node"),
+                ChatMessage::new(
+                    ChatRole::Assistant,
+                    MessageContent::Blocks(vec![tool_use.clone()])
+                ),
+                ChatMessage::new(
+                    ChatRole::User,
+                    MessageContent::Blocks(vec![tool_result.clone()])
+                ),
+            ]
+        )
+        .is_ok());
+        assert!(split_alternating_conversation(
+            "Anthropic",
+            vec![ChatMessage::new(
+                ChatRole::User,
+                MessageContent::Blocks(vec![tool_use])
+            )]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn content_deserializes_from_string_or_blocks() {
+        let message: ChatMessage = serde_json::from_value(serde_json::json!({
+            "role": "assistant",
+            "content": [
+                { "type": "text", "text": "Adding" },
+                { "type": "tool_use", "id": "toolu_1", "name": "add_node", "input": {} }
+            ]
+        }))
+        .unwrap();
+
+        match message.content {
+            MessageContent::Blocks(blocks) => assert_eq!(blocks[1].type_name(), "tool_use"),
+            other => panic!("expected blocks, got {:?}", other),
+        }
+        assert_eq!(
+            MessageContent::from("plain").into_text().unwrap(),
+            "plain".to_string()
+        );
+    }
+
+    #[test]
+    fn require_user_message_rejects_system_only_conversations() {
+        assert!(require_user_message(&[ChatMessage::new(ChatRole::System, "Only")]).is_err());
+        assert!(require_user_message(&[
+            ChatMessage::new(ChatRole::System, "Sys"),
+            ChatMessage::new(ChatRole::User, "Hi"),
+            ChatMessage::new(ChatRole::User, "Again"),
+        ])
+        .is_ok());
+    }
+}
@@ -0,0 +1,399 @@
+use async_trait::async_trait;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use tokio_util::io::ReaderStream;
+
+use super::budget::reserve_within_budget;
+use super::ledger::{self, CallContext, LedgerEntry};
+use super::provider::{
+    AuthScheme, Capability, GenerateRequest, GenerateResponse, Provider, ProviderClient,
+    ProviderContext,
+};
+use super::retry::Idempotency;
+use crate::cancel::cancellable;
+use crate::error::CommandError;
+use crate::settings::{load_settings, AppSettings};
+
+const FAL_RUN_BASE: &str = "https://fal.run";
+const FAL_QUEUE_BASE: &str = "https://queue.fal.run";
+const FAL_STORAGE_INITIATE_URL: &str =
+    "https://rest.alpha.fal.ai/storage/upload/initiate?storage_type=fal-cdn-v3";
+const MAX_UPLOAD_BYTES: u64 = 100 * 1024 * 1024;
+const UPLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FalQueueSubmission {
+    request_id: String,
+    status: Option<String>,
+    queue_position: Option<i64>,
+    response_url: Option<String>,
+    status_url: Option<String>,
+    cancel_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FalQueueStatus {
+    status: String,
+    queue_position: Option<i64>,
+    response_url: Option<String>,
+    #[serde(default, deserialize_with = "null_as_empty")]
+    logs: Vec<FalLog>,
+    #[serde(default)]
+    metrics: Option<serde_json::Value>,
+}
+
+impl FalQueueStatus {
+    fn inference_time(&self) -> Option<f64> {
+        self.metrics
+            .as_ref()
+            .and_then(|metrics| metrics.get("inference_time"))
+            .and_then(|value| value.as_f64())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FalLog {
+    message: String,
+    level: Option<String>,
+    source: Option<String>,
+    timestamp: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FalQueueResult {
+    request_id: String,
+    output: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FalCancelResult {
+    status: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FalFileUpload {
+    file_url: String,
+    file_name: String,
+    content_type: String,
+    size: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct FalUploadInitiateRequest<'a> {
+    content_type: &'a str,
+    file_name: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct FalUploadInitiateResponse {
+    upload_url: String,
+    file_url: String,
+}
+
+fn null_as_empty<'de, D>(deserializer: D) -> Result<Vec<FalLog>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Option::<Vec<FalLog>>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+pub struct FalProvider;
+
+#[async_trait]
+impl Provider for FalProvider {
+    fn id(&self) -> &'static str {
+        "fal"
+    }
+
+    fn name(&self) -> &'static str {
+        "fal"
+    }
+
+    fn capabilities(&self) -> &'static [Capability] {
+        &[
+            Capability::Image,
+            Capability::Video,
+            Capability::Audio,
+            Capability::Upscale,
+        ]
+    }
+
+    fn auth_scheme(&self) -> AuthScheme {
+        AuthScheme::Scheme("Key")
+    }
+
+    fn api_key(&self, settings: &AppSettings) -> Option<String> {
+        settings.fal_api_key.clone()
+    }
+
+    /// Uses the synchronous `fal.run` endpoint, which holds the connection until
+    /// the output is ready. Long jobs should go through the queue commands instead.
+    async fn generate(
+        &self,
+        context: &ProviderContext,
+        model: &str,
+        request: &GenerateRequest,
+    ) -> Result<GenerateResponse, CommandError> {
+        app_id(model)?;
+        let input = request.model_input()?;
+        let url = format!("{}/{}", FAL_RUN_BASE, model.trim_matches('/'));
+
+        let output: serde_json::Value = context
+            .client
+            .send_json_with(
+                context.client.post(&url).json(&input),
+                "run model",
+                Idempotency::CreatesJob,
+            )
+            .await?;
+
+        Ok(GenerateResponse {
+            status: "succeeded".to_string(),
+            output: Some(output),
+            ..Default::default()
+        })
+    }
+}
+
+/// `request_id` is the id a command can be cancelled by, which tags retry events;
+/// it is not fal's queue request id.
+async fn fal_client(
+    app_handle: tauri::AppHandle,
+    request_id: Option<&str>,
+) -> Result<ProviderClient, CommandError> {
+    let settings = load_settings(app_handle.clone()).await?;
+    let context = ProviderContext::new(&FalProvider, settings)?;
+    Ok(context.client.for_app(&app_handle, request_id))
+}
+
+/// Requests are submitted to the full endpoint id (`fal-ai/flux/dev`) but status,
+/// result and cancel live under the app id, which is only `owner/app`.
+fn app_id(endpoint_id: &str) -> Result<String, CommandError> {
+    let segments: Vec<&str> = endpoint_id
+        .trim_matches('/')
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    if segments.len() < 2 {
+        return Err(CommandError::invalid_request(format!(
+            "Invalid fal endpoint '{}'. Expected 'owner/app' or 'owner/app/path'.",
+            endpoint_id
+        )));
+    }
+    Ok(format!("{}/{}", segments[0], segments[1]))
+}
+
+fn request_url(endpoint_id: &str, request_id: &str, suffix: &str) -> Result<String, CommandError> {
+    Ok(format!(
+        "{}/{}/requests/{}{}",
+        FAL_QUEUE_BASE,
+        app_id(endpoint_id)?,
+        request_id,
+        suffix
+    ))
+}
+
+#[tauri::command]
+pub async fn fal_queue_submit(
+    app_handle: tauri::AppHandle,
+    model: String,
+    input: serde_json::Value,
+    call_context: Option<CallContext>,
+) -> Result<FalQueueSubmission, CommandError> {
+    app_id(&model)?;
+    let reservation =
+        reserve_within_budget(&app_handle, "fal", &model, call_context.as_ref()).await?;
+    let client = fal_client(app_handle, None).await?;
+    let url = format!("{}/{}", FAL_QUEUE_BASE, model.trim_matches('/'));
+
+    let submission: FalQueueSubmission = client
+        .send_json_with(
+            client.post(&url).json(&input),
+            "submit request",
+            Idempotency::CreatesJob,
+        )
+        .await?;
+    reservation.hold(&submission.request_id);
+    Ok(submission)
+}
+
+#[tauri::command]
+pub async fn fal_queue_status(
+    app_handle: tauri::AppHandle,
+    model: String,
+    request_id: String,
+    call_context: Option<CallContext>,
+) -> Result<FalQueueStatus, CommandError> {
+    let url = request_url(&model, &request_id, "/status")?;
+    let client = fal_client(app_handle.clone(), None).await?;
+
+    let status: FalQueueStatus = client
+        .send_json(
+            client.get(&url).query(&[("logs", "1")]),
+            "get request status",
+        )
+        .await?;
+
+    if status.status == "COMPLETED" {
+        ledger::record(
+            &app_handle,
+            LedgerEntry::new("fal", &model, call_context.as_ref())
+                .with_job(&request_id, status.inference_time()),
+        );
+    }
+    Ok(status)
+}
+
+#[tauri::command]
+pub async fn fal_queue_result(
+    app_handle: tauri::AppHandle,
+    model: String,
+    request_id: String,
+) -> Result<FalQueueResult, CommandError> {
+    let url = request_url(&model, &request_id, "")?;
+    let client = fal_client(app_handle, None).await?;
+
+    let output: serde_json::Value = client
+        .send_json(client.get(&url), "get request result")
+        .await?;
+
+    Ok(FalQueueResult { request_id, output })
+}
+
+#[tauri::command]
+pub async fn fal_queue_cancel(
+    app_handle: tauri::AppHandle,
+    model: String,
+    request_id: String,
+) -> Result<FalCancelResult, CommandError> {
+    let url = request_url(&model, &request_id, "/cancel")?;
+    let client = fal_client(app_handle, None).await?;
+
+    // Not routed through `send`: an already finished request answers 400 with
+    // `ALREADY_COMPLETED`, which is still a well-formed outcome for the caller.
+    let response = client
+        .request(Method::PUT, &url)
+        .send()
+        .await
+        .map_err(|e| CommandError::network(Some("fal"), "cancel request", e))?;
+
+    let status = response.status();
+    let response_text = response.text().await.map_err(|e| e.to_string())?;
+
+    match serde_json::from_str::<FalCancelResult>(&response_text) {
+        Ok(result) if status.is_success() || result.status == "ALREADY_COMPLETED" => Ok(result),
+        _ => Err(CommandError::provider_status("fal", status, &response_text)),
+    }
+}
+
+#[tauri::command]
+pub async fn fal_upload_file(
+    app_handle: tauri::AppHandle,
+    file_path: String,
+    filename: String,
+    content_type: String,
+    request_id: Option<String>,
+) -> Result<FalFileUpload, CommandError> {
+    cancellable(request_id.as_deref(), async {
+        let file = tokio::fs::File::open(&file_path)
+            .await
+            .map_err(|e| format!("Failed to open file: {}", e))?;
+        let size = file
+            .metadata()
+            .await
+            .map_err(|e| format!("Failed to read file metadata: {}", e))?
+            .len();
+        if size > MAX_UPLOAD_BYTES {
+            return Err(CommandError::invalid_request(format!(
+                "{} is {} MB; uploads are limited to {} MB",
+                filename,
+                size / (1024 * 1024),
+                MAX_UPLOAD_BYTES / (1024 * 1024)
+            )));
+        }
+
+        let client = fal_client(app_handle, request_id.as_deref()).await?;
+
+        let initiated: FalUploadInitiateResponse = client
+            .send_json(
+                client
+                    .post(FAL_STORAGE_INITIATE_URL)
+                    .json(&FalUploadInitiateRequest {
+                        content_type: &content_type,
+                        file_name: &filename,
+                    }),
+                "initiate upload",
+            )
+            .await?;
+
+        // The signed upload URL must not receive the fal Authorization header. The
+        // file is streamed from disk; storage rejects chunked bodies, so the length
+        // is sent up front.
+        let http = client.http();
+        let (body, watch) = http.watch_upload(ReaderStream::with_capacity(file, UPLOAD_CHUNK_SIZE));
+        let upload = http
+            .client()
+            .put(&initiated.upload_url)
+            .header(CONTENT_TYPE, &content_type)
+            .header(CONTENT_LENGTH, size)
+            .body(body)
+            .send();
+        let response = watch
+            .until_stalled(async {
+                upload
+                    .await
+                    .map_err(|e| CommandError::network(Some("fal"), "upload file", e))
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(CommandError::from_response("fal", response).await);
+        }
+
+        Ok(FalFileUpload {
+            file_url: initiated.file_url,
+            file_name: filename,
+            content_type,
+            size: size as i64,
+        })
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_urls_use_the_app_id() {
+        assert_eq!(
+            request_url("fal-ai/flux/dev", "abc", "/status").unwrap(),
+            "https://queue.fal.run/fal-ai/flux/requests/abc/status"
+        );
+        assert_eq!(
+            request_url("fal-ai/fast-sdxl", "abc", "").unwrap(),
+            "https://queue.fal.run/fal-ai/fast-sdxl/requests/abc"
+        );
+        assert!(request_url("flux", "abc", "").is_err());
+    }
+
+    #[test]
+    fn status_parses_logs_and_tolerates_null() {
+        let status: FalQueueStatus = serde_json::from_value(serde_json::json!({
+            "status": "IN_PROGRESS",
+            "logs": [{ "message": "step 1/28", "level": "INFO", "timestamp": "2024-01-01T00:00:00Z" }]
+        }))
+        .unwrap();
+        assert_eq!(status.logs[0].message, "step 1/28");
+
+        let status: FalQueueStatus = serde_json::from_value(serde_json::json!({
+            "status": "IN_QUEUE",
+            "queue_position": 3,
+            "logs": null
+        }))
+        .unwrap();
+        assert!(status.logs.is_empty());
+        assert_eq!(status.queue_position, Some(3));
+    }
+}
@@ -0,0 +1,564 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::attachments::{attach_to_conversation, ChatAttachment};
+use super::budget::ensure_within_budget;
+use super::cache::cached;
+use super::chat::{
+    build_conversation, split_alternating_conversation, ChatMessage, ChatRole, ContentBlock,
+    MediaSource, MessageContent,
+};
+use super::ledger::{self, CallContext, LedgerEntry};
+use super::provider::{
+    AuthScheme, Capability, GenerateRequest, GenerateResponse, Provider, ProviderClient,
+    ProviderContext,
+};
+use super::stream::{emit_delta, finish_stream, ChatStreamResult, SseParser, TokenUsage};
+use crate::cancel::cancellable;
+use crate::error::CommandError;
+use crate::settings::{load_settings, AppSettings};
+
+const GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiModel {
+    name: String,
+    display_name: Option<String>,
+    description: Option<String>,
+    input_token_limit: Option<u32>,
+    output_token_limit: Option<u32>,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiModelsResponse {
+    #[serde(default)]
+    models: Vec<GeminiModel>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiRequest {
+    contents: Vec<GeminiContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<GeminiContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generation_config: Option<GeminiGenerationConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct GeminiContent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    #[serde(default)]
+    parts: Vec<GeminiPart>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct GeminiPart {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inline_data: Option<GeminiBlob>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file_data: Option<GeminiFileData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thought: Option<bool>,
+}
+
+impl GeminiPart {
+    fn text(text: String) -> Self {
+        GeminiPart {
+            text: Some(text),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct GeminiBlob {
+    mime_type: String,
+    data: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct GeminiFileData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mime_type: Option<String>,
+    file_uri: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiGenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiResponse {
+    #[serde(default)]
+    candidates: Vec<GeminiCandidate>,
+    prompt_feedback: Option<GeminiPromptFeedback>,
+    usage_metadata: Option<GeminiUsageMetadata>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiCandidate {
+    content: Option<GeminiContent>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiPromptFeedback {
+    block_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiUsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    candidates_token_count: u32,
+}
+
+impl GeminiResponse {
+    fn text(&self) -> String {
+        self.candidates
+            .first()
+            .and_then(|candidate| candidate.content.as_ref())
+            .map(|content| {
+                content
+                    .parts
+                    .iter()
+                    .filter(|part| part.thought != Some(true))
+                    .filter_map(|part| part.text.as_deref())
+                    .collect::<Vec<_>>()
+                    .join("")
+            })
+            .unwrap_or_default()
+    }
+
+    fn finish_reason(&self) -> Option<String> {
+        self.candidates
+            .first()
+            .and_then(|candidate| candidate.finish_reason.clone())
+    }
+
+    fn block_reason(&self) -> Option<String> {
+        self.prompt_feedback
+            .as_ref()
+            .and_then(|feedback| feedback.block_reason.clone())
+    }
+
+    fn token_usage(&self) -> Option<TokenUsage> {
+        self.usage_metadata.as_ref().map(|usage| TokenUsage {
+            input_tokens: usage.prompt_token_count,
+            output_tokens: usage.candidates_token_count,
+        })
+    }
+}
+
+/// The client the Gemini commands send through, authenticated with the key from
+/// Settings.
+async fn gemini_client(
+    app_handle: &tauri::AppHandle,
+    request_id: Option<&str>,
+) -> Result<ProviderClient, CommandError> {
+    let settings = load_settings(app_handle.clone()).await?;
+    let context = ProviderContext::new(&GeminiProvider, settings)?;
+    Ok(context.client.for_app(app_handle, request_id))
+}
+
+/// Accepts both `gemini-2.0-flash` and the `models/gemini-2.0-flash` names returned
+/// by `gemini_list_models`.
+fn model_path(model: &str) -> String {
+    if model.starts_with("models/") || model.starts_with("tunedModels/") {
+        model.to_string()
+    } else {
+        format!("models/{}", model)
+    }
+}
+
+fn to_gemini_parts(content: MessageContent) -> Result<Vec<GeminiPart>, String> {
+    let blocks = match content {
+        MessageContent::Text(text) => return Ok(vec![GeminiPart::text(text)]),
+        MessageContent::Blocks(blocks) => blocks,
+    };
+
+    blocks
+        .into_iter()
+        .map(|block| match block {
+            ContentBlock::Text { text } => Ok(GeminiPart::text(text)),
+            ContentBlock::Image { source } | ContentBlock::Document { source, .. } => {
+                Ok(match source {
+                    MediaSource::Base64 { media_type, data } => GeminiPart {
+                        inline_data: Some(GeminiBlob {
+                            mime_type: media_type,
+                            data,
+                        }),
+                        ..Default::default()
+                    },
+                    MediaSource::Url { url } => GeminiPart {
+                        file_data: Some(GeminiFileData {
+                            mime_type: None,
+                            file_uri: url,
+                        }),
+                        ..Default::default()
+                    },
+                })
+            }
+            other => Err(format!(
+                "Content block '{}' is not supported by Gemini",
+                other.type_name()
+            )),
+        })
+        .collect()
+}
+
+fn build_request(
+    conversation: Vec<ChatMessage>,
+    temperature: Option<f32>,
+) -> Result<GeminiRequest, CommandError> {
+    let (system, turns) = split_alternating_conversation("Gemini", conversation)
+        .map_err(CommandError::invalid_request)?;
+
+    let contents = turns
+        .into_iter()
+        .map(|message| {
+            let role = match message.role {
+                ChatRole::Assistant => "model",
+                _ => "user",
+            };
+            Ok(GeminiContent {
+                role: Some(role.to_string()),
+                parts: to_gemini_parts(message.content)?,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(CommandError::invalid_request)?;
+
+    Ok(GeminiRequest {
+        contents,
+        system_instruction: system.map(|text| GeminiContent {
+            role: None,
+            parts: vec![GeminiPart::text(text)],
+        }),
+        generation_config: temperature.map(|temperature| GeminiGenerationConfig {
+            temperature: Some(temperature),
+        }),
+    })
+}
+
+pub struct GeminiProvider;
+
+#[async_trait]
+impl Provider for GeminiProvider {
+    fn id(&self) -> &'static str {
+        "google"
+    }
+
+    fn name(&self) -> &'static str {
+        "Gemini"
+    }
+
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::Text]
+    }
+
+    fn auth_scheme(&self) -> AuthScheme {
+        AuthScheme::Header("x-goog-api-key")
+    }
+
+    fn api_key(&self, settings: &AppSettings) -> Option<String> {
+        settings.gemini_api_key.clone()
+    }
+
+    async fn generate(
+        &self,
+        context: &ProviderContext,
+        model: &str,
+        request: &GenerateRequest,
+    ) -> Result<GenerateResponse, CommandError> {
+        let conversation = request.conversation(context.client.http()).await?;
+        let request_body = build_request(conversation, request.temperature)?;
+        let url = format!("{}/{}:generateContent", GEMINI_API_BASE, model_path(model));
+
+        let response_data: GeminiResponse = context
+            .client
+            .send_json(
+                context.client.post(&url).json(&request_body),
+                "generate content",
+            )
+            .await?;
+
+        if let Some(reason) = response_data.block_reason() {
+            return Err(CommandError::provider_failed(
+                "Gemini",
+                format!("blocked the prompt: {}", reason),
+            ));
+        }
+        if response_data.candidates.is_empty() {
+            return Err("No content in Gemini response".into());
+        }
+
+        Ok(GenerateResponse {
+            usage: response_data.token_usage(),
+            stop_reason: response_data.finish_reason(),
+            ..GenerateResponse::text(response_data.text())
+        })
+    }
+}
+
+#[tauri::command]
+pub async fn gemini_list_models(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<GeminiModel>, CommandError> {
+    let client = gemini_client(&app_handle, None).await?;
+    let mut models = Vec::new();
+    let mut page_token: Option<String> = None;
+
+    loop {
+        let mut request = client
+            .get(&format!("{}/models", GEMINI_API_BASE))
+            .query(&[("pageSize", "1000")]);
+        if let Some(token) = &page_token {
+            request = request.query(&[("pageToken", token)]);
+        }
+
+        let page: GeminiModelsResponse = client.send_json(request, "fetch models").await?;
+
+        models.extend(page.models);
+        page_token = page.next_page_token.filter(|token| !token.is_empty());
+        if page_token.is_none() {
+            break;
+        }
+    }
+
+    Ok(models)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn gemini_generate_content(
+    app_handle: tauri::AppHandle,
+    model: String,
+    system_prompt: Option<String>,
+    user_content: Option<String>,
+    temperature: Option<f32>,
+    messages: Option<Vec<ChatMessage>>,
+    attachments: Option<Vec<ChatAttachment>>,
+    call_context: Option<CallContext>,
+    request_id: Option<String>,
+) -> Result<String, CommandError> {
+    cancellable(request_id.as_deref(), async {
+        let client = gemini_client(&app_handle, request_id.as_deref()).await?;
+        let mut conversation = build_conversation(system_prompt, messages, user_content);
+        attach_to_conversation(&mut conversation, attachments, client.http()).await?;
+        let request_body = build_request(conversation, temperature)?;
+
+        cached(&app_handle, "google", &model, &request_body, async {
+            ensure_within_budget(&app_handle, "google", call_context.as_ref()).await?;
+            let url = format!("{}/{}:generateContent", GEMINI_API_BASE, model_path(&model));
+
+            let response_data: GeminiResponse = client
+                .send_json(client.post(&url).json(&request_body), "generate content")
+                .await?;
+
+            if let Some(reason) = response_data.block_reason() {
+                return Err(CommandError::provider_failed(
+                    "Gemini",
+                    format!("blocked the prompt: {}", reason),
+                ));
+            }
+            if response_data.candidates.is_empty() {
+                return Err("No content in Gemini response".into());
+            }
+
+            ledger::record(
+                &app_handle,
+                LedgerEntry::new("google", &model, call_context.as_ref())
+                    .with_usage(response_data.token_usage().as_ref()),
+            );
+
+            Ok(response_data.text())
+        })
+        .await
+    })
+    .await
+}
+
+/// Streams generated content over SSE, emitting `chat-stream` deltas tagged with `request_id`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn gemini_stream_generate_content(
+    app_handle: tauri::AppHandle,
+    request_id: String,
+    model: String,
+    system_prompt: Option<String>,
+    user_content: Option<String>,
+    temperature: Option<f32>,
+    messages: Option<Vec<ChatMessage>>,
+    attachments: Option<Vec<ChatAttachment>>,
+    call_context: Option<CallContext>,
+) -> Result<ChatStreamResult, CommandError> {
+    let result = cancellable(Some(&request_id), async {
+        ensure_within_budget(&app_handle, "google", call_context.as_ref()).await?;
+        let client = gemini_client(&app_handle, Some(&request_id)).await?;
+        let mut conversation = build_conversation(system_prompt, messages, user_content);
+        attach_to_conversation(&mut conversation, attachments, client.http()).await?;
+        let request_body = build_request(conversation, temperature)?;
+        let done = stream_content(&app_handle, &client, &request_id, &model, &request_body).await?;
+        ledger::record(
+            &app_handle,
+            LedgerEntry::new("google", &model, call_context.as_ref())
+                .with_usage(done.usage.as_ref()),
+        );
+        Ok(done)
+    })
+    .await;
+    finish_stream(&app_handle, &request_id, result)
+}
+
+async fn stream_content(
+    app_handle: &tauri::AppHandle,
+    client: &ProviderClient,
+    request_id: &str,
+    model: &str,
+    request_body: &GeminiRequest,
+) -> Result<ChatStreamResult, CommandError> {
+    let url = format!(
+        "{}/{}:streamGenerateContent",
+        GEMINI_API_BASE,
+        model_path(model)
+    );
+
+    let mut response = client
+        .send(
+            client
+                .post(&url)
+                .query(&[("alt", "sse")])
+                .json(request_body),
+            "generate content",
+        )
+        .await?;
+
+    let mut parser = SseParser::default();
+    let mut result = ChatStreamResult::default();
+
+    loop {
+        let chunk = client.http().next_chunk(&mut response).await?;
+        let events = match &chunk {
+            Some(bytes) => parser.push(bytes),
+            None => parser.finish().into_iter().collect(),
+        };
+
+        for event in events {
+            let parsed: GeminiResponse = serde_json::from_str(&event.data)
+                .map_err(|e| format!("Failed to parse stream chunk: {}", e))?;
+
+            if let Some(reason) = parsed.block_reason() {
+                return Err(CommandError::provider_failed(
+                    "Gemini",
+                    format!("blocked the prompt: {}", reason),
+                ));
+            }
+
+            let text = parsed.text();
+            if !text.is_empty() {
+                emit_delta(app_handle, request_id, &text);
+                result.text.push_str(&text);
+            }
+            if let Some(reason) = parsed.finish_reason() {
+                result.stop_reason = Some(reason);
+            }
+            if let Some(usage) = parsed.token_usage() {
+                result.usage = Some(usage);
+            }
+        }
+
+        if chunk.is_none() {
+            break;
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_maps_roles_system_and_inline_data() {
+        let conversation = vec![
+            ChatMessage::new(ChatRole::System, "Describe images"),
+            ChatMessage::new(ChatRole::User, "Hi"),
+            ChatMessage::new(ChatRole::Assistant, "Hello"),
+            ChatMessage::new(
+                ChatRole::User,
+                MessageContent::Blocks(vec![
+                    ContentBlock::Image {
+                        source: MediaSource::Base64 {
+                            media_type: "image/png".to_string(),
+                            data: "cG5n".to_string(),
+                        },
+                    },
+                    ContentBlock::Text {
+                        text: "What is this?".to_string(),
+                    },
+                ]),
+            ),
+        ];
+
+        let request =
+            serde_json::to_value(build_request(conversation, Some(0.2)).unwrap()).unwrap();
+
+        assert_eq!(
+            request["systemInstruction"],
+            serde_json::json!({ "parts": [{ "text": "Describe images" }] })
+        );
+        assert_eq!(request["contents"][1]["role"], "model");
+        assert_eq!(
+            request["contents"][2]["parts"][0],
+            serde_json::json!({ "inlineData": { "mimeType": "image/png", "data": "cG5n" } })
+        );
+        assert!((request["generationConfig"]["temperature"].as_f64().unwrap() - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn response_text_joins_parts_and_skips_unknown_ones() {
+        let response: GeminiResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [
+                        { "text": "Thinking...", "thought": true },
+                        { "text": "Hello" },
+                        { "executableCode": {} },
+                        { "text": " there" }
+                    ]
+                },
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": { "promptTokenCount": 3, "candidatesTokenCount": 2 }
+        }))
+        .unwrap();
+
+        assert_eq!(response.text(), "Hello there");
+        assert_eq!(response.finish_reason().as_deref(), Some("STOP"));
+        assert_eq!(model_path("gemini-2.0-flash"), "models/gemini-2.0-flash");
+        assert_eq!(model_path("models/gemini-pro"), "models/gemini-pro");
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use super::budget;
+use super::stream::TokenUsage;
+use crate::error::CommandError;
+
+const LEDGER_FILE: &str = "usage-ledger.jsonl";
+
+/// Ledger file and the `(provider, job_id)` pairs it already holds.
+type RecordedJobs = (PathBuf, HashSet<(String, String)>);
+
+/// Serializes appends so concurrent calls cannot interleave partial lines. It also
+/// holds the jobs already recorded, read once per ledger file, so polling a finished
+/// job does not re-read the whole ledger.
+static LEDGER_LOCK: Mutex<Option<RecordedJobs>> = Mutex::new(None);
+
+/// USD per million input and output tokens, matched by model id prefix. More
+/// specific prefixes must come before shorter ones they share a stem with.
+const TOKEN_PRICES: &[(&str, f64, f64)] = &[
+    ("claude-opus-4", 15.0, 75.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-haiku-4", 1.0, 5.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1", 2.0, 8.0),
+    ("o4-mini", 1.1, 4.4),
+    ("o3-pro", 20.0, 80.0),
+    ("o3-mini", 1.1, 4.4),
+    ("o3", 2.0, 8.0),
+    ("gemini-2.5-pro", 1.25, 10.0),
+    ("gemini-2.5-flash-lite", 0.1, 0.4),
+    ("gemini-2.5-flash", 0.3, 2.5),
+    ("gemini-2.0-flash", 0.1, 0.4),
+];
+
+/// USD per second of billed compute. Neither Replicate nor fal report the hardware
+/// a run used, so this is the A100 rate: high for small models, which keeps spending
+/// limits on the safe side.
+const COMPUTE_PRICES: &[(&str, f64)] = &[("replicate", 0.0014), ("fal", 0.0014)];
+
+/// Where in a workflow a provider call came from. Every field is optional so
+/// calls made outside a workflow run (e.g. the assistant panel) are still recorded.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct CallContext {
+    #[serde(default)]
+    pub workflow_id: Option<String>,
+    #[serde(default)]
+    pub run_id: Option<String>,
+    #[serde(default)]
+    pub node_id: Option<String>,
+}
+
+/// One provider call. Text providers fill in tokens, model-hosting providers the
+/// billed compute time in seconds.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LedgerEntry {
+    pub timestamp: String,
+    pub provider: String,
+    pub model: String,
+    #[serde(flatten)]
+    pub context: CallContext,
+    #[serde(default)]
+    pub job_id: Option<String>,
+    /// `None` when the provider did not report usage for the call.
+    #[serde(default)]
+    pub input_tokens: Option<u32>,
+    #[serde(default)]
+    pub output_tokens: Option<u32>,
+    #[serde(default)]
+    pub predict_time: Option<f64>,
+    /// `None` when the price is unknown, e.g. a custom endpoint or a job without timing.
+    #[serde(default)]
+    pub estimated_cost: Option<f64>,
+}
+
+impl LedgerEntry {
+    pub fn new(provider: &str, model: &str, context: Option<&CallContext>) -> Self {
+        LedgerEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            provider: provider.to_string(),
+            model: model.to_string(),
+            context: context.cloned().unwrap_or_default(),
+            job_id: None,
+            input_tokens: None,
+            output_tokens: None,
+            predict_time: None,
+            estimated_cost: None,
+        }
+    }
+
+    pub fn with_usage(mut self, usage: Option<&TokenUsage>) -> Self {
+        if let Some(usage) = usage {
+            self.input_tokens = Some(usage.input_tokens);
+            self.output_tokens = Some(usage.output_tokens);
+        }
+        self
+    }
+
+    pub fn with_job(mut self, job_id: &str, predict_time: Option<f64>) -> Self {
+        self.job_id = Some(job_id.to_string());
+        self.predict_time = predict_time;
+        self
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageGrouping {
+    Day,
+    Workflow,
+    Provider,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct UsageSummary {
+    /// The day (`YYYY-MM-DD`, UTC), workflow id or provider id; calls without a
+    /// workflow are grouped under an empty key.
+    pub key: String,
+    pub calls: u32,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub predict_time: f64,
+    pub estimated_cost: f64,
+    /// Calls whose cost could not be estimated and are missing from `estimated_cost`.
+    pub unpriced_calls: u32,
+}
+
+/// Local providers are free; hosted text models are priced from `TOKEN_PRICES` when
+/// the call reported its usage, and compute jobs from `COMPUTE_PRICES`. OpenRouter ids carry a vendor prefix
+/// (`anthropic/claude-sonnet-4`), which is ignored for the lookup.
+pub fn estimate_cost(entry: &LedgerEntry) -> Option<f64> {
+    match entry.provider.as_str() {
+        "ollama" | "lmstudio" => return Some(0.0),
+        "anthropic" | "openai" | "google" | "openrouter" => {}
+        provider => {
+            let predict_time = entry.predict_time?;
+            return COMPUTE_PRICES
+                .iter()
+                .find(|(id, _)| *id == provider)
+                .map(|(_, price)| predict_time * price);
+        }
+    }
+
+    let (input_tokens, output_tokens) = (entry.input_tokens?, entry.output_tokens?);
+    let model = entry.model.rsplit('/').next().unwrap_or(&entry.model);
+    let model = model.strip_prefix("models/").unwrap_or(model);
+    TOKEN_PRICES
+        .iter()
+        .find(|(prefix, _, _)| model.starts_with(prefix))
+        .map(|(_, input_price, output_price)| {
+            (input_tokens as f64 * input_price + output_tokens as f64 * output_price) / 1_000_000.0
+        })
+}
+
+fn ledger_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let app_data = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    fs::create_dir_all(&app_data)
+        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    Ok(app_data.join(LEDGER_FILE))
+}
+
+/// Every recorded call, oldest first.
+pub fn load_entries(app_handle: &tauri::AppHandle) -> Result<Vec<LedgerEntry>, String> {
+    read_entries(&ledger_path(app_handle)?)
+}
+
+fn read_entries(path: &PathBuf) -> Result<Vec<LedgerEntry>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read usage ledger: {}", e))?;
+
+    // A line cut short by a crash should not hide the rest of the history.
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn append_entry(path: &PathBuf, mut entry: LedgerEntry) -> Result<(), String> {
+    let mut recorded = LEDGER_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let jobs = match &mut *recorded {
+        Some((recorded_path, jobs)) if recorded_path == path => jobs,
+        slot => {
+            let jobs = read_entries(path)?
+                .into_iter()
+                .filter_map(|existing| Some((existing.provider, existing.job_id?)))
+                .collect();
+            &mut slot.insert((path.clone(), jobs)).1
+        }
+    };
+
+    // Polling commands see a finished job more than once; bill it only the first time.
+    let job = entry
+        .job_id
+        .clone()
+        .map(|job_id| (entry.provider.clone(), job_id));
+    if job.as_ref().is_some_and(|job| jobs.contains(job)) {
+        return Ok(());
+    }
+
+    // A caller-supplied estimate stands in when the call reported nothing to price.
+    entry.estimated_cost = estimate_cost(&entry).or(entry.estimated_cost);
+    let line =
+        serde_json::to_string(&entry).map_err(|e| format!("Failed to serialize entry: {}", e))?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open usage ledger: {}", e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write usage ledger: {}", e))?;
+    jobs.extend(job);
+    Ok(())
+}
+
+/// Appends a call to the ledger. Accounting must never fail the call it
+/// describes, so errors are only logged. A recorded job's real cost replaces its
+/// budget reservation.
+pub fn record(app_handle: &tauri::AppHandle, entry: LedgerEntry) {
+    let job = entry
+        .job_id
+        .clone()
+        .map(|job_id| (entry.provider.clone(), job_id));
+    if let Err(e) = ledger_path(app_handle).and_then(|path| append_entry(&path, entry)) {
+        eprintln!("Failed to record provider usage: {}", e);
+    }
+    if let Some((provider, job_id)) = job {
+        budget::settle(&provider, &job_id);
+    }
+}
+
+/// Aggregates entries whose timestamp falls in `[since, until)`. Bounds are compared
+/// as strings, so both dates (`2024-05-01`) and RFC 3339 timestamps work.
+fn summarize(
+    entries: &[LedgerEntry],
+    group_by: UsageGrouping,
+    since: Option<&str>,
+    until: Option<&str>,
+) -> Vec<UsageSummary> {
+    let mut buckets: BTreeMap<String, UsageSummary> = BTreeMap::new();
+
+    for entry in entries {
+        if since.is_some_and(|since| entry.timestamp.as_str() < since)
+            || until.is_some_and(|until| entry.timestamp.as_str() >= until)
+        {
+            continue;
+        }
+
+        let key = match group_by {
+            UsageGrouping::Day => entry.timestamp.get(..10).unwrap_or_default().to_string(),
+            UsageGrouping::Workflow => entry.context.workflow_id.clone().unwrap_or_default(),
+            UsageGrouping::Provider => entry.provider.clone(),
+        };
+
+        let bucket = buckets.entry(key.clone()).or_insert_with(|| UsageSummary {
+            key,
+            ..Default::default()
+        });
+        bucket.calls += 1;
+        bucket.input_tokens += entry.input_tokens.unwrap_or(0) as u64;
+        bucket.output_tokens += entry.output_tokens.unwrap_or(0) as u64;
+        bucket.predict_time += entry.predict_time.unwrap_or(0.0);
+        match entry.estimated_cost {
+            Some(cost) => bucket.estimated_cost += cost,
+            None => bucket.unpriced_calls += 1,
+        }
+    }
+
+    buckets.into_values().collect()
+}
+
+#[tauri::command]
+pub async fn usage_summary(
+    app_handle: tauri::AppHandle,
+    group_by: UsageGrouping,
+    since: Option<String>,
+    until: Option<String>,
+) -> Result<Vec<UsageSummary>, CommandError> {
+    let entries = load_entries(&app_handle)?;
+    Ok(summarize(
+        &entries,
+        group_by,
+        since.as_deref(),
+        until.as_deref(),
+    ))
+}
+
+/// Most recent entries first, optionally limited to one workflow.
+#[tauri::command]
+pub async fn list_usage_entries(
+    app_handle: tauri::AppHandle,
+    workflow_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<LedgerEntry>, CommandError> {
+    let entries = load_entries(&app_handle)?;
+
+    Ok(entries
+        .into_iter()
+        .rev()
+        .filter(|entry| {
+            workflow_id.is_none() || entry.context.workflow_id.as_ref() == workflow_id.as_ref()
+        })
+        .take(limit.unwrap_or(usize::MAX))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(
+        timestamp: &str,
+        provider: &str,
+        model: &str,
+        workflow_id: Option<&str>,
+    ) -> LedgerEntry {
+        let mut entry = LedgerEntry::new(
+            provider,
+            model,
+            Some(&CallContext {
+                workflow_id: workflow_id.map(str::to_string),
+                ..Default::default()
+            }),
+        );
+        entry.timestamp = timestamp.to_string();
+        entry
+    }
+
+    #[test]
+    fn estimates_token_cost_by_model_prefix() {
+        let usage = TokenUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+        };
+
+        let priced = entry("", "anthropic", "claude-sonnet-4-5", None).with_usage(Some(&usage));
+        assert_eq!(estimate_cost(&priced), Some(4.5));
+
+        let routed = entry("", "openrouter", "openai/gpt-4o-mini", None).with_usage(Some(&usage));
+        assert_eq!(estimate_cost(&routed), Some(0.21));
+
+        let pro = entry("", "openai", "o3-pro-2025-06-10", None).with_usage(Some(&usage));
+        assert_eq!(estimate_cost(&pro), Some(28.0));
+
+        let local = entry("", "ollama", "llama3.2", None).with_usage(Some(&usage));
+        assert_eq!(estimate_cost(&local), Some(0.0));
+
+        let compute = entry("", "replicate", "black-forest-labs/flux-schnell", None)
+            .with_job("abc", Some(2.5));
+        assert_eq!(estimate_cost(&compute), Some(0.0035));
+
+        let untimed = entry("", "fal", "fal-ai/flux/dev", None).with_job("abc", None);
+        assert_eq!(estimate_cost(&untimed), None);
+
+        let unreported = entry("", "openai", "gpt-4o", None).with_usage(None);
+        assert_eq!(estimate_cost(&unreported), None);
+    }
+
+    #[test]
+    fn summarizes_by_day_workflow_and_provider() {
+        let mut first = entry(
+            "2024-05-01T10:00:00+00:00",
+            "openai",
+            "gpt-4o",
+            Some("wf-1"),
+        );
+        first.input_tokens = Some(10);
+        first.estimated_cost = Some(0.5);
+        let mut second = entry(
+            "2024-05-01T23:00:00+00:00",
+            "replicate",
+            "flux",
+            Some("wf-1"),
+        );
+        second.predict_time = Some(3.0);
+        let mut third = entry("2024-05-02T08:00:00+00:00", "openai", "gpt-4o", None);
+        third.estimated_cost = Some(0.25);
+        let entries = vec![first, second, third];
+
+        let by_day = summarize(&entries, UsageGrouping::Day, None, None);
+        assert_eq!(by_day.len(), 2);
+        assert_eq!(by_day[0].key, "2024-05-01");
+        assert_eq!(by_day[0].calls, 2);
+        assert_eq!(by_day[0].unpriced_calls, 1);
+        assert_eq!(by_day[0].predict_time, 3.0);
+
+        let by_workflow = summarize(&entries, UsageGrouping::Workflow, None, None);
+        assert_eq!(by_workflow[0].key, "");
+        assert_eq!(by_workflow[1].key, "wf-1");
+        assert_eq!(by_workflow[1].input_tokens, 10);
+
+        let by_provider = summarize(&entries, UsageGrouping::Provider, Some("2024-05-02"), None);
+        assert_eq!(by_provider.len(), 1);
+        assert_eq!(by_provider[0].key, "openai");
+        assert_eq!(by_provider[0].estimated_cost, 0.25);
+    }
+
+    #[test]
+    fn append_skips_jobs_already_recorded() {
+        let path =
+            std::env::temp_dir().join(format!("noder-ledger-test-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let job = LedgerEntry::new("replicate", "flux", None).with_job("abc", Some(1.5));
+        append_entry(&path, job.clone()).unwrap();
+        append_entry(&path, job).unwrap();
+        append_entry(&path, LedgerEntry::new("openai", "gpt-4o", None)).unwrap();
+        let mut untimed = LedgerEntry::new("fal", "fal-ai/flux/dev", None);
+        untimed.estimated_cost = Some(0.084);
+        append_entry(&path, untimed).unwrap();
+
+        let entries = read_entries(&path).unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].predict_time, Some(1.5));
+        assert_eq!(entries[1].estimated_cost, None);
+        assert_eq!(entries[2].estimated_cost, Some(0.084));
+    }
+}
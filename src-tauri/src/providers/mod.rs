@@ -0,0 +1,19 @@
+pub mod anthropic;
+pub mod attachments;
+pub mod budget;
+pub mod cache;
+pub mod chat;
+pub mod fal;
+pub mod gemini;
+pub mod ledger;
+pub mod ollama;
+pub mod openai;
+pub mod openrouter;
+pub mod provider;
+pub mod registry;
+pub mod replicate;
+pub mod replicate_catalog;
+pub mod replicate_files;
+pub mod replicate_schema;
+pub mod retry;
+pub mod stream;
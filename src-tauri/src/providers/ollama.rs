@@ -0,0 +1,718 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+
+use super::attachments::{attach_to_conversation, ChatAttachment};
+use super::chat::{
+    build_conversation, require_user_message, ChatMessage, ChatRole, ContentBlock, MediaSource,
+    MessageContent,
+};
+use super::ledger::{self, CallContext, LedgerEntry};
+use super::provider::{
+    AuthScheme, Capability, GenerateRequest, GenerateResponse, Provider, ProviderContext,
+};
+use super::stream::{emit_delta, finish_stream, ChatStreamResult, NdjsonParser, TokenUsage};
+use crate::cancel::cancellable;
+use crate::error::CommandError;
+use crate::http::{self, HttpClient};
+use crate::settings::{load_settings, AppSettings};
+
+const DEFAULT_OLLAMA_BASE_URL: &str = "http://localhost:11434";
+
+pub const OLLAMA_PULL_EVENT: &str = "ollama-pull-progress";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OllamaModel {
+    name: String,
+    model: Option<String>,
+    modified_at: Option<String>,
+    size: Option<u64>,
+    digest: Option<String>,
+    details: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaTagsResponse {
+    #[serde(default)]
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct OllamaMessage {
+    role: String,
+    content: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    images: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaOptions {
+    temperature: f32,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaChatRequest {
+    model: String,
+    messages: Vec<OllamaMessage>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<OllamaOptions>,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaGenerateRequest {
+    model: String,
+    prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    images: Vec<String>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<OllamaOptions>,
+}
+
+/// One line of a `/api/chat` or `/api/generate` stream. Chat carries text in
+/// `message.content`, generate in `response`.
+#[derive(Debug, Deserialize)]
+struct OllamaStreamChunk {
+    message: Option<OllamaMessage>,
+    response: Option<String>,
+    #[serde(default)]
+    done: bool,
+    done_reason: Option<String>,
+    prompt_eval_count: Option<u32>,
+    eval_count: Option<u32>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaPullRequest {
+    model: String,
+    stream: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct OllamaPullProgress {
+    #[serde(default)]
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed: Option<u64>,
+    #[serde(default, skip_serializing)]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct OllamaPullEvent {
+    request_id: String,
+    model: String,
+    #[serde(flatten)]
+    progress: OllamaPullProgress,
+}
+
+async fn load_base_url(app_handle: tauri::AppHandle) -> Result<String, String> {
+    let settings = load_settings(app_handle).await?;
+    Ok(normalize_base_url(settings.ollama_base_url.as_deref()))
+}
+
+fn normalize_base_url(base_url: Option<&str>) -> String {
+    base_url
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .unwrap_or(DEFAULT_OLLAMA_BASE_URL)
+        .trim_end_matches('/')
+        .to_string()
+}
+
+fn connection_error(base_url: &str, error: reqwest::Error) -> CommandError {
+    CommandError::Network {
+        provider: Some("Ollama".to_string()),
+        timeout: error.is_timeout(),
+        message: format!(
+            "Failed to reach Ollama at {}: {}. Is Ollama running?",
+            base_url, error
+        ),
+    }
+}
+
+async fn error_from_response(response: reqwest::Response) -> CommandError {
+    CommandError::from_response("Ollama", response).await
+}
+
+fn ollama_options(temperature: Option<f32>) -> Option<OllamaOptions> {
+    temperature.map(|temperature| OllamaOptions { temperature })
+}
+
+/// Ollama takes plain text plus a list of base64 images per message.
+fn to_ollama_message(message: ChatMessage) -> Result<OllamaMessage, String> {
+    let role = message.role.as_str().to_string();
+    let blocks = match message.content {
+        MessageContent::Text(content) => {
+            return Ok(OllamaMessage {
+                role,
+                content,
+                images: Vec::new(),
+            })
+        }
+        MessageContent::Blocks(blocks) => blocks,
+    };
+
+    let mut text = Vec::new();
+    let mut images = Vec::new();
+    for block in blocks {
+        match block {
+            ContentBlock::Text { text: part } => text.push(part),
+            ContentBlock::Image {
+                source: MediaSource::Base64 { data, .. },
+            } => images.push(data),
+            ContentBlock::Image { .. } => {
+                return Err("Ollama only accepts images as base64 data".to_string())
+            }
+            other => {
+                return Err(format!(
+                    "Content block '{}' is not supported by Ollama",
+                    other.type_name()
+                ))
+            }
+        }
+    }
+
+    Ok(OllamaMessage {
+        role,
+        content: text.join("\n"),
+        images,
+    })
+}
+
+fn build_chat_request(
+    model: String,
+    conversation: Vec<ChatMessage>,
+    temperature: Option<f32>,
+) -> Result<OllamaChatRequest, CommandError> {
+    require_user_message(&conversation).map_err(CommandError::invalid_request)?;
+
+    let messages = conversation
+        .into_iter()
+        .map(to_ollama_message)
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(CommandError::invalid_request)?;
+
+    Ok(OllamaChatRequest {
+        model,
+        messages,
+        stream: true,
+        options: ollama_options(temperature),
+    })
+}
+
+async fn list_models(http: &HttpClient, base_url: &str) -> Result<Vec<OllamaModel>, CommandError> {
+    let response = http
+        .with_timeout(http.client().get(format!("{}/api/tags", base_url)))
+        .send()
+        .await
+        .map_err(|e| connection_error(base_url, e))?;
+
+    if !response.status().is_success() {
+        return Err(error_from_response(response).await);
+    }
+
+    let tags: OllamaTagsResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse models response: {}", e))?;
+
+    Ok(tags.models)
+}
+
+/// Posts a streaming request and reads the NDJSON body, passing each text
+/// fragment to `on_delta`. Shared by `/api/chat` and `/api/generate`.
+async fn stream_completion<T: Serialize>(
+    http: &HttpClient,
+    base_url: &str,
+    path: &str,
+    request_body: &T,
+    mut on_delta: impl FnMut(&str),
+) -> Result<ChatStreamResult, CommandError> {
+    let request = http
+        .client()
+        .post(format!("{}{}", base_url, path))
+        .json(request_body);
+    let mut response = http
+        .await_response(
+            Some("Ollama"),
+            &format!("reach Ollama at {}", base_url),
+            request.send(),
+            |e| connection_error(base_url, e),
+        )
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(error_from_response(response).await);
+    }
+
+    let mut parser = NdjsonParser::default();
+    let mut result = ChatStreamResult::default();
+
+    loop {
+        let chunk = http.next_chunk(&mut response).await?;
+        let lines = match &chunk {
+            Some(bytes) => parser.push(bytes),
+            None => parser.finish().into_iter().collect(),
+        };
+
+        for line in lines {
+            let parsed: OllamaStreamChunk = serde_json::from_str(&line)
+                .map_err(|e| format!("Failed to parse stream chunk: {}", e))?;
+
+            if let Some(error) = parsed.error {
+                return Err(CommandError::provider_failed("Ollama", error));
+            }
+
+            let text = parsed
+                .message
+                .map(|message| message.content)
+                .or(parsed.response)
+                .unwrap_or_default();
+            if !text.is_empty() {
+                on_delta(&text);
+                result.text.push_str(&text);
+            }
+
+            if parsed.done {
+                result.stop_reason = parsed.done_reason;
+                result.usage = Some(TokenUsage {
+                    input_tokens: parsed.prompt_eval_count.unwrap_or(0),
+                    output_tokens: parsed.eval_count.unwrap_or(0),
+                });
+            }
+        }
+
+        if chunk.is_none() {
+            break;
+        }
+    }
+
+    Ok(result)
+}
+
+/// Pulls `model`, reporting each progress line to `on_progress` and returning
+/// the last one (normally `status: "success"`).
+async fn pull_model(
+    http: &HttpClient,
+    base_url: &str,
+    model: &str,
+    mut on_progress: impl FnMut(&OllamaPullProgress),
+) -> Result<OllamaPullProgress, CommandError> {
+    let request_body = OllamaPullRequest {
+        model: model.to_string(),
+        stream: true,
+    };
+
+    let request = http
+        .client()
+        .post(format!("{}/api/pull", base_url))
+        .json(&request_body);
+    let mut response = http
+        .await_response(
+            Some("Ollama"),
+            &format!("reach Ollama at {}", base_url),
+            request.send(),
+            |e| connection_error(base_url, e),
+        )
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(error_from_response(response).await);
+    }
+
+    let mut parser = NdjsonParser::default();
+    let mut last = OllamaPullProgress::default();
+
+    loop {
+        let chunk = http.next_chunk(&mut response).await?;
+        let lines = match &chunk {
+            Some(bytes) => parser.push(bytes),
+            None => parser.finish().into_iter().collect(),
+        };
+
+        for line in lines {
+            let progress: OllamaPullProgress = serde_json::from_str(&line)
+                .map_err(|e| format!("Failed to parse pull progress: {}", e))?;
+
+            if let Some(error) = progress.error {
+                return Err(format!("Failed to pull {}: {}", model, error).into());
+            }
+
+            on_progress(&progress);
+            last = progress;
+        }
+
+        if chunk.is_none() {
+            break;
+        }
+    }
+
+    Ok(last)
+}
+
+pub struct OllamaProvider;
+
+#[async_trait]
+impl Provider for OllamaProvider {
+    fn id(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn name(&self) -> &'static str {
+        "Ollama"
+    }
+
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::Text]
+    }
+
+    fn auth_scheme(&self) -> AuthScheme {
+        AuthScheme::None
+    }
+
+    fn api_key(&self, _settings: &AppSettings) -> Option<String> {
+        None
+    }
+
+    fn requires_api_key(&self) -> bool {
+        false
+    }
+
+    async fn generate(
+        &self,
+        context: &ProviderContext,
+        model: &str,
+        request: &GenerateRequest,
+    ) -> Result<GenerateResponse, CommandError> {
+        let conversation = request.conversation(context.client.http()).await?;
+        let request_body =
+            build_chat_request(model.to_string(), conversation, request.temperature)?;
+        let base_url = normalize_base_url(context.settings.ollama_base_url.as_deref());
+
+        let result = stream_completion(
+            context.client.http(),
+            &base_url,
+            "/api/chat",
+            &request_body,
+            |_| {},
+        )
+        .await?;
+
+        Ok(GenerateResponse {
+            usage: result.usage,
+            stop_reason: result.stop_reason,
+            ..GenerateResponse::text(result.text)
+        })
+    }
+}
+
+#[tauri::command]
+pub async fn ollama_list_models(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<OllamaModel>, CommandError> {
+    let base_url = load_base_url(app_handle.clone()).await?;
+    list_models(&http::shared_client(&app_handle), &base_url).await
+}
+
+/// Streams a chat from a local Ollama model, emitting `chat-stream` deltas tagged with `request_id`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn ollama_chat(
+    app_handle: tauri::AppHandle,
+    request_id: String,
+    model: String,
+    system_prompt: Option<String>,
+    user_content: Option<String>,
+    temperature: Option<f32>,
+    messages: Option<Vec<ChatMessage>>,
+    attachments: Option<Vec<ChatAttachment>>,
+    call_context: Option<CallContext>,
+) -> Result<ChatStreamResult, CommandError> {
+    let result = cancellable(Some(&request_id), async {
+        let http = http::shared_client(&app_handle);
+        let mut conversation = build_conversation(system_prompt, messages, user_content);
+        attach_to_conversation(&mut conversation, attachments, &http).await?;
+        let request_body = build_chat_request(model.clone(), conversation, temperature)?;
+        let base_url = load_base_url(app_handle.clone()).await?;
+        let done = stream_completion(&http, &base_url, "/api/chat", &request_body, |text| {
+            emit_delta(&app_handle, &request_id, text)
+        })
+        .await?;
+        ledger::record(
+            &app_handle,
+            LedgerEntry::new("ollama", &model, call_context.as_ref())
+                .with_usage(done.usage.as_ref()),
+        );
+        Ok(done)
+    })
+    .await;
+    finish_stream(&app_handle, &request_id, result)
+}
+
+/// Streams a raw completion from `/api/generate`, emitting `chat-stream` deltas
+/// tagged with `request_id`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn ollama_generate(
+    app_handle: tauri::AppHandle,
+    request_id: String,
+    model: String,
+    prompt: String,
+    system_prompt: Option<String>,
+    temperature: Option<f32>,
+    attachments: Option<Vec<ChatAttachment>>,
+    call_context: Option<CallContext>,
+) -> Result<ChatStreamResult, CommandError> {
+    let result = cancellable(Some(&request_id), async {
+        let http = http::shared_client(&app_handle);
+        let mut conversation = vec![ChatMessage::new(ChatRole::User, prompt)];
+        attach_to_conversation(&mut conversation, attachments, &http).await?;
+        let OllamaMessage {
+            content, images, ..
+        } = to_ollama_message(conversation.remove(0))?;
+
+        let request_body = OllamaGenerateRequest {
+            model: model.clone(),
+            prompt: content,
+            system: system_prompt.filter(|prompt| !prompt.trim().is_empty()),
+            images,
+            stream: true,
+            options: ollama_options(temperature),
+        };
+        let base_url = load_base_url(app_handle.clone()).await?;
+        let done = stream_completion(&http, &base_url, "/api/generate", &request_body, |text| {
+            emit_delta(&app_handle, &request_id, text)
+        })
+        .await?;
+        ledger::record(
+            &app_handle,
+            LedgerEntry::new("ollama", &model, call_context.as_ref())
+                .with_usage(done.usage.as_ref()),
+        );
+        Ok(done)
+    })
+    .await;
+    finish_stream(&app_handle, &request_id, result)
+}
+
+/// Downloads a model into the local Ollama library, emitting `ollama-pull-progress`
+/// events tagged with `request_id` as layers arrive.
+#[tauri::command]
+pub async fn ollama_pull_model(
+    app_handle: tauri::AppHandle,
+    request_id: String,
+    model: String,
+) -> Result<OllamaPullProgress, CommandError> {
+    let base_url = load_base_url(app_handle.clone()).await?;
+    let http = http::shared_client(&app_handle);
+    let pull = pull_model(&http, &base_url, &model, |progress| {
+        let _ = app_handle.emit(
+            OLLAMA_PULL_EVENT,
+            OllamaPullEvent {
+                request_id: request_id.clone(),
+                model: model.clone(),
+                progress: progress.clone(),
+            },
+        );
+    });
+    cancellable(Some(&request_id), pull).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    /// Serves one canned response and hands the raw request back over the channel.
+    fn serve_once(status: &str, body: &str) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/x-ndjson\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        let (sender, receiver) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 4096];
+            loop {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse::<usize>().ok())?
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        sender.send(text).ok();
+                        break;
+                    }
+                }
+                if read == 0 {
+                    break;
+                }
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+
+        (base_url, receiver)
+    }
+
+    #[test]
+    fn lists_models_from_tags() {
+        let (base_url, requests) = serve_once(
+            "200 OK",
+            r#"{"models":[{"name":"llama3.2:latest","size":2019393189,"details":{"family":"llama"}}]}"#,
+        );
+
+        let models =
+            tauri::async_runtime::block_on(list_models(&HttpClient::default(), &base_url)).unwrap();
+
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].name, "llama3.2:latest");
+        assert!(requests.recv().unwrap().starts_with("GET /api/tags"));
+    }
+
+    #[test]
+    fn streams_chat_deltas_and_usage() {
+        let (base_url, requests) = serve_once(
+            "200 OK",
+            concat!(
+                "{\"message\":{\"role\":\"assistant\",\"content\":\"Hel\"},\"done\":false}\n",
+                "{\"message\":{\"role\":\"assistant\",\"content\":\"lo\"},\"done\":false}\n",
+                "{\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true,",
+                "\"done_reason\":\"stop\",\"prompt_eval_count\":12,\"eval_count\":2}\n"
+            ),
+        );
+        let request_body = build_chat_request(
+            "llama3.2".to_string(),
+            build_conversation(Some("Be brief".to_string()), None, Some("Hi".to_string())),
+            Some(0.2),
+        )
+        .unwrap();
+
+        let mut deltas = Vec::new();
+        let result = tauri::async_runtime::block_on(stream_completion(
+            &HttpClient::default(),
+            &base_url,
+            "/api/chat",
+            &request_body,
+            |text| deltas.push(text.to_string()),
+        ))
+        .unwrap();
+
+        assert_eq!(deltas, vec!["Hel", "lo"]);
+        assert_eq!(result.text, "Hello");
+        assert_eq!(result.stop_reason.as_deref(), Some("stop"));
+        assert_eq!(
+            result.usage,
+            Some(TokenUsage {
+                input_tokens: 12,
+                output_tokens: 2,
+            })
+        );
+
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("POST /api/chat"));
+        assert!(request.contains("\"stream\":true"));
+        assert!(request.contains("\"temperature\":0.2"));
+    }
+
+    #[test]
+    fn streams_generate_responses_and_surfaces_errors() {
+        let (base_url, _) = serve_once(
+            "200 OK",
+            "{\"response\":\"Once\",\"done\":false}\n{\"error\":\"model crashed\"}\n",
+        );
+        let request_body = OllamaGenerateRequest {
+            model: "llama3.2".to_string(),
+            prompt: "Tell a story".to_string(),
+            system: None,
+            images: Vec::new(),
+            stream: true,
+            options: None,
+        };
+
+        let error = tauri::async_runtime::block_on(stream_completion(
+            &HttpClient::default(),
+            &base_url,
+            "/api/generate",
+            &request_body,
+            |_| {},
+        ))
+        .unwrap_err();
+
+        assert_eq!(error.to_string(), "Ollama error: model crashed");
+        assert_eq!(error.code(), ErrorCode::ProviderFailed);
+    }
+
+    #[test]
+    fn pull_reports_progress_until_success() {
+        let (base_url, _) = serve_once(
+            "200 OK",
+            concat!(
+                "{\"status\":\"pulling manifest\"}\n",
+                "{\"status\":\"pulling 6a0746a1ec1a\",\"digest\":\"sha256:6a07\",\"total\":100,\"completed\":40}\n",
+                "{\"status\":\"success\"}\n"
+            ),
+        );
+
+        let mut statuses = Vec::new();
+        let last = tauri::async_runtime::block_on(pull_model(
+            &HttpClient::default(),
+            &base_url,
+            "llama3.2",
+            |progress| {
+                statuses.push((progress.status.clone(), progress.completed));
+            },
+        ))
+        .unwrap();
+
+        assert_eq!(last.status, "success");
+        assert_eq!(statuses[1], ("pulling 6a0746a1ec1a".to_string(), Some(40)));
+        assert_eq!(statuses.len(), 3);
+    }
+
+    #[test]
+    fn pull_failure_returns_api_error() {
+        let (base_url, _) = serve_once(
+            "404 Not Found",
+            r#"{"error":"pull model manifest: file does not exist"}"#,
+        );
+
+        let error = tauri::async_runtime::block_on(pull_model(
+            &HttpClient::default(),
+            &base_url,
+            "missing",
+            |_| {},
+        ))
+        .unwrap_err();
+
+        assert_eq!(error.code(), ErrorCode::NotFound);
+        assert_eq!(
+            error.to_string(),
+            "Ollama API error (404 Not Found): pull model manifest: file does not exist"
+        );
+    }
+}
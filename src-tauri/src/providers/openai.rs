@@ -0,0 +1,739 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use reqwest::header::AUTHORIZATION;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::attachments::{attach_to_conversation, ChatAttachment};
+use super::budget::ensure_within_budget;
+use super::cache::cached;
+use super::chat::{
+    build_conversation, require_user_message, ChatMessage, ChatRole, ContentBlock, MediaSource,
+    MessageContent,
+};
+use super::ledger::{self, CallContext, LedgerEntry};
+use super::provider::{
+    AuthScheme, Capability, GenerateRequest, GenerateResponse, Provider, ProviderClient,
+    ProviderContext,
+};
+use super::retry::Retry;
+use super::stream::{emit_delta, finish_stream, ChatStreamResult, SseParser, TokenUsage};
+use crate::cancel::cancellable;
+use crate::error::CommandError;
+use crate::settings::{load_settings, AppSettings};
+
+const OPENAI_API_BASE: &str = "https://api.openai.com/v1";
+
+const DEFAULT_LM_STUDIO_BASE_URL: &str = "http://localhost:1234";
+
+/// Where OpenAI-style requests go. Omitting it targets api.openai.com with the key
+/// from Settings; `custom` covers vLLM, llama.cpp server, LiteLLM and proxies, with
+/// `base_url` including the version prefix (e.g. `http://host:8000/v1`).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OpenAIEndpoint {
+    #[serde(rename = "openai")]
+    OpenAI,
+    LmStudio,
+    Custom {
+        base_url: String,
+        #[serde(default)]
+        api_key: Option<String>,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+}
+
+#[derive(Debug)]
+struct ResolvedEndpoint {
+    /// Provider id recorded in the usage ledger.
+    provider: &'static str,
+    base_url: String,
+    client: ProviderClient,
+}
+
+impl ResolvedEndpoint {
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// The provider part of response-cache keys. Every custom endpoint reports the
+    /// same provider id, so the key also names the server: its base URL, plus a
+    /// digest of any extra headers, which gateways may route on.
+    fn cache_provider(&self) -> String {
+        if self.provider == "openai" {
+            return self.provider.to_string();
+        }
+        let mut headers: Vec<String> = self
+            .client
+            .headers()
+            .iter()
+            .filter(|(name, _)| **name != AUTHORIZATION)
+            .map(|(name, value)| format!("{}={}", name, value.to_str().unwrap_or_default()))
+            .collect();
+        if headers.is_empty() {
+            return format!("{}:{}", self.provider, self.base_url);
+        }
+        headers.sort();
+        let digest = format!("{:x}", Sha256::digest(headers.join("\n").as_bytes()));
+        format!("{}:{}#{}", self.provider, self.base_url, &digest[..12])
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct OpenAIChatMessage {
+    role: String,
+    content: OpenAIMessageContent,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+enum OpenAIMessageContent {
+    Text(String),
+    Parts(Vec<OpenAIContentPart>),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OpenAIContentPart {
+    Text { text: String },
+    ImageUrl { image_url: OpenAIImageUrl },
+    File { file: OpenAIFile },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct OpenAIImageUrl {
+    url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct OpenAIFile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filename: Option<String>,
+    file_data: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct OpenAIChatRequest {
+    model: String,
+    messages: Vec<OpenAIChatMessage>,
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<OpenAIStreamOptions>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct OpenAIStreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIChatChoice {
+    message: OpenAIChatMessage,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIChatResponse {
+    choices: Vec<OpenAIChatChoice>,
+    usage: Option<OpenAIUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIStreamChunk {
+    #[serde(default)]
+    choices: Vec<OpenAIStreamChoice>,
+    usage: Option<OpenAIUsage>,
+    /// Sent in place of a chunk when the server or a gateway fails mid-stream.
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIStreamChoice {
+    delta: OpenAIStreamDelta,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIStreamDelta {
+    content: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIUsage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OpenAIModel {
+    id: String,
+    owned_by: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct OpenAIModelsResponse {
+    data: Vec<OpenAIModel>,
+}
+
+fn custom_endpoint(
+    base_url: &str,
+    api_key: Option<&str>,
+    extra_headers: &HashMap<String, String>,
+) -> Result<ResolvedEndpoint, String> {
+    let base_url = base_url.trim().trim_end_matches('/');
+    if base_url.is_empty() {
+        return Err("OpenAI-compatible endpoint requires a base URL".to_string());
+    }
+
+    let client = ProviderClient::new(
+        "OpenAI-compatible endpoint",
+        AuthScheme::Bearer,
+        api_key,
+        &[],
+    )?
+    .with_headers(extra_headers)?;
+
+    Ok(ResolvedEndpoint {
+        provider: "openai_compatible",
+        base_url: base_url.to_string(),
+        client,
+    })
+}
+
+fn lm_studio_api_base(settings: &AppSettings) -> String {
+    let base_url = settings
+        .lm_studio_base_url
+        .as_deref()
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .unwrap_or(DEFAULT_LM_STUDIO_BASE_URL);
+    format!("{}/v1", base_url.trim_end_matches('/'))
+}
+
+/// The Settings key is only sent to api.openai.com; other endpoints use the key
+/// passed with them, if any. Retries are reported with `request_id` when given.
+async fn resolve_endpoint(
+    app_handle: &tauri::AppHandle,
+    endpoint: Option<OpenAIEndpoint>,
+    request_id: Option<&str>,
+) -> Result<ResolvedEndpoint, CommandError> {
+    let settings = load_settings(app_handle.clone()).await?;
+
+    let resolved = match endpoint.unwrap_or(OpenAIEndpoint::OpenAI) {
+        OpenAIEndpoint::OpenAI => ResolvedEndpoint {
+            provider: "openai",
+            base_url: OPENAI_API_BASE.to_string(),
+            client: ProviderContext::new(&OpenAIProvider, settings)?.client,
+        },
+        OpenAIEndpoint::LmStudio => ResolvedEndpoint {
+            provider: "lmstudio",
+            base_url: lm_studio_api_base(&settings),
+            client: ProviderContext::new(&LmStudioProvider, settings)?.client,
+        },
+        OpenAIEndpoint::Custom {
+            base_url,
+            api_key,
+            headers,
+        } => {
+            let resolved = custom_endpoint(&base_url, api_key.as_deref(), &headers)
+                .map_err(CommandError::invalid_request)?;
+            let retry = Retry::from_settings(&settings, resolved.provider);
+            ResolvedEndpoint {
+                client: resolved.client.with_retry(retry),
+                ..resolved
+            }
+        }
+    };
+
+    Ok(ResolvedEndpoint {
+        client: resolved.client.for_app(app_handle, request_id),
+        ..resolved
+    })
+}
+
+/// Maps our content blocks onto chat completion content parts. Images become
+/// `image_url` parts and PDFs become `file` parts, both carried as data URLs.
+fn to_openai_content(content: MessageContent) -> Result<OpenAIMessageContent, String> {
+    let blocks = match content {
+        MessageContent::Text(text) => return Ok(OpenAIMessageContent::Text(text)),
+        MessageContent::Blocks(blocks) => blocks,
+    };
+
+    let parts = blocks
+        .into_iter()
+        .map(|block| match block {
+            ContentBlock::Text { text } => Ok(OpenAIContentPart::Text { text }),
+            ContentBlock::Image { source } => Ok(OpenAIContentPart::ImageUrl {
+                image_url: OpenAIImageUrl {
+                    url: source.to_url(),
+                },
+            }),
+            ContentBlock::Document {
+                source: source @ MediaSource::Base64 { .. },
+                title,
+            } => Ok(OpenAIContentPart::File {
+                file: OpenAIFile {
+                    filename: title,
+                    file_data: source.to_url(),
+                },
+            }),
+            ContentBlock::Document { .. } => {
+                Err("OpenAI only accepts documents as base64 data".to_string())
+            }
+            other => Err(format!(
+                "Content block '{}' is not supported by OpenAI chat",
+                other.type_name()
+            )),
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(OpenAIMessageContent::Parts(parts))
+}
+
+fn content_text(content: &OpenAIMessageContent) -> String {
+    match content {
+        OpenAIMessageContent::Text(text) => text.clone(),
+        OpenAIMessageContent::Parts(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                OpenAIContentPart::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join(""),
+    }
+}
+
+fn build_request(
+    model: String,
+    mut conversation: Vec<ChatMessage>,
+    temperature: Option<f32>,
+) -> Result<OpenAIChatRequest, CommandError> {
+    if !conversation
+        .iter()
+        .any(|message| message.role == ChatRole::System)
+    {
+        conversation.insert(
+            0,
+            ChatMessage::new(ChatRole::System, "You are a helpful assistant"),
+        );
+    }
+    require_user_message(&conversation).map_err(CommandError::invalid_request)?;
+
+    let messages = conversation
+        .into_iter()
+        .map(|message| {
+            Ok(OpenAIChatMessage {
+                role: message.role.as_str().to_string(),
+                content: to_openai_content(message.content)?,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(CommandError::invalid_request)?;
+
+    Ok(OpenAIChatRequest {
+        model,
+        messages,
+        temperature: temperature.unwrap_or(0.7),
+        stream: None,
+        stream_options: None,
+    })
+}
+
+/// Runs a non-streaming chat completion for `generate` against any
+/// OpenAI-compatible base URL.
+async fn generate_completion(
+    context: &ProviderContext,
+    api_base: &str,
+    model: &str,
+    request: &GenerateRequest,
+) -> Result<GenerateResponse, CommandError> {
+    let conversation = request.conversation(context.client.http()).await?;
+    let request_body = build_request(model.to_string(), conversation, request.temperature)?;
+
+    let response_data: OpenAIChatResponse = context
+        .client
+        .send_json(
+            context
+                .client
+                .post(&format!("{}/chat/completions", api_base))
+                .json(&request_body),
+            "create completion",
+        )
+        .await?;
+
+    let choice = response_data
+        .choices
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("No content in {} response", context.client.name()))?;
+
+    Ok(GenerateResponse {
+        usage: response_data.usage.map(|usage| TokenUsage {
+            input_tokens: usage.prompt_tokens,
+            output_tokens: usage.completion_tokens,
+        }),
+        stop_reason: choice.finish_reason,
+        ..GenerateResponse::text(content_text(&choice.message.content))
+    })
+}
+
+pub struct OpenAIProvider;
+
+#[async_trait]
+impl Provider for OpenAIProvider {
+    fn id(&self) -> &'static str {
+        "openai"
+    }
+
+    fn name(&self) -> &'static str {
+        "OpenAI"
+    }
+
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::Text]
+    }
+
+    fn api_key(&self, settings: &AppSettings) -> Option<String> {
+        settings.openai_api_key.clone()
+    }
+
+    async fn generate(
+        &self,
+        context: &ProviderContext,
+        model: &str,
+        request: &GenerateRequest,
+    ) -> Result<GenerateResponse, CommandError> {
+        generate_completion(context, OPENAI_API_BASE, model, request).await
+    }
+}
+
+pub struct LmStudioProvider;
+
+#[async_trait]
+impl Provider for LmStudioProvider {
+    fn id(&self) -> &'static str {
+        "lmstudio"
+    }
+
+    fn name(&self) -> &'static str {
+        "LM Studio"
+    }
+
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::Text]
+    }
+
+    fn auth_scheme(&self) -> AuthScheme {
+        AuthScheme::None
+    }
+
+    fn api_key(&self, _settings: &AppSettings) -> Option<String> {
+        None
+    }
+
+    fn requires_api_key(&self) -> bool {
+        false
+    }
+
+    async fn generate(
+        &self,
+        context: &ProviderContext,
+        model: &str,
+        request: &GenerateRequest,
+    ) -> Result<GenerateResponse, CommandError> {
+        let api_base = lm_studio_api_base(&context.settings);
+        generate_completion(context, &api_base, model, request).await
+    }
+}
+
+#[tauri::command]
+pub async fn openai_list_models(
+    app_handle: tauri::AppHandle,
+    endpoint: Option<OpenAIEndpoint>,
+) -> Result<Vec<OpenAIModel>, CommandError> {
+    let endpoint = resolve_endpoint(&app_handle, endpoint, None).await?;
+    let client = &endpoint.client;
+
+    let models: OpenAIModelsResponse = client
+        .send_json(client.get(&endpoint.url("/models")), "fetch models")
+        .await?;
+
+    Ok(models.data)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn openai_chat_completion(
+    app_handle: tauri::AppHandle,
+    model: String,
+    system_prompt: Option<String>,
+    user_content: Option<String>,
+    temperature: Option<f32>,
+    messages: Option<Vec<ChatMessage>>,
+    attachments: Option<Vec<ChatAttachment>>,
+    endpoint: Option<OpenAIEndpoint>,
+    call_context: Option<CallContext>,
+    request_id: Option<String>,
+) -> Result<String, CommandError> {
+    cancellable(request_id.as_deref(), async {
+        let endpoint = resolve_endpoint(&app_handle, endpoint, request_id.as_deref()).await?;
+        let mut conversation = build_conversation(system_prompt, messages, user_content);
+        attach_to_conversation(&mut conversation, attachments, endpoint.client.http()).await?;
+        let request_body = build_request(model, conversation, temperature)?;
+
+        cached(
+            &app_handle,
+            &endpoint.cache_provider(),
+            &request_body.model,
+            &request_body,
+            async {
+                ensure_within_budget(&app_handle, endpoint.provider, call_context.as_ref()).await?;
+                let client = &endpoint.client;
+                let response_data: OpenAIChatResponse = client
+                    .send_json(
+                        client
+                            .post(&endpoint.url("/chat/completions"))
+                            .json(&request_body),
+                        "create completion",
+                    )
+                    .await?;
+
+                let usage = response_data.usage.as_ref().map(|usage| TokenUsage {
+                    input_tokens: usage.prompt_tokens,
+                    output_tokens: usage.completion_tokens,
+                });
+                ledger::record(
+                    &app_handle,
+                    LedgerEntry::new(
+                        endpoint.provider,
+                        &request_body.model,
+                        call_context.as_ref(),
+                    )
+                    .with_usage(usage.as_ref()),
+                );
+
+                response_data
+                    .choices
+                    .first()
+                    .map(|choice| content_text(&choice.message.content))
+                    .ok_or_else(|| {
+                        format!("No content in {} response", endpoint.client.name()).into()
+                    })
+            },
+        )
+        .await
+    })
+    .await
+}
+
+/// Streams a chat completion over SSE, emitting `chat-stream` deltas tagged with `request_id`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn openai_chat_stream(
+    app_handle: tauri::AppHandle,
+    request_id: String,
+    model: String,
+    system_prompt: Option<String>,
+    user_content: Option<String>,
+    temperature: Option<f32>,
+    messages: Option<Vec<ChatMessage>>,
+    attachments: Option<Vec<ChatAttachment>>,
+    endpoint: Option<OpenAIEndpoint>,
+    call_context: Option<CallContext>,
+) -> Result<ChatStreamResult, CommandError> {
+    let result = cancellable(Some(&request_id), async {
+        let endpoint = resolve_endpoint(&app_handle, endpoint, Some(&request_id)).await?;
+        ensure_within_budget(&app_handle, endpoint.provider, call_context.as_ref()).await?;
+        let mut conversation = build_conversation(system_prompt, messages, user_content);
+        attach_to_conversation(&mut conversation, attachments, endpoint.client.http()).await?;
+        let mut request_body = build_request(model, conversation, temperature)?;
+        request_body.stream = Some(true);
+        request_body.stream_options = Some(OpenAIStreamOptions {
+            include_usage: true,
+        });
+        let done = stream_completion(&app_handle, &request_id, &endpoint, &request_body).await?;
+        ledger::record(
+            &app_handle,
+            LedgerEntry::new(
+                endpoint.provider,
+                &request_body.model,
+                call_context.as_ref(),
+            )
+            .with_usage(done.usage.as_ref()),
+        );
+        Ok(done)
+    })
+    .await;
+    finish_stream(&app_handle, &request_id, result)
+}
+
+async fn stream_completion(
+    app_handle: &tauri::AppHandle,
+    request_id: &str,
+    endpoint: &ResolvedEndpoint,
+    request_body: &OpenAIChatRequest,
+) -> Result<ChatStreamResult, CommandError> {
+    let client = &endpoint.client;
+    let mut response = client
+        .send(
+            client
+                .post(&endpoint.url("/chat/completions"))
+                .json(request_body),
+            "create completion",
+        )
+        .await?;
+
+    let mut parser = SseParser::default();
+    let mut result = ChatStreamResult::default();
+
+    loop {
+        let chunk = client.http().next_chunk(&mut response).await?;
+        let events = match &chunk {
+            Some(bytes) => parser.push(bytes),
+            None => parser.finish().into_iter().collect(),
+        };
+
+        for event in events {
+            if event.data.trim() == "[DONE]" {
+                continue;
+            }
+
+            let parsed: OpenAIStreamChunk = serde_json::from_str(&event.data)
+                .map_err(|e| format!("Failed to parse stream chunk: {}", e))?;
+
+            if parsed.error.is_some() {
+                return Err(CommandError::provider_event(client.name(), &event.data));
+            }
+
+            for choice in parsed.choices {
+                if let Some(text) = choice.delta.content.filter(|text| !text.is_empty()) {
+                    emit_delta(app_handle, request_id, &text);
+                    result.text.push_str(&text);
+                }
+                if choice.finish_reason.is_some() {
+                    result.stop_reason = choice.finish_reason;
+                }
+            }
+
+            if let Some(usage) = parsed.usage {
+                result.usage = Some(TokenUsage {
+                    input_tokens: usage.prompt_tokens,
+                    output_tokens: usage.completion_tokens,
+                });
+            }
+        }
+
+        if chunk.is_none() {
+            break;
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::cache::CacheKey;
+
+    #[test]
+    fn custom_endpoint_trims_base_url_and_applies_headers() {
+        let endpoint = custom_endpoint(
+            " http://localhost:8000/v1/ ",
+            None,
+            &HashMap::from([("X-Gateway-Team".to_string(), "design".to_string())]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            endpoint.url("/chat/completions"),
+            "http://localhost:8000/v1/chat/completions"
+        );
+        assert!(endpoint.client.headers().get("authorization").is_none());
+        assert_eq!(endpoint.client.headers()["x-gateway-team"], "design");
+    }
+
+    #[test]
+    fn cache_keys_tell_custom_endpoints_apart() {
+        let input = serde_json::json!({ "model": "llama-3", "temperature": 0 });
+        let key = |base_url: &str, headers: &[(&str, &str)]| {
+            let headers = headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            let endpoint = custom_endpoint(base_url, Some("sk-local"), &headers).unwrap();
+            CacheKey::new(&endpoint.cache_provider(), "llama-3", &input)
+        };
+
+        let lm_studio = key("http://localhost:1234/v1", &[]);
+        let gateway = key("https://llm.corp.example/v1", &[]);
+        assert_ne!(lm_studio.digest, gateway.digest);
+        assert_eq!(
+            lm_studio.provider,
+            "openai_compatible:http://localhost:1234/v1"
+        );
+
+        let routed = key("https://llm.corp.example/v1", &[("x-route", "gpu-a")]);
+        assert_ne!(routed.digest, gateway.digest);
+        assert_eq!(
+            routed.digest,
+            key("https://llm.corp.example/v1", &[("x-route", "gpu-a")]).digest
+        );
+    }
+
+    #[test]
+    fn custom_endpoint_sends_bearer_key_and_rejects_bad_headers() {
+        let endpoint = custom_endpoint(
+            "https://gateway.example/v1",
+            Some("sk-local"),
+            &HashMap::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            endpoint.client.headers()["authorization"],
+            "Bearer sk-local"
+        );
+
+        assert!(custom_endpoint(
+            "https://gateway.example/v1",
+            None,
+            &HashMap::from([("bad header".to_string(), "x".to_string())]),
+        )
+        .is_err());
+        assert!(custom_endpoint("  ", None, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn stream_chunks_carry_in_band_errors() {
+        let event = r#"{"error":{"message":"upstream timed out","type":"server_error"}}"#;
+        let chunk: OpenAIStreamChunk = serde_json::from_str(event).unwrap();
+        assert!(chunk.choices.is_empty());
+        assert!(chunk.error.is_some());
+
+        let error = CommandError::provider_event("LM Studio", event);
+        assert_eq!(error.to_string(), "LM Studio error: upstream timed out");
+        assert!(error.retryable());
+    }
+
+    #[test]
+    fn endpoint_deserializes_by_kind() {
+        let endpoint: OpenAIEndpoint = serde_json::from_value(serde_json::json!({
+            "kind": "custom",
+            "base_url": "http://localhost:8080/v1"
+        }))
+        .unwrap();
+        assert!(matches!(
+            endpoint,
+            OpenAIEndpoint::Custom { api_key: None, .. }
+        ));
+
+        let endpoint: OpenAIEndpoint =
+            serde_json::from_value(serde_json::json!({ "kind": "lm_studio" })).unwrap();
+        assert!(matches!(endpoint, OpenAIEndpoint::LmStudio));
+    }
+}
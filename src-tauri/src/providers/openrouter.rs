@@ -0,0 +1,625 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::budget::ensure_within_budget;
+use super::cache::cached;
+use super::ledger::{self, CallContext, LedgerEntry};
+use super::provider::{
+    Capability, GenerateRequest, GenerateResponse, Provider, ProviderClient, ProviderContext,
+};
+use super::retry::Retry;
+use super::stream::{emit_delta, finish_stream, ChatStreamResult, SseParser, TokenUsage};
+use crate::cancel::cancellable;
+use crate::error::CommandError;
+use crate::settings::{load_settings, AppSettings};
+
+const OPENROUTER_API_BASE: &str = "https://openrouter.ai/api/v1";
+
+/// Chat message in OpenAI's wire format, which is what OpenRouter and the assistant
+/// panel speak: `tool` turns carry `tool_call_id`, assistant turns may carry `tool_calls`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OpenRouterMessage {
+    role: String,
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OpenRouterToolCall>,
+}
+
+impl OpenRouterMessage {
+    fn new(role: &str, content: String) -> Self {
+        OpenRouterMessage {
+            role: role.to_string(),
+            content: Some(content),
+            name: None,
+            tool_call_id: None,
+            tool_calls: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OpenRouterTool {
+    #[serde(rename = "type")]
+    kind: String,
+    function: OpenRouterFunction,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct OpenRouterFunction {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parameters: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OpenRouterToolCall {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    function: OpenRouterFunctionCall,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct OpenRouterFunctionCall {
+    name: String,
+    arguments: String,
+}
+
+#[derive(Debug, Serialize)]
+struct OpenRouterChatRequest {
+    model: String,
+    messages: Vec<OpenRouterMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<OpenRouterTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OpenRouterChatResult {
+    message: OpenRouterMessage,
+    finish_reason: Option<String>,
+    usage: Option<TokenUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenRouterChatResponse {
+    #[serde(default)]
+    choices: Vec<OpenRouterChoice>,
+    usage: Option<OpenRouterUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenRouterChoice {
+    message: OpenRouterMessage,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenRouterUsage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenRouterStreamChunk {
+    #[serde(default)]
+    choices: Vec<OpenRouterStreamChoice>,
+    usage: Option<OpenRouterUsage>,
+    /// Sent in place of a chunk when the upstream provider fails mid-stream.
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenRouterStreamChoice {
+    delta: OpenRouterStreamDelta,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct OpenRouterStreamDelta {
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<OpenRouterToolCallDelta>,
+}
+
+/// Streamed tool calls arrive in pieces keyed by `index`; only the first piece
+/// carries the id and name, later ones append to `arguments`.
+#[derive(Debug, Deserialize)]
+struct OpenRouterToolCallDelta {
+    #[serde(default)]
+    index: usize,
+    id: Option<String>,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    function: Option<OpenRouterFunctionDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenRouterFunctionDelta {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OpenRouterModel {
+    id: String,
+    name: Option<String>,
+    description: Option<String>,
+    created: Option<i64>,
+    context_length: Option<u64>,
+    architecture: Option<OpenRouterArchitecture>,
+    pricing: Option<serde_json::Value>,
+    top_provider: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct OpenRouterArchitecture {
+    modality: Option<String>,
+    #[serde(default)]
+    input_modalities: Vec<String>,
+    #[serde(default)]
+    output_modalities: Vec<String>,
+    tokenizer: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenRouterModelsResponse {
+    #[serde(default)]
+    data: Vec<OpenRouterModel>,
+}
+
+impl OpenRouterModel {
+    /// Falls back to the output side of `modality` (`"text+image->text"`) for
+    /// models listed without `output_modalities`.
+    fn has_output_modality(&self, modality: &str) -> bool {
+        self.architecture.as_ref().is_some_and(|architecture| {
+            if !architecture.output_modalities.is_empty() {
+                return architecture
+                    .output_modalities
+                    .iter()
+                    .any(|output| output == modality);
+            }
+            architecture
+                .modality
+                .as_deref()
+                .and_then(|value| value.split_once("->"))
+                .is_some_and(|(_, outputs)| outputs.split('+').any(|output| output == modality))
+        })
+    }
+}
+
+pub struct OpenRouterProvider;
+
+#[async_trait]
+impl Provider for OpenRouterProvider {
+    fn id(&self) -> &'static str {
+        "openrouter"
+    }
+
+    fn name(&self) -> &'static str {
+        "OpenRouter"
+    }
+
+    fn capabilities(&self) -> &'static [Capability] {
+        &[Capability::Text]
+    }
+
+    /// App attribution for openrouter.ai rankings, as the webview client sent.
+    fn extra_headers(&self) -> &'static [(&'static str, &'static str)] {
+        &[
+            ("http-referer", "https://github.com/oshtz/noder"),
+            ("x-title", "noder"),
+        ]
+    }
+
+    fn api_key(&self, settings: &AppSettings) -> Option<String> {
+        settings.openrouter_api_key.clone()
+    }
+
+    async fn generate(
+        &self,
+        context: &ProviderContext,
+        model: &str,
+        request: &GenerateRequest,
+    ) -> Result<GenerateResponse, CommandError> {
+        let messages = request
+            .conversation(context.client.http())
+            .await?
+            .into_iter()
+            .map(|message| {
+                Ok(OpenRouterMessage::new(
+                    message.role.as_str(),
+                    message.content.into_text()?,
+                ))
+            })
+            .collect::<Result<Vec<_>, String>>()
+            .map_err(CommandError::invalid_request)?;
+
+        let request_body = OpenRouterChatRequest {
+            model: model.to_string(),
+            messages,
+            temperature: request.temperature,
+            tools: Vec::new(),
+            tool_choice: None,
+            stream: None,
+        };
+        let result = complete_chat(&context.client, &request_body).await?;
+
+        Ok(GenerateResponse {
+            usage: result.usage,
+            stop_reason: result.finish_reason,
+            ..GenerateResponse::text(result.message.content.unwrap_or_default())
+        })
+    }
+}
+
+/// The model list is public, so the key is optional there; chat requires it.
+/// Retries are reported with `request_id` when given.
+async fn openrouter_context(
+    app_handle: tauri::AppHandle,
+    require_key: bool,
+    request_id: Option<&str>,
+) -> Result<ProviderContext, CommandError> {
+    let settings = load_settings(app_handle.clone()).await?;
+    let mut context = if require_key {
+        ProviderContext::new(&OpenRouterProvider, settings)?
+    } else {
+        let api_key = OpenRouterProvider.api_key(&settings);
+        let client = ProviderClient::new(
+            OpenRouterProvider.name(),
+            OpenRouterProvider.auth_scheme(),
+            api_key.as_deref(),
+            OpenRouterProvider.extra_headers(),
+        )?
+        .with_retry(Retry::from_settings(&settings, OpenRouterProvider.id()));
+        ProviderContext { client, settings }
+    };
+
+    context.client = context.client.for_app(&app_handle, request_id);
+    Ok(context)
+}
+
+fn apply_tool_call_deltas(
+    tool_calls: &mut Vec<OpenRouterToolCall>,
+    deltas: Vec<OpenRouterToolCallDelta>,
+) {
+    for delta in deltas {
+        while tool_calls.len() <= delta.index {
+            tool_calls.push(OpenRouterToolCall {
+                id: String::new(),
+                kind: "function".to_string(),
+                function: OpenRouterFunctionCall {
+                    name: String::new(),
+                    arguments: String::new(),
+                },
+            });
+        }
+
+        let call = &mut tool_calls[delta.index];
+        if let Some(id) = delta.id.filter(|id| !id.is_empty()) {
+            call.id = id;
+        }
+        if let Some(kind) = delta.kind {
+            call.kind = kind;
+        }
+        if let Some(function) = delta.function {
+            if let Some(name) = function.name.filter(|name| !name.is_empty()) {
+                call.function.name = name;
+            }
+            if let Some(arguments) = function.arguments {
+                call.function.arguments.push_str(&arguments);
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn openrouter_list_models(
+    app_handle: tauri::AppHandle,
+    output_modality: Option<String>,
+) -> Result<Vec<OpenRouterModel>, CommandError> {
+    let client = openrouter_context(app_handle, false, None).await?.client;
+    let models: OpenRouterModelsResponse = client
+        .send_json(
+            client.get(&format!("{}/models", OPENROUTER_API_BASE)),
+            "fetch models",
+        )
+        .await?;
+
+    Ok(
+        match output_modality.filter(|modality| !modality.is_empty()) {
+            Some(modality) => models
+                .data
+                .into_iter()
+                .filter(|model| model.has_output_modality(&modality))
+                .collect(),
+            None => models.data,
+        },
+    )
+}
+
+/// Runs a chat completion. With `stream: true`, text is emitted as `chat-stream`
+/// deltas tagged with `request_id` and tool calls are assembled before returning.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn openrouter_chat(
+    app_handle: tauri::AppHandle,
+    model: String,
+    messages: Vec<OpenRouterMessage>,
+    tools: Option<Vec<OpenRouterTool>>,
+    tool_choice: Option<serde_json::Value>,
+    temperature: Option<f32>,
+    stream: Option<bool>,
+    request_id: Option<String>,
+    call_context: Option<CallContext>,
+) -> Result<OpenRouterChatResult, CommandError> {
+    if messages.is_empty() {
+        return Err(CommandError::invalid_request(
+            "Conversation must contain at least one message",
+        ));
+    }
+
+    let tools = tools.unwrap_or_default();
+    let mut request_body = OpenRouterChatRequest {
+        model,
+        messages,
+        temperature,
+        tool_choice: if tools.is_empty() { None } else { tool_choice },
+        tools,
+        stream: None,
+    };
+
+    if !stream.unwrap_or(false) {
+        return cached(
+            &app_handle,
+            "openrouter",
+            &request_body.model,
+            &request_body,
+            async {
+                ensure_within_budget(&app_handle, "openrouter", call_context.as_ref()).await?;
+                let context =
+                    openrouter_context(app_handle.clone(), true, request_id.as_deref()).await?;
+                let result = cancellable(
+                    request_id.as_deref(),
+                    complete_chat(&context.client, &request_body),
+                )
+                .await?;
+                record_usage(
+                    &app_handle,
+                    &request_body.model,
+                    &result,
+                    call_context.as_ref(),
+                );
+                Ok(result)
+            },
+        )
+        .await;
+    }
+
+    let request_id = request_id
+        .ok_or_else(|| CommandError::invalid_request("Streaming requires a request_id"))?;
+    request_body.stream = Some(true);
+    let result = cancellable(Some(&request_id), async {
+        ensure_within_budget(&app_handle, "openrouter", call_context.as_ref()).await?;
+        let client = openrouter_context(app_handle.clone(), true, Some(&request_id))
+            .await?
+            .client;
+        stream_chat(&app_handle, &request_id, &client, &request_body).await
+    })
+    .await;
+
+    let summary = result
+        .as_ref()
+        .map(|done| ChatStreamResult {
+            text: done.message.content.clone().unwrap_or_default(),
+            stop_reason: done.finish_reason.clone(),
+            usage: done.usage.clone(),
+        })
+        .map_err(|e| e.clone());
+    finish_stream(&app_handle, &request_id, summary)?;
+    if let Ok(done) = &result {
+        record_usage(
+            &app_handle,
+            &request_body.model,
+            done,
+            call_context.as_ref(),
+        );
+    }
+    result
+}
+
+fn record_usage(
+    app_handle: &tauri::AppHandle,
+    model: &str,
+    result: &OpenRouterChatResult,
+    call_context: Option<&CallContext>,
+) {
+    ledger::record(
+        app_handle,
+        LedgerEntry::new("openrouter", model, call_context).with_usage(result.usage.as_ref()),
+    );
+}
+
+async fn complete_chat(
+    client: &ProviderClient,
+    request_body: &OpenRouterChatRequest,
+) -> Result<OpenRouterChatResult, CommandError> {
+    let response_data: OpenRouterChatResponse = client
+        .send_json(
+            client
+                .post(&format!("{}/chat/completions", OPENROUTER_API_BASE))
+                .json(request_body),
+            "create completion",
+        )
+        .await?;
+
+    let choice = response_data
+        .choices
+        .into_iter()
+        .next()
+        .ok_or("No choices in OpenRouter response")?;
+
+    Ok(OpenRouterChatResult {
+        message: choice.message,
+        finish_reason: choice.finish_reason,
+        usage: response_data.usage.map(|usage| TokenUsage {
+            input_tokens: usage.prompt_tokens,
+            output_tokens: usage.completion_tokens,
+        }),
+    })
+}
+
+async fn stream_chat(
+    app_handle: &tauri::AppHandle,
+    request_id: &str,
+    client: &ProviderClient,
+    request_body: &OpenRouterChatRequest,
+) -> Result<OpenRouterChatResult, CommandError> {
+    let mut response = client
+        .send(
+            client
+                .post(&format!("{}/chat/completions", OPENROUTER_API_BASE))
+                .json(request_body),
+            "create completion",
+        )
+        .await?;
+
+    let mut parser = SseParser::default();
+    let mut content = String::new();
+    let mut tool_calls = Vec::new();
+    let mut finish_reason = None;
+    let mut usage = None;
+
+    loop {
+        let chunk = client.http().next_chunk(&mut response).await?;
+        let events = match &chunk {
+            Some(bytes) => parser.push(bytes),
+            None => parser.finish().into_iter().collect(),
+        };
+
+        for event in events {
+            if event.data.trim() == "[DONE]" {
+                continue;
+            }
+
+            let parsed: OpenRouterStreamChunk = serde_json::from_str(&event.data)
+                .map_err(|e| format!("Failed to parse stream chunk: {}", e))?;
+
+            if parsed.error.is_some() {
+                return Err(CommandError::provider_event(client.name(), &event.data));
+            }
+
+            for choice in parsed.choices {
+                if let Some(text) = choice.delta.content.filter(|text| !text.is_empty()) {
+                    emit_delta(app_handle, request_id, &text);
+                    content.push_str(&text);
+                }
+                apply_tool_call_deltas(&mut tool_calls, choice.delta.tool_calls);
+                if choice.finish_reason.is_some() {
+                    finish_reason = choice.finish_reason;
+                }
+            }
+
+            if let Some(chunk_usage) = parsed.usage {
+                usage = Some(TokenUsage {
+                    input_tokens: chunk_usage.prompt_tokens,
+                    output_tokens: chunk_usage.completion_tokens,
+                });
+            }
+        }
+
+        if chunk.is_none() {
+            break;
+        }
+    }
+
+    Ok(OpenRouterChatResult {
+        message: OpenRouterMessage {
+            tool_calls,
+            ..OpenRouterMessage::new("assistant", content)
+        },
+        finish_reason,
+        usage,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deltas(value: serde_json::Value) -> Vec<OpenRouterToolCallDelta> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn assembles_streamed_tool_calls_by_index() {
+        let mut tool_calls = Vec::new();
+
+        apply_tool_call_deltas(
+            &mut tool_calls,
+            deltas(serde_json::json!([
+                { "index": 0, "id": "call_1", "type": "function", "function": { "name": "add_node", "arguments": "{\"ty" } }
+            ])),
+        );
+        apply_tool_call_deltas(
+            &mut tool_calls,
+            deltas(serde_json::json!([
+                { "index": 0, "function": { "arguments": "pe\":\"text\"}" } },
+                { "index": 1, "id": "call_2", "function": { "name": "connect", "arguments": "{}" } }
+            ])),
+        );
+
+        assert_eq!(tool_calls.len(), 2);
+        assert_eq!(tool_calls[0].id, "call_1");
+        assert_eq!(tool_calls[0].function.arguments, "{\"type\":\"text\"}");
+        assert_eq!(tool_calls[1].function.name, "connect");
+    }
+
+    #[test]
+    fn tool_messages_round_trip_in_openai_format() {
+        let message: OpenRouterMessage = serde_json::from_value(serde_json::json!({
+            "role": "tool",
+            "content": "ok",
+            "tool_call_id": "call_1"
+        }))
+        .unwrap();
+
+        let value = serde_json::to_value(&message).unwrap();
+        assert_eq!(value["tool_call_id"], "call_1");
+        assert!(value.get("tool_calls").is_none());
+        assert!(value.get("name").is_none());
+    }
+
+    #[test]
+    fn filters_models_by_output_modality() {
+        let model: OpenRouterModel = serde_json::from_value(serde_json::json!({
+            "id": "google/gemini-2.5-flash-image",
+            "architecture": { "modality": "text+image->text+image", "output_modalities": ["image", "text"] }
+        }))
+        .unwrap();
+
+        assert!(model.has_output_modality("image"));
+        assert!(!model.has_output_modality("audio"));
+
+        let vision: OpenRouterModel = serde_json::from_value(serde_json::json!({
+            "id": "openai/gpt-4o",
+            "architecture": { "modality": "text+image->text" }
+        }))
+        .unwrap();
+
+        assert!(vision.has_output_modality("text"));
+        assert!(!vision.has_output_modality("image"));
+    }
+}
@@ -0,0 +1,527 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::attachments::{attach_to_conversation, ChatAttachment};
+use super::chat::{build_conversation, ChatMessage};
+use super::ledger::CallContext;
+use super::retry::{Idempotency, Retry};
+use super::stream::TokenUsage;
+use crate::error::CommandError;
+use crate::http::{self, HttpClient, UploadWatch};
+use crate::settings::AppSettings;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    Text,
+    Image,
+    Video,
+    Audio,
+    Upscale,
+}
+
+impl Capability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Capability::Text => "text",
+            Capability::Image => "image",
+            Capability::Video => "video",
+            Capability::Audio => "audio",
+            Capability::Upscale => "upscale",
+        }
+    }
+}
+
+/// How a provider expects its API key to be sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthScheme {
+    /// `Authorization: Bearer <key>`
+    Bearer,
+    /// `Authorization: <scheme> <key>`, e.g. fal's `Key <key>`.
+    Scheme(&'static str),
+    /// The raw key in a custom header such as `x-api-key`.
+    Header(&'static str),
+    None,
+}
+
+/// Provider-neutral generation request. Chat providers read `prompt`,
+/// `system_prompt`, `messages` and `attachments`; model-hosting providers such as
+/// Replicate and fal read `input`, with `prompt` merged in when not already set.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GenerateRequest {
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub capability: Option<Capability>,
+    #[serde(default)]
+    pub prompt: Option<String>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub messages: Option<Vec<ChatMessage>>,
+    #[serde(default)]
+    pub attachments: Option<Vec<ChatAttachment>>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub input: Option<serde_json::Value>,
+    /// Recorded with the call in the usage ledger.
+    #[serde(default)]
+    pub call_context: Option<CallContext>,
+}
+
+impl GenerateRequest {
+    /// The chat history implied by the request, with attachments encoded.
+    pub async fn conversation(&self, http: &HttpClient) -> Result<Vec<ChatMessage>, CommandError> {
+        let mut conversation = build_conversation(
+            self.system_prompt.clone(),
+            self.messages.clone(),
+            self.prompt.clone(),
+        );
+        attach_to_conversation(&mut conversation, self.attachments.clone(), http).await?;
+        Ok(conversation)
+    }
+
+    /// `input` as a JSON object, with `prompt` added unless the caller set one.
+    pub fn model_input(&self) -> Result<serde_json::Value, CommandError> {
+        let mut input = match self.input.clone() {
+            Some(serde_json::Value::Object(map)) => map,
+            Some(serde_json::Value::Null) | None => serde_json::Map::new(),
+            Some(_) => {
+                return Err(CommandError::invalid_request(
+                    "Model input must be a JSON object",
+                ))
+            }
+        };
+        if let Some(prompt) = self.prompt.as_ref().filter(|prompt| !prompt.is_empty()) {
+            input
+                .entry("prompt")
+                .or_insert_with(|| serde_json::Value::String(prompt.clone()));
+        }
+        Ok(serde_json::Value::Object(input))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GenerateResponse {
+    pub provider: String,
+    pub model: String,
+    /// `succeeded` when the output is final, otherwise the provider's job status.
+    pub status: String,
+    pub text: Option<String>,
+    pub output: Option<serde_json::Value>,
+    /// Provider job id for outputs that are still running (e.g. a Replicate prediction).
+    pub job_id: Option<String>,
+    pub usage: Option<TokenUsage>,
+    pub stop_reason: Option<String>,
+    /// Billed compute seconds, for providers that report them.
+    pub predict_time: Option<f64>,
+    /// Served from the response cache instead of calling the provider.
+    #[serde(default)]
+    pub cached: bool,
+    /// Local copies of URL outputs, kept by the response cache.
+    #[serde(default)]
+    pub local_files: Vec<String>,
+}
+
+impl GenerateResponse {
+    pub fn text(text: String) -> Self {
+        GenerateResponse {
+            status: "succeeded".to_string(),
+            text: Some(text),
+            ..Default::default()
+        }
+    }
+}
+
+/// An HTTP client preloaded with a provider's auth headers. Every request goes
+/// through `send`, so retries, connection failures and non-2xx responses behave
+/// the same way for all providers.
+#[derive(Debug, Clone)]
+pub struct ProviderClient {
+    name: &'static str,
+    http: HttpClient,
+    headers: HeaderMap,
+    retry: Retry,
+}
+
+impl ProviderClient {
+    pub fn new(
+        name: &'static str,
+        auth: AuthScheme,
+        api_key: Option<&str>,
+        extra_headers: &[(&'static str, &'static str)],
+    ) -> Result<Self, String> {
+        let mut headers = HeaderMap::new();
+
+        if let Some(api_key) = api_key.filter(|key| !key.is_empty()) {
+            let invalid_key =
+                |e: reqwest::header::InvalidHeaderValue| format!("Invalid {} API key: {}", name, e);
+            match auth {
+                AuthScheme::Bearer => {
+                    headers.insert(
+                        "Authorization",
+                        HeaderValue::from_str(&format!("Bearer {}", api_key))
+                            .map_err(invalid_key)?,
+                    );
+                }
+                AuthScheme::Scheme(scheme) => {
+                    headers.insert(
+                        "Authorization",
+                        HeaderValue::from_str(&format!("{} {}", scheme, api_key))
+                            .map_err(invalid_key)?,
+                    );
+                }
+                AuthScheme::Header(header) => {
+                    headers.insert(
+                        HeaderName::from_static(header),
+                        HeaderValue::from_str(api_key).map_err(invalid_key)?,
+                    );
+                }
+                AuthScheme::None => {}
+            }
+        }
+
+        for (name, value) in extra_headers {
+            headers.insert(
+                HeaderName::from_static(name),
+                HeaderValue::from_static(value),
+            );
+        }
+
+        Ok(ProviderClient {
+            name,
+            http: HttpClient::default(),
+            headers,
+            retry: Retry::new(name, Default::default()),
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Adds headers supplied at runtime, such as the routing headers a gateway expects.
+    pub fn with_headers(mut self, headers: &HashMap<String, String>) -> Result<Self, String> {
+        for (name, value) in headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| format!("Invalid header name '{}': {}", name, e))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| format!("Invalid value for header '{}': {}", name, e))?;
+            self.headers.insert(name, value);
+        }
+        Ok(self)
+    }
+
+    pub fn with_retry(mut self, retry: Retry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Uses the app's shared HTTP client and reports retries as `provider-retry`
+    /// events, tagged with `request_id` if given.
+    pub fn for_app(mut self, app_handle: &tauri::AppHandle, request_id: Option<&str>) -> Self {
+        self.http = http::shared_client(app_handle);
+        self.retry = self.retry.with_events(app_handle, request_id);
+        self
+    }
+
+    pub fn http(&self) -> &HttpClient {
+        &self.http
+    }
+
+    pub fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        self.http
+            .client()
+            .request(method, url)
+            .headers(self.headers.clone())
+    }
+
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.request(Method::POST, url)
+            .header(CONTENT_TYPE, "application/json")
+    }
+
+    pub fn delete(&self, url: &str) -> RequestBuilder {
+        self.request(Method::DELETE, url)
+    }
+
+    /// Sends the request under the provider's retry policy and turns transport errors
+    /// and non-2xx statuses into `Network` / `Provider` errors. The wait for response
+    /// headers is bounded by the read timeout; bodies are bounded by the caller.
+    pub async fn send(
+        &self,
+        request: RequestBuilder,
+        action: &str,
+    ) -> Result<Response, CommandError> {
+        self.send_with(request, action, Idempotency::Idempotent)
+            .await
+    }
+
+    pub async fn send_with(
+        &self,
+        request: RequestBuilder,
+        action: &str,
+        idempotency: Idempotency,
+    ) -> Result<Response, CommandError> {
+        let response = self
+            .http
+            .await_response(
+                Some(self.name),
+                action,
+                self.retry.send_with(request, idempotency),
+                |e| CommandError::network(Some(self.name), action, e),
+            )
+            .await?;
+        self.ensure_success(response).await
+    }
+
+    /// `send` for a request carrying a body from `HttpClient::watch_upload`. It is
+    /// bounded by upload progress instead of the header timeout, which would cut off
+    /// a large file that is still moving.
+    pub async fn send_upload(
+        &self,
+        request: RequestBuilder,
+        watch: UploadWatch,
+        action: &str,
+    ) -> Result<Response, CommandError> {
+        let response = watch
+            .until_stalled(async {
+                self.retry
+                    .send(request)
+                    .await
+                    .map_err(|e| CommandError::network(Some(self.name), action, e))
+            })
+            .await?;
+        self.ensure_success(response).await
+    }
+
+    async fn ensure_success(&self, response: Response) -> Result<Response, CommandError> {
+        if !response.status().is_success() {
+            return Err(CommandError::from_response(self.name, response).await);
+        }
+        Ok(response)
+    }
+
+    /// `send` for conditional requests: a `304 Not Modified` answer comes back as
+    /// `None` instead of an error.
+    pub async fn send_if_modified(
+        &self,
+        request: RequestBuilder,
+        action: &str,
+    ) -> Result<Option<Response>, CommandError> {
+        let response = self
+            .http
+            .await_response(Some(self.name), action, self.retry.send(request), |e| {
+                CommandError::network(Some(self.name), action, e)
+            })
+            .await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        self.ensure_success(response).await.map(Some)
+    }
+
+    pub async fn send_json<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        action: &str,
+    ) -> Result<T, CommandError> {
+        self.send_json_with(request, action, Idempotency::Idempotent)
+            .await
+    }
+
+    /// `send_json` for requests that must not be repeated freely, such as ones that
+    /// start a billed job.
+    pub async fn send_json_with<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        action: &str,
+        idempotency: Idempotency,
+    ) -> Result<T, CommandError> {
+        let response_text = self
+            .send_with(self.http.with_timeout(request), action, idempotency)
+            .await?
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read response: {}", e))?;
+
+        serde_json::from_str(&response_text).map_err(|e| {
+            CommandError::from(format!(
+                "Failed to parse {} response: {} - Response: {}",
+                self.name, e, response_text
+            ))
+        })
+    }
+}
+
+/// Pulls the human-readable part out of the error bodies providers commonly return
+/// (`{"error": {"message"}}`, `{"error": "..."}`, `{"detail": "..."}`), falling back
+/// to the raw body.
+pub fn error_message(body: &str) -> String {
+    let parsed: serde_json::Value = match serde_json::from_str(body) {
+        Ok(value) => value,
+        Err(_) => return body.to_string(),
+    };
+
+    let message = [
+        parsed.pointer("/error/message"),
+        parsed.get("error"),
+        parsed.get("detail"),
+        parsed.get("message"),
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|value| value.as_str())
+    .map(|message| message.to_string());
+
+    message.unwrap_or_else(|| body.to_string())
+}
+
+/// Everything a provider needs to serve one `generate` call.
+pub struct ProviderContext {
+    pub client: ProviderClient,
+    pub settings: AppSettings,
+}
+
+/// A generation backend. Implementing this and listing the type in
+/// `registry::PROVIDERS` is all it takes to make a provider reachable through
+/// `generate` and `list_providers`.
+#[async_trait]
+pub trait Provider: Send + Sync {
+    /// Identifier used by the frontend settings (`openrouter`, `replicate`, ...).
+    fn id(&self) -> &'static str;
+
+    fn name(&self) -> &'static str;
+
+    fn capabilities(&self) -> &'static [Capability];
+
+    fn auth_scheme(&self) -> AuthScheme {
+        AuthScheme::Bearer
+    }
+
+    /// Static headers sent with every request, such as API version pins.
+    fn extra_headers(&self) -> &'static [(&'static str, &'static str)] {
+        &[]
+    }
+
+    /// The key from Settings. Providers that run locally return `None` and
+    /// override `requires_api_key`.
+    fn api_key(&self, settings: &AppSettings) -> Option<String>;
+
+    fn requires_api_key(&self) -> bool {
+        true
+    }
+
+    async fn generate(
+        &self,
+        context: &ProviderContext,
+        model: &str,
+        request: &GenerateRequest,
+    ) -> Result<GenerateResponse, CommandError>;
+}
+
+impl ProviderContext {
+    pub fn new(provider: &dyn Provider, settings: AppSettings) -> Result<Self, CommandError> {
+        let api_key = provider
+            .api_key(&settings)
+            .filter(|key| !key.trim().is_empty());
+        if api_key.is_none() && provider.requires_api_key() {
+            return Err(CommandError::missing_api_key(provider.name()));
+        }
+
+        let client = ProviderClient::new(
+            provider.name(),
+            provider.auth_scheme(),
+            api_key.as_deref(),
+            provider.extra_headers(),
+        )?
+        .with_retry(Retry::from_settings(&settings, provider.id()));
+
+        Ok(ProviderContext { client, settings })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_applies_auth_scheme_and_extra_headers() {
+        let client = ProviderClient::new(
+            "Anthropic",
+            AuthScheme::Header("x-api-key"),
+            Some("sk-ant"),
+            &[("anthropic-version", "2023-06-01")],
+        )
+        .unwrap();
+        assert_eq!(client.headers["x-api-key"], "sk-ant");
+        assert_eq!(client.headers["anthropic-version"], "2023-06-01");
+
+        let client =
+            ProviderClient::new("fal", AuthScheme::Scheme("Key"), Some("abc"), &[]).unwrap();
+        assert_eq!(client.headers["authorization"], "Key abc");
+
+        let client = ProviderClient::new("Ollama", AuthScheme::None, None, &[]).unwrap();
+        assert!(client.headers.is_empty());
+    }
+
+    #[test]
+    fn error_message_reads_common_error_shapes() {
+        assert_eq!(
+            error_message(r#"{"error":{"message":"No auth credentials found","code":401}}"#),
+            "No auth credentials found"
+        );
+        assert_eq!(
+            error_message(r#"{"error":"model not found"}"#),
+            "model not found"
+        );
+        assert_eq!(
+            error_message(r#"{"detail":"Invalid version or not permitted"}"#),
+            "Invalid version or not permitted"
+        );
+        assert_eq!(error_message("Bad Gateway"), "Bad Gateway");
+    }
+
+    #[test]
+    fn model_input_merges_prompt_without_overriding() {
+        let request = GenerateRequest {
+            prompt: Some("a red fox".to_string()),
+            input: Some(serde_json::json!({ "aspect_ratio": "16:9" })),
+            ..Default::default()
+        };
+        assert_eq!(
+            request.model_input().unwrap(),
+            serde_json::json!({ "aspect_ratio": "16:9", "prompt": "a red fox" })
+        );
+
+        let request = GenerateRequest {
+            prompt: Some("ignored".to_string()),
+            input: Some(serde_json::json!({ "prompt": "kept" })),
+            ..Default::default()
+        };
+        assert_eq!(request.model_input().unwrap()["prompt"], "kept");
+
+        let request = GenerateRequest {
+            input: Some(serde_json::json!(["not", "an", "object"])),
+            ..Default::default()
+        };
+        assert!(request.model_input().is_err());
+    }
+}
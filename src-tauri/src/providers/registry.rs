@@ -0,0 +1,292 @@
+use serde::{Deserialize, Serialize};
+
+use super::anthropic::AnthropicProvider;
+use super::budget::reserve_within_budget;
+use super::cache::ResponseCache;
+use super::fal::FalProvider;
+use super::gemini::GeminiProvider;
+use super::ledger::{self, LedgerEntry};
+use super::ollama::OllamaProvider;
+use super::openai::{LmStudioProvider, OpenAIProvider};
+use super::openrouter::OpenRouterProvider;
+use super::provider::{Capability, GenerateRequest, GenerateResponse, Provider, ProviderContext};
+use super::replicate::ReplicateProvider;
+use crate::cancel::cancellable;
+use crate::error::CommandError;
+use crate::settings::{load_settings, AppSettings};
+
+static PROVIDERS: &[&dyn Provider] = &[
+    &OpenRouterProvider,
+    &OpenAIProvider,
+    &AnthropicProvider,
+    &GeminiProvider,
+    &OllamaProvider,
+    &LmStudioProvider,
+    &ReplicateProvider,
+    &FalProvider,
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProviderInfo {
+    id: String,
+    name: String,
+    capabilities: Vec<Capability>,
+    /// Whether a call could be made right now: local providers always are, hosted
+    /// ones once their key is saved.
+    configured: bool,
+}
+
+pub fn find_provider(id: &str) -> Option<&'static dyn Provider> {
+    PROVIDERS
+        .iter()
+        .copied()
+        .find(|provider| provider.id() == id)
+}
+
+fn default_target(
+    settings: &AppSettings,
+    capability: Capability,
+) -> (Option<String>, Option<String>) {
+    let (provider, model) = match capability {
+        Capability::Text => (
+            &settings.default_text_provider,
+            &settings.default_text_model,
+        ),
+        Capability::Image => (
+            &settings.default_image_provider,
+            &settings.default_image_model,
+        ),
+        Capability::Video => (
+            &settings.default_video_provider,
+            &settings.default_video_model,
+        ),
+        Capability::Audio => (
+            &settings.default_audio_provider,
+            &settings.default_audio_model,
+        ),
+        Capability::Upscale => (
+            &settings.default_upscaler_provider,
+            &settings.default_upscaler_model,
+        ),
+    };
+    (
+        provider.clone().filter(|value| !value.is_empty()),
+        model.clone().filter(|value| !value.is_empty()),
+    )
+}
+
+/// Picks the provider and model for a request. Missing values fall back to the
+/// Settings defaults for the requested capability (text when unspecified); the
+/// default model is only used together with its default provider.
+fn resolve_target(
+    request: &GenerateRequest,
+    settings: &AppSettings,
+) -> Result<(&'static dyn Provider, String), String> {
+    let capability = request.capability.unwrap_or(Capability::Text);
+    let (default_provider, default_model) = default_target(settings, capability);
+
+    let requested_provider = request
+        .provider
+        .clone()
+        .filter(|provider| !provider.is_empty());
+    let uses_default_provider =
+        requested_provider.is_none() || requested_provider == default_provider;
+    let provider_id = requested_provider.or(default_provider).ok_or_else(|| {
+        format!(
+            "No provider given and no default {} provider is set",
+            capability.as_str()
+        )
+    })?;
+
+    let provider =
+        find_provider(&provider_id).ok_or_else(|| format!("Unknown provider '{}'", provider_id))?;
+    if !provider.capabilities().contains(&capability) {
+        return Err(format!(
+            "{} does not support {} generation",
+            provider.name(),
+            capability.as_str()
+        ));
+    }
+
+    let model = request
+        .model
+        .clone()
+        .filter(|model| !model.is_empty())
+        .or(default_model.filter(|_| uses_default_provider))
+        .ok_or_else(|| format!("No model given for {}", provider.name()))?;
+
+    Ok((provider, model))
+}
+
+#[tauri::command]
+pub async fn list_providers(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<ProviderInfo>, CommandError> {
+    let settings = load_settings(app_handle).await?;
+
+    Ok(PROVIDERS
+        .iter()
+        .map(|provider| ProviderInfo {
+            id: provider.id().to_string(),
+            name: provider.name().to_string(),
+            capabilities: provider.capabilities().to_vec(),
+            configured: !provider.requires_api_key()
+                || provider
+                    .api_key(&settings)
+                    .is_some_and(|key| !key.trim().is_empty()),
+        })
+        .collect())
+}
+
+/// Runs one generation on whichever provider the request (or Settings) names.
+/// `cancel_request(request_id)` stops it.
+#[tauri::command]
+pub async fn generate(
+    app_handle: tauri::AppHandle,
+    request: GenerateRequest,
+    request_id: Option<String>,
+) -> Result<GenerateResponse, CommandError> {
+    cancellable(request_id.as_deref(), async {
+        let settings = load_settings(app_handle.clone()).await?;
+        let (provider, model) =
+            resolve_target(&request, &settings).map_err(CommandError::invalid_request)?;
+        let cache = ResponseCache::new(&app_handle, &settings).and_then(|cache| {
+            let key = cache.key(provider.id(), &model, &cache_input(&request))?;
+            Some((cache, key))
+        });
+        if let Some(mut response) = cache
+            .as_ref()
+            .and_then(|(cache, key)| cache.get_value::<GenerateResponse>(key))
+        {
+            response.cached = true;
+            return Ok(response);
+        }
+
+        let reservation = reserve_within_budget(
+            &app_handle,
+            provider.id(),
+            &model,
+            request.call_context.as_ref(),
+        )
+        .await?;
+        let mut context = ProviderContext::new(provider, settings)?;
+        context.client = context.client.for_app(&app_handle, request_id.as_deref());
+
+        let mut response = provider.generate(&context, &model, &request).await?;
+
+        let mut entry = LedgerEntry::new(provider.id(), &model, request.call_context.as_ref())
+            .with_usage(response.usage.as_ref());
+        if let Some(job_id) = &response.job_id {
+            entry = entry.with_job(job_id, response.predict_time);
+            reservation.hold(job_id);
+        } else if response.usage.is_none() {
+            // Synchronous runs such as `fal.run` report no compute time; bill the
+            // reserved estimate so they still count toward the spending limits.
+            entry.estimated_cost = reservation.estimated_cost();
+        }
+        // Jobs still running are recorded by the polling command once they finish.
+        if response.status == "succeeded" {
+            ledger::record(&app_handle, entry);
+        }
+
+        response.provider = provider.id().to_string();
+        response.model = model;
+        if let Some((cache, key)) = cache.filter(|_| response.status == "succeeded") {
+            let media = match &response.output {
+                Some(output) => {
+                    cache
+                        .download_media(context.client.http(), &key, output)
+                        .await
+                }
+                None => Some(Vec::new()),
+            };
+            // Outputs too large for the cache are returned but not stored.
+            if let Some(local_files) = media {
+                response.local_files = local_files;
+                cache.put(&key, &response, response.local_files.clone());
+            }
+        }
+        Ok(response)
+    })
+    .await
+}
+
+/// The parts of a request that shape its output; routing and ledger fields are
+/// left out so they do not split the cache.
+fn cache_input(request: &GenerateRequest) -> serde_json::Value {
+    let mut input = serde_json::to_value(request).unwrap_or_default();
+    if let Some(fields) = input.as_object_mut() {
+        for field in ["provider", "model", "call_context"] {
+            fields.remove(field);
+        }
+    }
+    input
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::default_app_settings;
+
+    #[test]
+    fn provider_ids_are_unique() {
+        for (index, provider) in PROVIDERS.iter().enumerate() {
+            assert!(
+                PROVIDERS[index + 1..]
+                    .iter()
+                    .all(|other| other.id() != provider.id()),
+                "duplicate provider id '{}'",
+                provider.id()
+            );
+        }
+    }
+
+    #[test]
+    fn resolve_falls_back_to_settings_defaults() {
+        let mut settings = default_app_settings();
+        settings.default_image_provider = Some("replicate".to_string());
+        settings.default_image_model = Some("black-forest-labs/flux-schnell".to_string());
+
+        let request = GenerateRequest {
+            capability: Some(Capability::Image),
+            ..Default::default()
+        };
+        let (provider, model) = resolve_target(&request, &settings).unwrap();
+        assert_eq!(provider.id(), "replicate");
+        assert_eq!(model, "black-forest-labs/flux-schnell");
+
+        // A different provider must not inherit the default provider's model.
+        let request = GenerateRequest {
+            provider: Some("fal".to_string()),
+            capability: Some(Capability::Image),
+            ..Default::default()
+        };
+        assert!(resolve_target(&request, &settings).is_err());
+    }
+
+    #[test]
+    fn resolve_rejects_unknown_providers_and_capabilities() {
+        let settings = default_app_settings();
+
+        let request = GenerateRequest {
+            provider: Some("nope".to_string()),
+            model: Some("x".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve_target(&request, &settings).err().as_deref(),
+            Some("Unknown provider 'nope'")
+        );
+
+        let request = GenerateRequest {
+            provider: Some("anthropic".to_string()),
+            model: Some("claude-sonnet-4-5".to_string()),
+            capability: Some(Capability::Video),
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve_target(&request, &settings).err().as_deref(),
+            Some("Anthropic does not support video generation")
+        );
+    }
+}
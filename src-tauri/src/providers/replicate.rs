@@ -0,0 +1,1215 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use reqwest::header::{ACCEPT, CACHE_CONTROL};
+use reqwest::multipart::{Form, Part};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+use tokio_util::io::ReaderStream;
+
+use super::budget::reserve_within_budget;
+use super::cache::{CacheKey, ResponseCache};
+use super::ledger::{self, CallContext, LedgerEntry};
+use super::provider::{
+    Capability, GenerateRequest, GenerateResponse, Provider, ProviderClient, ProviderContext,
+};
+use super::replicate_catalog;
+use super::replicate_files;
+use super::retry::Idempotency;
+use super::stream::{emit_delta, finish_stream, ChatStreamResult, SseEvent, SseParser, TokenUsage};
+use crate::cancel::cancellable;
+use crate::error::CommandError;
+use crate::http::HttpClient;
+use crate::settings::{load_settings, AppSettings};
+
+pub const REPLICATE_API_BASE: &str = "https://api.replicate.com/v1";
+const POLL_INITIAL_DELAY: Duration = Duration::from_millis(500);
+const POLL_MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// Marks a model reference as a deployment, e.g. `deployment:acme/image-generator`.
+const DEPLOYMENT_PREFIX: &str = "deployment:";
+
+pub const REPLICATE_PREDICTION_EVENT: &str = "replicate-prediction";
+pub const REPLICATE_UPLOAD_EVENT: &str = "replicate-upload-progress";
+/// The files API rejects anything larger.
+const MAX_UPLOAD_BYTES: u64 = 100 * 1024 * 1024;
+const UPLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Cache keys of predictions that were still running when created, stored once a
+/// poll sees them succeed.
+static PENDING_CACHE: LazyLock<Mutex<HashMap<String, CacheKey>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn pending_cache() -> MutexGuard<'static, HashMap<String, CacheKey>> {
+    PENDING_CACHE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReplicatePrediction {
+    id: String,
+    #[serde(default)]
+    model: Option<String>,
+    status: String,
+    output: Option<serde_json::Value>,
+    error: Option<String>,
+    logs: Option<String>,
+    #[serde(default)]
+    metrics: Option<serde_json::Value>,
+    #[serde(default)]
+    urls: Option<ReplicatePredictionUrls>,
+    /// Served from the response cache instead of running the model again.
+    #[serde(default)]
+    cached: bool,
+    /// Local copies of the output kept by the response cache, since Replicate's
+    /// output URLs expire after an hour.
+    #[serde(default)]
+    local_files: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct ReplicatePredictionUrls {
+    #[serde(default)]
+    get: Option<String>,
+    #[serde(default)]
+    cancel: Option<String>,
+    /// Server-sent events of the output as it is generated, for models that support it.
+    #[serde(default)]
+    stream: Option<String>,
+}
+
+impl ReplicatePrediction {
+    /// Replicate bills compute for failed and canceled runs too.
+    fn is_finished(&self) -> bool {
+        matches!(self.status.as_str(), "succeeded" | "failed" | "canceled")
+    }
+
+    fn predict_time(&self) -> Option<f64> {
+        self.metric("predict_time").and_then(|value| value.as_f64())
+    }
+
+    /// Language models report token counts alongside the timing metrics.
+    fn token_usage(&self) -> Option<TokenUsage> {
+        let count = |name| {
+            self.metric(name)
+                .and_then(|value| value.as_u64())
+                .map(|count| count as u32)
+        };
+        Some(TokenUsage {
+            input_tokens: count("input_token_count")?,
+            output_tokens: count("output_token_count")?,
+        })
+    }
+
+    fn metric(&self, name: &str) -> Option<&serde_json::Value> {
+        self.metrics.as_ref().and_then(|metrics| metrics.get(name))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReplicateModel {
+    pub owner: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub visibility: String,
+    pub github_url: Option<String>,
+    pub paper_url: Option<String>,
+    pub license_url: Option<String>,
+    pub run_count: i64,
+    pub cover_image_url: Option<String>,
+    pub default_example: Option<serde_json::Value>,
+    pub latest_version: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReplicateModelsResponse {
+    pub next: Option<String>,
+    pub previous: Option<String>,
+    pub results: Vec<ReplicateModel>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReplicateDeployment {
+    owner: String,
+    name: String,
+    /// `None` for a deployment that has never been released.
+    current_release: Option<ReplicateDeploymentRelease>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct ReplicateDeploymentRelease {
+    number: i64,
+    model: String,
+    version: String,
+    created_at: Option<String>,
+    configuration: ReplicateDeploymentConfiguration,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct ReplicateDeploymentConfiguration {
+    /// Replicate's hardware SKU, e.g. `gpu-a100-large`.
+    hardware: String,
+    min_instances: u32,
+    max_instances: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReplicateDeploymentsPage {
+    next: Option<String>,
+    results: Vec<ReplicateDeployment>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReplicateUploadEvent {
+    request_id: Option<String>,
+    filename: String,
+    bytes_sent: u64,
+    total_bytes: u64,
+}
+
+/// Counts streamed bytes and decides when a progress event is worth sending: at
+/// most once per percent, so large files do not flood the webview.
+#[derive(Debug)]
+struct UploadProgress {
+    total_bytes: u64,
+    bytes_sent: u64,
+    reported_percent: u64,
+}
+
+impl UploadProgress {
+    fn new(total_bytes: u64) -> Self {
+        UploadProgress {
+            total_bytes,
+            bytes_sent: 0,
+            reported_percent: 0,
+        }
+    }
+
+    /// Returns the new total when it is worth reporting.
+    fn advance(&mut self, bytes: u64) -> Option<u64> {
+        self.bytes_sent += bytes;
+        let percent = (self.bytes_sent * 100)
+            .checked_div(self.total_bytes)
+            .unwrap_or(100);
+        if percent == self.reported_percent {
+            return None;
+        }
+        self.reported_percent = percent;
+        Some(self.bytes_sent)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReplicateFileUpload {
+    pub id: String,
+    pub name: String,
+    pub content_type: String,
+    pub size: i64,
+    pub urls: ReplicateFileUrls,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+    /// Set when an earlier upload of the same content was handed back instead.
+    #[serde(default)]
+    pub reused: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReplicateFileUrls {
+    pub get: String,
+}
+
+pub struct ReplicateProvider;
+
+#[async_trait]
+impl Provider for ReplicateProvider {
+    fn id(&self) -> &'static str {
+        "replicate"
+    }
+
+    fn name(&self) -> &'static str {
+        "Replicate"
+    }
+
+    fn capabilities(&self) -> &'static [Capability] {
+        &[
+            Capability::Text,
+            Capability::Image,
+            Capability::Video,
+            Capability::Audio,
+            Capability::Upscale,
+        ]
+    }
+
+    fn api_key(&self, settings: &AppSettings) -> Option<String> {
+        settings.replicate_api_key.clone()
+    }
+
+    /// Creates the prediction with `Prefer: wait`, so short runs come back finished.
+    /// Longer ones return their current status and id for polling.
+    async fn generate(
+        &self,
+        context: &ProviderContext,
+        model: &str,
+        request: &GenerateRequest,
+    ) -> Result<GenerateResponse, CommandError> {
+        let input = request.model_input()?;
+        let (endpoint, request_body) = prediction_target(model, input);
+
+        let prediction: ReplicatePrediction = context
+            .client
+            .send_json_with(
+                context
+                    .client
+                    .post(&endpoint)
+                    .header("Prefer", "wait")
+                    .json(&request_body),
+                "create prediction",
+                Idempotency::CreatesJob,
+            )
+            .await?;
+
+        if prediction.status == "failed" {
+            return Err(prediction_failure(&prediction));
+        }
+
+        let text = match (&request.capability, &prediction.output) {
+            (Some(Capability::Text), Some(output)) => output_text(output),
+            _ => None,
+        };
+
+        Ok(GenerateResponse {
+            predict_time: prediction.predict_time(),
+            status: prediction.status,
+            text,
+            output: prediction.output,
+            job_id: Some(prediction.id),
+            ..Default::default()
+        })
+    }
+}
+
+fn record_finished(
+    app_handle: &tauri::AppHandle,
+    prediction: &ReplicatePrediction,
+    model: Option<&str>,
+    call_context: Option<&CallContext>,
+) {
+    if !prediction.is_finished() {
+        return;
+    }
+    let model = prediction.model.as_deref().or(model).unwrap_or_default();
+    ledger::record(
+        app_handle,
+        LedgerEntry::new("replicate", model, call_context)
+            .with_job(&prediction.id, prediction.predict_time()),
+    );
+}
+
+/// Retries are reported with `request_id` when the calling command has one.
+pub async fn replicate_client(
+    app_handle: tauri::AppHandle,
+    request_id: Option<&str>,
+) -> Result<ProviderClient, CommandError> {
+    let settings = load_settings(app_handle.clone()).await?;
+    let context = ProviderContext::new(&ReplicateProvider, settings)?;
+    Ok(context.client.for_app(&app_handle, request_id))
+}
+
+/// Picks the endpoint and body for a model reference, which can be `owner/model`,
+/// `owner/model:version`, a bare version id, or `deployment:owner/name`. Only
+/// official models run without a version, through the model-specific endpoint;
+/// deployments run whatever release is current.
+fn prediction_target(model: &str, input: serde_json::Value) -> (String, serde_json::Value) {
+    if let Some(deployment) = model.strip_prefix(DEPLOYMENT_PREFIX) {
+        return (
+            format!(
+                "{}/deployments/{}/predictions",
+                REPLICATE_API_BASE, deployment
+            ),
+            serde_json::json!({ "input": input }),
+        );
+    }
+
+    match model.split_once(':') {
+        None if model.contains('/') => (
+            format!("{}/models/{}/predictions", REPLICATE_API_BASE, model),
+            serde_json::json!({ "input": input }),
+        ),
+        _ => (
+            format!("{}/predictions", REPLICATE_API_BASE),
+            serde_json::json!({ "version": model, "input": input }),
+        ),
+    }
+}
+
+/// What `replicate_run_prediction` reports about its prediction as polling sees it change.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PredictionUpdate {
+    /// The prediction exists and can be cancelled.
+    Created,
+    Status {
+        status: String,
+    },
+    /// Log lines added since the previous event.
+    Logs {
+        logs: String,
+    },
+    /// The output so far, e.g. the tokens of a language model or a preview image.
+    Output {
+        output: serde_json::Value,
+    },
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReplicatePredictionEvent {
+    request_id: String,
+    prediction_id: String,
+    #[serde(flatten)]
+    update: PredictionUpdate,
+}
+
+/// Remembers what has been reported so each poll only emits what changed.
+#[derive(Debug, Default)]
+struct PredictionProgress {
+    status: Option<String>,
+    logs: String,
+    output: Option<serde_json::Value>,
+}
+
+impl PredictionProgress {
+    fn update(&mut self, prediction: &ReplicatePrediction) -> Vec<PredictionUpdate> {
+        let mut updates = Vec::new();
+
+        if self.status.as_deref() != Some(prediction.status.as_str()) {
+            self.status = Some(prediction.status.clone());
+            updates.push(PredictionUpdate::Status {
+                status: prediction.status.clone(),
+            });
+        }
+
+        let logs = prediction.logs.as_deref().unwrap_or_default();
+        if logs != self.logs {
+            // Replicate appends to the log; anything else is reported in full.
+            let added = logs.strip_prefix(self.logs.as_str()).unwrap_or(logs);
+            updates.push(PredictionUpdate::Logs {
+                logs: added.to_string(),
+            });
+            self.logs = logs.to_string();
+        }
+
+        if prediction.output.is_some() && prediction.output != self.output {
+            self.output = prediction.output.clone();
+            updates.push(PredictionUpdate::Output {
+                output: prediction.output.clone().unwrap_or_default(),
+            });
+        }
+
+        updates
+    }
+}
+
+fn emit_update(
+    app_handle: &tauri::AppHandle,
+    request_id: &str,
+    prediction_id: &str,
+    update: PredictionUpdate,
+) {
+    let _ = app_handle.emit(
+        REPLICATE_PREDICTION_EVENT,
+        ReplicatePredictionEvent {
+            request_id: request_id.to_string(),
+            prediction_id: prediction_id.to_string(),
+            update,
+        },
+    );
+}
+
+/// Cancels the prediction on Replicate when a run is dropped before it finishes,
+/// which is what `cancel_request` does to it.
+struct CancelOnDrop {
+    target: Option<(tauri::AppHandle, ProviderClient, Option<CallContext>)>,
+    prediction_id: String,
+}
+
+impl CancelOnDrop {
+    fn new(
+        app_handle: &tauri::AppHandle,
+        client: &ProviderClient,
+        prediction: &ReplicatePrediction,
+        call_context: Option<&CallContext>,
+    ) -> Self {
+        CancelOnDrop {
+            target: Some((app_handle.clone(), client.clone(), call_context.cloned())),
+            prediction_id: prediction.id.clone(),
+        }
+    }
+
+    /// Called once the prediction has finished and there is nothing left to cancel.
+    fn disarm(mut self) {
+        self.target = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        let Some((app_handle, client, call_context)) = self.target.take() else {
+            return;
+        };
+        let url = format!(
+            "{}/predictions/{}/cancel",
+            REPLICATE_API_BASE, self.prediction_id
+        );
+        tauri::async_runtime::spawn(async move {
+            match client
+                .send_json::<ReplicatePrediction>(
+                    client.request(Method::POST, &url),
+                    "cancel prediction",
+                )
+                .await
+            {
+                Ok(prediction) => {
+                    record_finished(&app_handle, &prediction, None, call_context.as_ref())
+                }
+                Err(e) => eprintln!("Failed to cancel Replicate prediction: {}", e),
+            }
+        });
+    }
+}
+
+/// Caches a succeeded prediction along with local copies of its output. Failed and
+/// canceled runs are left out so the next run tries again.
+async fn cache_prediction(
+    cache: &ResponseCache,
+    key: &CacheKey,
+    prediction: &mut ReplicatePrediction,
+    http: &HttpClient,
+) {
+    if prediction.status != "succeeded" {
+        return;
+    }
+    if let Some(output) = &prediction.output {
+        match cache.download_media(http, key, output).await {
+            Some(paths) => prediction.local_files = paths,
+            // Too large for the cache; the prediction is returned but not stored.
+            None => return,
+        }
+    }
+    cache.put(key, &*prediction, prediction.local_files.clone());
+}
+
+/// Language models on Replicate stream tokens into an array of strings.
+fn output_text(output: &serde_json::Value) -> Option<String> {
+    match output {
+        serde_json::Value::String(text) => Some(text.clone()),
+        serde_json::Value::Array(parts) => parts
+            .iter()
+            .map(|part| part.as_str())
+            .collect::<Option<Vec<_>>>()
+            .map(|parts| parts.join("")),
+        _ => None,
+    }
+}
+
+/// Creates a prediction, or returns the cached one when the input is deterministic
+/// and the response cache has it.
+async fn create_prediction(
+    app_handle: &tauri::AppHandle,
+    client: &ProviderClient,
+    model: &str,
+    input: serde_json::Value,
+    call_context: Option<&CallContext>,
+) -> Result<ReplicatePrediction, CommandError> {
+    let cache = ResponseCache::load(app_handle).await?.and_then(|cache| {
+        let key = cache.key("replicate", model, &input)?;
+        Some((cache, key))
+    });
+    if let Some(mut prediction) = cache
+        .as_ref()
+        .and_then(|(cache, key)| cache.get_value::<ReplicatePrediction>(key))
+    {
+        prediction.cached = true;
+        return Ok(prediction);
+    }
+
+    let reservation = reserve_within_budget(app_handle, "replicate", model, call_context).await?;
+    let (endpoint, request_body) = prediction_target(model, input);
+
+    println!("Creating prediction at: {}", endpoint);
+    if cfg!(debug_assertions) {
+        println!("Request body prepared for model: {}", model);
+    }
+
+    let mut prediction: ReplicatePrediction = client
+        .send_json_with(
+            client.post(&endpoint).json(&request_body),
+            "create prediction",
+            Idempotency::CreatesJob,
+        )
+        .await?;
+
+    reservation.hold(&prediction.id);
+    record_finished(app_handle, &prediction, Some(model), call_context);
+    if let Some((cache, key)) = cache {
+        if prediction.is_finished() {
+            cache_prediction(&cache, &key, &mut prediction, client.http()).await;
+        } else {
+            pending_cache().insert(prediction.id.clone(), key);
+        }
+    }
+    Ok(prediction)
+}
+
+async fn fetch_prediction(
+    app_handle: &tauri::AppHandle,
+    client: &ProviderClient,
+    prediction_id: &str,
+    call_context: Option<&CallContext>,
+) -> Result<ReplicatePrediction, CommandError> {
+    let url = format!("{}/predictions/{}", REPLICATE_API_BASE, prediction_id);
+
+    let mut prediction: ReplicatePrediction =
+        client.send_json(client.get(&url), "get prediction").await?;
+
+    record_finished(app_handle, &prediction, None, call_context);
+    if prediction.is_finished() {
+        let key = pending_cache().remove(&prediction.id);
+        if let (Some(key), Some(cache)) = (key, ResponseCache::load(app_handle).await?) {
+            cache_prediction(&cache, &key, &mut prediction, client.http()).await;
+        }
+    }
+    Ok(prediction)
+}
+
+#[tauri::command]
+pub async fn replicate_create_prediction(
+    app_handle: tauri::AppHandle,
+    model: String,
+    input: serde_json::Value,
+    call_context: Option<CallContext>,
+) -> Result<ReplicatePrediction, CommandError> {
+    let client = replicate_client(app_handle.clone(), None).await?;
+    create_prediction(&app_handle, &client, &model, input, call_context.as_ref()).await
+}
+
+#[tauri::command]
+pub async fn replicate_get_prediction(
+    app_handle: tauri::AppHandle,
+    prediction_id: String,
+    call_context: Option<CallContext>,
+) -> Result<ReplicatePrediction, CommandError> {
+    let client = replicate_client(app_handle.clone(), None).await?;
+    fetch_prediction(&app_handle, &client, &prediction_id, call_context.as_ref()).await
+}
+
+/// Creates a prediction and polls it until it finishes, emitting
+/// `replicate-prediction` events tagged with `request_id`. The first event carries
+/// the prediction id; `cancel_request(request_id)` stops polling and cancels the
+/// prediction on Replicate.
+#[tauri::command]
+pub async fn replicate_run_prediction(
+    app_handle: tauri::AppHandle,
+    request_id: String,
+    model: String,
+    input: serde_json::Value,
+    call_context: Option<CallContext>,
+) -> Result<ReplicatePrediction, CommandError> {
+    cancellable(Some(&request_id), async {
+        let client = replicate_client(app_handle.clone(), Some(&request_id)).await?;
+        let mut prediction =
+            create_prediction(&app_handle, &client, &model, input, call_context.as_ref()).await?;
+        emit_update(
+            &app_handle,
+            &request_id,
+            &prediction.id,
+            PredictionUpdate::Created,
+        );
+        let mut progress = PredictionProgress::default();
+        let remote = CancelOnDrop::new(&app_handle, &client, &prediction, call_context.as_ref());
+        let mut delay = POLL_INITIAL_DELAY;
+
+        loop {
+            let updates = progress.update(&prediction);
+            if !updates.is_empty() {
+                delay = POLL_INITIAL_DELAY;
+            }
+            for update in updates {
+                emit_update(&app_handle, &request_id, &prediction.id, update);
+            }
+            if prediction.is_finished() {
+                remote.disarm();
+                return Ok(prediction);
+            }
+
+            tokio::time::sleep(delay).await;
+            delay = (delay * 3 / 2).min(POLL_MAX_DELAY);
+            let polled =
+                fetch_prediction(&app_handle, &client, &prediction.id, call_context.as_ref()).await;
+            prediction = match polled {
+                Ok(prediction) => prediction,
+                // A failed poll says nothing about the prediction, which may still
+                // succeed; only a cancelled run should cancel it.
+                Err(error) => {
+                    remote.disarm();
+                    return Err(error);
+                }
+            };
+        }
+    })
+    .await
+}
+
+/// Runs a model with streamed output, emitting `chat-stream` deltas tagged with
+/// `request_id` like the other providers' stream commands. Log lines and the
+/// prediction id go out as `replicate-prediction` events.
+#[tauri::command]
+pub async fn replicate_stream_prediction(
+    app_handle: tauri::AppHandle,
+    request_id: String,
+    model: String,
+    input: serde_json::Value,
+    call_context: Option<CallContext>,
+) -> Result<ChatStreamResult, CommandError> {
+    let result = cancellable(Some(&request_id), async {
+        let client = replicate_client(app_handle.clone(), Some(&request_id)).await?;
+        let prediction =
+            create_prediction(&app_handle, &client, &model, input, call_context.as_ref()).await?;
+        // Armed before anything else can fail, so an early return cancels the job.
+        let remote = CancelOnDrop::new(&app_handle, &client, &prediction, call_context.as_ref());
+        emit_update(
+            &app_handle,
+            &request_id,
+            &prediction.id,
+            PredictionUpdate::Created,
+        );
+
+        // Cached and very short runs are already done; send their text in one delta.
+        if prediction.is_finished() {
+            remote.disarm();
+            return finished_stream_result(&app_handle, &request_id, &prediction);
+        }
+
+        let stream_url = prediction
+            .urls
+            .as_ref()
+            .and_then(|urls| urls.stream.clone())
+            .ok_or_else(|| format!("Replicate model {} does not support streaming", model))?;
+        let text = follow_stream(
+            &app_handle,
+            &request_id,
+            &client,
+            &prediction.id,
+            &stream_url,
+        )
+        .await?;
+        // The output is complete once the stream ends; nothing is left to cancel.
+        remote.disarm();
+
+        // The prediction can take a moment to be marked finished after the stream
+        // ends; wait for it so the run is billed in the usage ledger.
+        let mut finished = prediction;
+        for _ in 0..5 {
+            finished =
+                fetch_prediction(&app_handle, &client, &finished.id, call_context.as_ref()).await?;
+            if finished.is_finished() {
+                break;
+            }
+            tokio::time::sleep(POLL_INITIAL_DELAY).await;
+        }
+
+        Ok(ChatStreamResult {
+            text,
+            stop_reason: Some(finished.status.clone()),
+            usage: finished.token_usage(),
+        })
+    })
+    .await;
+    finish_stream(&app_handle, &request_id, result)
+}
+
+fn finished_stream_result(
+    app_handle: &tauri::AppHandle,
+    request_id: &str,
+    prediction: &ReplicatePrediction,
+) -> Result<ChatStreamResult, CommandError> {
+    if prediction.status != "succeeded" {
+        return Err(prediction_failure(prediction));
+    }
+    let text = prediction
+        .output
+        .as_ref()
+        .and_then(output_text)
+        .unwrap_or_default();
+    if !text.is_empty() {
+        emit_delta(app_handle, request_id, &text);
+    }
+    Ok(ChatStreamResult {
+        text,
+        stop_reason: Some(prediction.status.clone()),
+        usage: prediction.token_usage(),
+    })
+}
+
+fn prediction_failure(prediction: &ReplicatePrediction) -> CommandError {
+    let message = match &prediction.error {
+        Some(error) => format!("prediction {}: {}", prediction.status, error),
+        None => format!("prediction {}", prediction.status),
+    };
+    CommandError::provider_failed("Replicate", message)
+}
+
+/// One event of a prediction's `urls.stream` feed.
+#[derive(Debug, PartialEq)]
+enum PredictionStreamEvent {
+    Output(String),
+    Logs(String),
+    Error(String),
+    /// `reason` is set when the prediction did not succeed, e.g. `canceled`.
+    Done {
+        reason: Option<String>,
+    },
+}
+
+impl PredictionStreamEvent {
+    fn parse(event: SseEvent) -> Option<Self> {
+        let field = |name: &str| {
+            serde_json::from_str::<serde_json::Value>(&event.data)
+                .ok()
+                .and_then(|data| data.get(name)?.as_str().map(str::to_string))
+                .filter(|value| !value.is_empty())
+        };
+
+        match event.event.as_deref() {
+            Some("output") | None => Some(PredictionStreamEvent::Output(event.data.clone())),
+            Some("logs") => Some(PredictionStreamEvent::Logs(event.data.clone())),
+            Some("error") => Some(PredictionStreamEvent::Error(
+                field("detail").unwrap_or_else(|| event.data.clone()),
+            )),
+            Some("done") => Some(PredictionStreamEvent::Done {
+                reason: field("reason"),
+            }),
+            Some(_) => None,
+        }
+    }
+}
+
+/// Reads the SSE feed until `done`, forwarding output as deltas and logs as
+/// prediction events. Returns the full text.
+async fn follow_stream(
+    app_handle: &tauri::AppHandle,
+    request_id: &str,
+    client: &ProviderClient,
+    prediction_id: &str,
+    stream_url: &str,
+) -> Result<String, CommandError> {
+    let mut response = client
+        .send(
+            client
+                .get(stream_url)
+                .header(ACCEPT, "text/event-stream")
+                .header(CACHE_CONTROL, "no-store"),
+            "open prediction stream",
+        )
+        .await?;
+
+    let mut parser = SseParser::default();
+    let mut text = String::new();
+
+    loop {
+        let chunk = client.http().next_chunk(&mut response).await?;
+        let events = match &chunk {
+            Some(bytes) => parser.push(bytes),
+            None => parser.finish().into_iter().collect(),
+        };
+
+        for event in events.into_iter().filter_map(PredictionStreamEvent::parse) {
+            match event {
+                PredictionStreamEvent::Output(output) => {
+                    emit_delta(app_handle, request_id, &output);
+                    text.push_str(&output);
+                }
+                PredictionStreamEvent::Logs(logs) => emit_update(
+                    app_handle,
+                    request_id,
+                    prediction_id,
+                    PredictionUpdate::Logs { logs },
+                ),
+                PredictionStreamEvent::Error(detail) => {
+                    return Err(CommandError::provider_failed(
+                        "Replicate",
+                        format!("prediction failed: {}", detail),
+                    ));
+                }
+                PredictionStreamEvent::Done { reason: None } => return Ok(text),
+                PredictionStreamEvent::Done {
+                    reason: Some(reason),
+                } => {
+                    return Err(CommandError::provider_failed(
+                        "Replicate",
+                        format!("prediction ended: {}", reason),
+                    ));
+                }
+            }
+        }
+
+        if chunk.is_none() {
+            return Err("Replicate stream ended before the prediction finished".into());
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn replicate_cancel_prediction(
+    app_handle: tauri::AppHandle,
+    prediction_id: String,
+) -> Result<ReplicatePrediction, CommandError> {
+    let client = replicate_client(app_handle, None).await?;
+    let url = format!(
+        "{}/predictions/{}/cancel",
+        REPLICATE_API_BASE, prediction_id
+    );
+
+    client
+        .send_json(client.request(Method::POST, &url), "cancel prediction")
+        .await
+}
+
+#[tauri::command]
+pub async fn replicate_get_model(
+    app_handle: tauri::AppHandle,
+    owner: String,
+    model_name: String,
+) -> Result<ReplicateModel, CommandError> {
+    let client = replicate_client(app_handle, None).await?;
+    let url = format!("{}/models/{}/{}", REPLICATE_API_BASE, owner, model_name);
+
+    client.send_json(client.get(&url), "get model").await
+}
+
+/// The account's deployments with their current release and hardware.
+#[tauri::command]
+pub async fn replicate_list_deployments(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<ReplicateDeployment>, CommandError> {
+    let client = replicate_client(app_handle, None).await?;
+    let mut deployments = Vec::new();
+    let mut next_url = Some(format!("{}/deployments", REPLICATE_API_BASE));
+
+    while let Some(url) = next_url {
+        let page: ReplicateDeploymentsPage = client
+            .send_json(client.get(&url), "list deployments")
+            .await?;
+        deployments.extend(page.results);
+        next_url = page.next;
+    }
+
+    Ok(deployments)
+}
+
+#[tauri::command]
+pub async fn replicate_get_deployment(
+    app_handle: tauri::AppHandle,
+    owner: String,
+    name: String,
+) -> Result<ReplicateDeployment, CommandError> {
+    let client = replicate_client(app_handle, None).await?;
+    let url = format!("{}/deployments/{}/{}", REPLICATE_API_BASE, owner, name);
+
+    client.send_json(client.get(&url), "get deployment").await
+}
+
+#[tauri::command]
+pub async fn replicate_list_models(
+    app_handle: tauri::AppHandle,
+    collection_slug: Option<String>,
+) -> Result<ReplicateModelsResponse, CommandError> {
+    let client = replicate_client(app_handle.clone(), None).await?;
+
+    // Use collection endpoint if collection_slug is provided, otherwise use general models endpoint
+    if let Some(slug) = collection_slug {
+        // Collection endpoint - returns all models in one response, as { models: [...] }
+        let url = format!("{}/collections/{}", REPLICATE_API_BASE, slug);
+        let collection_data: serde_json::Value =
+            client.send_json(client.get(&url), "list models").await?;
+
+        let models = collection_data["models"]
+            .as_array()
+            .ok_or("Collection response missing models array")?;
+
+        let parsed_models: Vec<ReplicateModel> = models
+            .iter()
+            .filter_map(|m| serde_json::from_value(m.clone()).ok())
+            .collect();
+
+        Ok(ReplicateModelsResponse {
+            next: None,
+            previous: None,
+            results: parsed_models,
+        })
+    } else {
+        // The full catalog is too large to page through on every call, so it is
+        // served from the on-disk copy, which syncs itself when stale.
+        let catalog = replicate_catalog::load_synced_catalog(&app_handle, &client).await?;
+        Ok(ReplicateModelsResponse {
+            next: None,
+            previous: None,
+            results: catalog.models,
+        })
+    }
+}
+
+/// Streams a file from disk to Replicate's files API, emitting
+/// `replicate-upload-progress` events. `metadata` is stored with the file and must
+/// be a JSON object. Uploads are tracked in a local index; sending the same content
+/// again for the same run hands back the earlier upload while it stays valid.
+#[tauri::command]
+pub async fn replicate_upload_file(
+    app_handle: tauri::AppHandle,
+    file_path: String,
+    filename: String,
+    content_type: String,
+    metadata: Option<serde_json::Value>,
+    request_id: Option<String>,
+    call_context: Option<CallContext>,
+) -> Result<ReplicateFileUpload, CommandError> {
+    cancellable(request_id.as_deref(), async {
+        let mut metadata = metadata.unwrap_or_else(|| serde_json::json!({}));
+        let Some(fields) = metadata.as_object_mut() else {
+            return Err(CommandError::invalid_request(
+                "Upload metadata must be a JSON object",
+            ));
+        };
+
+        let file = tokio::fs::File::open(&file_path)
+            .await
+            .map_err(|e| format!("Failed to open file: {}", e))?;
+        let total_bytes = file
+            .metadata()
+            .await
+            .map_err(|e| format!("Failed to read file metadata: {}", e))?
+            .len();
+        if total_bytes > MAX_UPLOAD_BYTES {
+            return Err(CommandError::invalid_request(format!(
+                "{} is {} MB; Replicate accepts uploads up to {} MB",
+                filename,
+                total_bytes / (1024 * 1024),
+                MAX_UPLOAD_BYTES / (1024 * 1024)
+            )));
+        }
+
+        // The startup cleanup must finish before the index is read or a file added.
+        replicate_files::wait_for_cleanup().await;
+        let context = call_context.unwrap_or_default();
+        let sha256 = replicate_files::file_sha256(&file_path).await?;
+        if let Some(upload) = replicate_files::reusable_upload(
+            &app_handle,
+            &sha256,
+            &content_type,
+            context.run_id.as_deref(),
+        ) {
+            return Ok(ReplicateFileUpload {
+                reused: true,
+                ..upload
+            });
+        }
+        replicate_files::mark_upload(&app_handle, fields, &sha256)?;
+
+        let client = replicate_client(app_handle.clone(), request_id.as_deref()).await?;
+        let index_handle = app_handle.clone();
+
+        let mut progress = UploadProgress::new(total_bytes);
+        let event = ReplicateUploadEvent {
+            request_id: request_id.clone(),
+            filename: filename.clone(),
+            bytes_sent: 0,
+            total_bytes,
+        };
+        let chunks = ReaderStream::with_capacity(file, UPLOAD_CHUNK_SIZE).map(move |chunk| {
+            if let Ok(bytes) = &chunk {
+                if let Some(bytes_sent) = progress.advance(bytes.len() as u64) {
+                    let _ = app_handle.emit(
+                        REPLICATE_UPLOAD_EVENT,
+                        ReplicateUploadEvent {
+                            bytes_sent,
+                            ..event.clone()
+                        },
+                    );
+                }
+            }
+            chunk
+        });
+
+        let (body, watch) = client.http().watch_upload(chunks);
+        let content = Part::stream_with_length(body, total_bytes)
+            .file_name(filename.clone())
+            .mime_str(&content_type)
+            .map_err(|e| format!("Invalid content type '{}': {}", content_type, e))?;
+        let metadata = Part::text(metadata.to_string())
+            .mime_str("application/json")
+            .map_err(|e| format!("Failed to encode upload metadata: {}", e))?;
+        let form = Form::new()
+            .part("content", content)
+            .part("metadata", metadata);
+
+        let request = client
+            .request(Method::POST, &format!("{}/files", REPLICATE_API_BASE))
+            .multipart(form);
+        let response = client.send_upload(request, watch, "upload file").await?;
+        let body = client.http().read_body(response).await?;
+        let file_upload: ReplicateFileUpload = serde_json::from_slice(&body).map_err(|e| {
+            format!(
+                "Failed to parse Replicate response: {} - Response: {}",
+                e,
+                String::from_utf8_lossy(&body)
+            )
+        })?;
+
+        if cfg!(debug_assertions) {
+            println!("File uploaded successfully:");
+            println!("  ID: {}", file_upload.id);
+            println!("  URL (urls.get): {}", file_upload.urls.get);
+            println!("  Name: {}", file_upload.name);
+        }
+
+        replicate_files::track(&index_handle, &file_upload, &sha256, context);
+        Ok(file_upload)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn replicate_delete_file(
+    app_handle: tauri::AppHandle,
+    file_id: String,
+) -> Result<(), CommandError> {
+    let client = replicate_client(app_handle.clone(), None).await?;
+    let url = format!("{}/files/{}", REPLICATE_API_BASE, file_id);
+
+    client.send(client.delete(&url), "delete file").await?;
+    replicate_files::forget(&app_handle, &file_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prediction_target_matches_model_reference_format() {
+        let input = serde_json::json!({ "prompt": "fox" });
+
+        let (url, body) = prediction_target("black-forest-labs/flux-schnell", input.clone());
+        assert_eq!(
+            url,
+            "https://api.replicate.com/v1/models/black-forest-labs/flux-schnell/predictions"
+        );
+        assert!(body.get("version").is_none());
+
+        let (url, body) = prediction_target("stability-ai/sdxl:39ed52f2", input.clone());
+        assert_eq!(url, "https://api.replicate.com/v1/predictions");
+        assert_eq!(body["version"], "stability-ai/sdxl:39ed52f2");
+
+        let (url, _) = prediction_target("39ed52f2", input.clone());
+        assert_eq!(url, "https://api.replicate.com/v1/predictions");
+
+        let (url, body) = prediction_target("deployment:acme/image-generator", input);
+        assert_eq!(
+            url,
+            "https://api.replicate.com/v1/deployments/acme/image-generator/predictions"
+        );
+        assert!(body.get("version").is_none());
+    }
+
+    #[test]
+    fn progress_reports_only_what_changed() {
+        let mut prediction: ReplicatePrediction = serde_json::from_value(serde_json::json!({
+            "id": "abc",
+            "status": "starting",
+            "output": null,
+            "error": null,
+            "logs": "",
+        }))
+        .unwrap();
+        let mut progress = PredictionProgress::default();
+
+        assert_eq!(
+            progress.update(&prediction),
+            vec![PredictionUpdate::Status {
+                status: "starting".to_string()
+            }]
+        );
+        assert!(progress.update(&prediction).is_empty());
+
+        prediction.status = "processing".to_string();
+        prediction.logs = Some("step 1\n".to_string());
+        progress.update(&prediction);
+        prediction.logs = Some("step 1\nstep 2\n".to_string());
+        prediction.output = Some(serde_json::json!(["Hel"]));
+        assert_eq!(
+            progress.update(&prediction),
+            vec![
+                PredictionUpdate::Logs {
+                    logs: "step 2\n".to_string()
+                },
+                PredictionUpdate::Output {
+                    output: serde_json::json!(["Hel"])
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_prediction_stream_events() {
+        let event = |name: Option<&str>, data: &str| SseEvent {
+            event: name.map(str::to_string),
+            data: data.to_string(),
+        };
+
+        assert_eq!(
+            PredictionStreamEvent::parse(event(Some("output"), " world")),
+            Some(PredictionStreamEvent::Output(" world".to_string()))
+        );
+        assert_eq!(
+            PredictionStreamEvent::parse(event(Some("error"), r#"{"detail":"CUDA OOM"}"#)),
+            Some(PredictionStreamEvent::Error("CUDA OOM".to_string()))
+        );
+        assert_eq!(
+            PredictionStreamEvent::parse(event(Some("done"), "{}")),
+            Some(PredictionStreamEvent::Done { reason: None })
+        );
+        assert_eq!(
+            PredictionStreamEvent::parse(event(Some("done"), r#"{"reason":"canceled"}"#)),
+            Some(PredictionStreamEvent::Done {
+                reason: Some("canceled".to_string())
+            })
+        );
+        assert_eq!(PredictionStreamEvent::parse(event(Some("ping"), "")), None);
+    }
+
+    #[test]
+    fn upload_progress_reports_each_percent_once() {
+        let mut progress = UploadProgress::new(1000);
+
+        assert_eq!(progress.advance(4), None);
+        assert_eq!(progress.advance(6), Some(10));
+        assert_eq!(progress.advance(5), None);
+        assert_eq!(progress.advance(985), Some(1000));
+
+        let mut empty = UploadProgress::new(0);
+        assert_eq!(empty.advance(0), Some(0));
+    }
+
+    #[test]
+    fn output_text_joins_streamed_tokens() {
+        assert_eq!(
+            output_text(&serde_json::json!(["Hel", "lo"])).as_deref(),
+            Some("Hello")
+        );
+        assert_eq!(output_text(&serde_json::json!([1, 2])), None);
+    }
+}
@@ -0,0 +1,456 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use chrono::{DateTime, Duration, Utc};
+use futures_util::lock::Mutex;
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use super::provider::ProviderClient;
+use super::replicate::{
+    replicate_client, ReplicateModel, ReplicateModelsResponse, REPLICATE_API_BASE,
+};
+use crate::error::CommandError;
+
+const CATALOG_FILE: &str = "replicate-models.json";
+/// How long a synced catalog is trusted before the next sync.
+const CATALOG_TTL_HOURS: i64 = 24;
+/// Pages fetched per sync. A catalog larger than this fills in over later syncs,
+/// which resume from the saved cursor.
+const SYNC_PAGE_LIMIT: usize = 50;
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+
+/// Serializes syncs so two pickers opening at once don't both walk the catalog.
+static SYNC_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+
+/// The on-disk copy of Replicate's public model list.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ModelCatalog {
+    pub models: Vec<ReplicateModel>,
+    /// Cursor of the page to fetch next when a sync stopped at the page limit.
+    #[serde(default)]
+    next: Option<String>,
+    /// ETag of the first page, sent as `If-None-Match` when refreshing.
+    #[serde(default)]
+    etag: Option<String>,
+    /// Whether a sync has reached the last page at least once.
+    #[serde(default)]
+    complete: bool,
+    #[serde(default)]
+    synced_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct CatalogStatus {
+    pub cached_models: usize,
+    pub complete: bool,
+    pub stale: bool,
+    pub synced_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ModelSearchResults {
+    pub models: Vec<ReplicateModel>,
+    #[serde(flatten)]
+    pub status: CatalogStatus,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    models: Vec<serde_json::Value>,
+}
+
+impl ModelCatalog {
+    fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        let synced_at = self
+            .synced_at
+            .as_deref()
+            .and_then(|at| DateTime::parse_from_rfc3339(at).ok());
+        match synced_at {
+            Some(at) => {
+                !self.complete
+                    || self.next.is_some()
+                    || now - at.with_timezone(&Utc) > Duration::hours(CATALOG_TTL_HOURS)
+            }
+            None => true,
+        }
+    }
+
+    pub fn status(&self) -> CatalogStatus {
+        CatalogStatus {
+            cached_models: self.models.len(),
+            complete: self.complete,
+            stale: self.is_stale(Utc::now()),
+            synced_at: self.synced_at.clone(),
+        }
+    }
+
+    /// Adds new models and replaces changed ones, returning how many were either.
+    fn upsert(&mut self, models: Vec<ReplicateModel>) -> usize {
+        let index: HashMap<(String, String), usize> = self
+            .models
+            .iter()
+            .enumerate()
+            .map(|(i, model)| ((model.owner.clone(), model.name.clone()), i))
+            .collect();
+
+        let mut changed = 0;
+        for model in models.into_iter().map(slim) {
+            match index.get(&(model.owner.clone(), model.name.clone())) {
+                Some(&i) if same_model(&self.models[i], &model) => {}
+                Some(&i) => {
+                    self.models[i] = model;
+                    changed += 1;
+                }
+                None => {
+                    self.models.push(model);
+                    changed += 1;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Keyword search: every word of `query` must appear in the model's
+    /// `owner/name` or description. Name matches rank first, then run count.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<ReplicateModel> {
+        let terms: Vec<String> = query
+            .split_whitespace()
+            .map(|term| term.to_lowercase())
+            .collect();
+
+        let mut hits: Vec<(usize, &ReplicateModel)> = self
+            .models
+            .iter()
+            .filter_map(|model| {
+                let name = format!("{}/{}", model.owner, model.name).to_lowercase();
+                let description = model
+                    .description
+                    .as_deref()
+                    .unwrap_or_default()
+                    .to_lowercase();
+                if !terms
+                    .iter()
+                    .all(|term| name.contains(term) || description.contains(term))
+                {
+                    return None;
+                }
+                let name_hits = terms.iter().filter(|term| name.contains(*term)).count();
+                Some((name_hits, model))
+            })
+            .collect();
+
+        hits.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then_with(|| b.1.run_count.cmp(&a.1.run_count))
+        });
+        hits.into_iter()
+            .take(limit)
+            .map(|(_, model)| model.clone())
+            .collect()
+    }
+}
+
+/// Schemas make up most of a listed model and are served separately by
+/// `replicate_get_model_schema`, so the catalog doesn't keep them.
+fn slim(mut model: ReplicateModel) -> ReplicateModel {
+    if let Some(version) = model
+        .latest_version
+        .as_mut()
+        .and_then(|version| version.as_object_mut())
+    {
+        version.remove("openapi_schema");
+    }
+    model
+}
+
+/// Run counts tick constantly, so a model only counts as changed when its
+/// version or listing details do.
+fn same_model(a: &ReplicateModel, b: &ReplicateModel) -> bool {
+    let version_id = |model: &ReplicateModel| {
+        model
+            .latest_version
+            .as_ref()
+            .and_then(|version| version.get("id"))
+            .cloned()
+    };
+    version_id(a) == version_id(b)
+        && a.description == b.description
+        && a.visibility == b.visibility
+        && a.cover_image_url == b.cover_image_url
+}
+
+fn catalog_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app_handle
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to get app cache directory: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create app cache directory: {}", e))?;
+    Ok(dir.join(CATALOG_FILE))
+}
+
+/// A missing or unreadable catalog reads as empty and is rebuilt by the next sync.
+fn read_catalog(path: &PathBuf) -> ModelCatalog {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_catalog(path: &PathBuf, catalog: &ModelCatalog) {
+    let written = serde_json::to_string(catalog)
+        .map_err(|e| e.to_string())
+        .and_then(|content| fs::write(path, content).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        eprintln!("Failed to save Replicate model catalog: {}", e);
+    }
+}
+
+/// Walks the model list newest version first, up to `SYNC_PAGE_LIMIT` pages. A
+/// first sync (or one resuming from a saved cursor) keeps going until the last
+/// page; once the catalog is complete, a refresh stops at the first page that
+/// holds nothing new, or right away when the first page's ETag still matches.
+async fn sync_catalog(
+    client: &ProviderClient,
+    catalog: &mut ModelCatalog,
+) -> Result<(), CommandError> {
+    let resuming = catalog.next.is_some();
+    let mut url = catalog.next.clone().unwrap_or_else(|| {
+        format!(
+            "{}/models?sort_by=latest_version_created_at&sort_direction=desc",
+            REPLICATE_API_BASE
+        )
+    });
+
+    for page_number in 0..SYNC_PAGE_LIMIT {
+        let mut request = client.http().with_timeout(client.get(&url));
+        let first_page = page_number == 0 && !resuming;
+        if let Some(etag) = catalog.etag.as_deref().filter(|_| first_page) {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+
+        let Some(response) = client.send_if_modified(request, "list models").await? else {
+            catalog.next = None;
+            catalog.synced_at = Some(Utc::now().to_rfc3339());
+            return Ok(());
+        };
+        if first_page {
+            catalog.etag = response
+                .headers()
+                .get(ETAG)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+        }
+        let page: ReplicateModelsResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Replicate models page: {}", e))?;
+
+        let changed = catalog.upsert(page.results);
+        match page.next {
+            Some(next) if !(catalog.complete && changed == 0) => {
+                catalog.next = Some(next.clone());
+                url = next;
+            }
+            next => {
+                catalog.complete |= next.is_none();
+                catalog.next = None;
+                catalog.synced_at = Some(Utc::now().to_rfc3339());
+                return Ok(());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The catalog, synced first when stale. A failed sync still serves whatever was
+/// cached (marked stale) and keeps the pages it got through; it only fails when
+/// there is nothing cached to fall back on.
+pub async fn load_synced_catalog(
+    app_handle: &tauri::AppHandle,
+    client: &ProviderClient,
+) -> Result<ModelCatalog, CommandError> {
+    let _guard = SYNC_LOCK.lock().await;
+    let path = catalog_path(app_handle)?;
+    let mut catalog = read_catalog(&path);
+    if !catalog.is_stale(Utc::now()) {
+        return Ok(catalog);
+    }
+
+    let synced = sync_catalog(client, &mut catalog).await;
+    write_catalog(&path, &catalog);
+    match synced {
+        Err(e) if catalog.models.is_empty() => Err(e),
+        Err(e) => {
+            eprintln!("Failed to sync Replicate model catalog: {}", e);
+            Ok(catalog)
+        }
+        Ok(()) => Ok(catalog),
+    }
+}
+
+/// Asks Replicate's search endpoint, for when the local catalog can't be trusted
+/// to hold everything yet.
+async fn search_remote(
+    client: &ProviderClient,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<ReplicateModel>, CommandError> {
+    let limit = limit.to_string();
+    let response: SearchResponse = client
+        .send_json(
+            client
+                .get(&format!("{}/search", REPLICATE_API_BASE))
+                .query(&[("query", query), ("limit", limit.as_str())]),
+            "search models",
+        )
+        .await?;
+
+    Ok(response
+        .models
+        .into_iter()
+        .filter_map(|hit| serde_json::from_value(hit["model"].clone()).ok())
+        .collect())
+}
+
+/// Searches the cached catalog. While the catalog is stale or incomplete the query
+/// also goes to Replicate's search endpoint, and its hits are folded into the
+/// catalog; if that fails, or no API key is set, the local results are returned
+/// on their own.
+#[tauri::command]
+pub async fn replicate_search_models(
+    app_handle: tauri::AppHandle,
+    query: String,
+    limit: Option<usize>,
+) -> Result<ModelSearchResults, CommandError> {
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    let path = catalog_path(&app_handle)?;
+    let catalog = read_catalog(&path);
+
+    let catalog = if catalog.is_stale(Utc::now()) && !query.trim().is_empty() {
+        let remote = match replicate_client(app_handle, None).await {
+            Ok(client) => search_remote(&client, &query, limit).await,
+            Err(e) => Err(e),
+        };
+        match remote {
+            Ok(models) => {
+                let _guard = SYNC_LOCK.lock().await;
+                let mut catalog = read_catalog(&path);
+                if catalog.upsert(models) > 0 {
+                    write_catalog(&path, &catalog);
+                }
+                catalog
+            }
+            Err(e) => {
+                eprintln!("Replicate model search failed: {}", e);
+                catalog
+            }
+        }
+    } else {
+        catalog
+    };
+
+    Ok(ModelSearchResults {
+        models: catalog.search(&query, limit),
+        status: catalog.status(),
+    })
+}
+
+/// Syncs the catalog now if it is stale and reports its state.
+#[tauri::command]
+pub async fn replicate_sync_models(
+    app_handle: tauri::AppHandle,
+) -> Result<CatalogStatus, CommandError> {
+    let client = replicate_client(app_handle.clone(), None).await?;
+    let catalog = load_synced_catalog(&app_handle, &client).await?;
+    Ok(catalog.status())
+}
+
+#[tauri::command]
+pub async fn replicate_model_catalog_status(
+    app_handle: tauri::AppHandle,
+) -> Result<CatalogStatus, CommandError> {
+    Ok(read_catalog(&catalog_path(&app_handle)?).status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(owner: &str, name: &str, description: &str, run_count: i64) -> ReplicateModel {
+        serde_json::from_value(serde_json::json!({
+            "owner": owner,
+            "name": name,
+            "description": description,
+            "visibility": "public",
+            "github_url": null,
+            "paper_url": null,
+            "license_url": null,
+            "run_count": run_count,
+            "cover_image_url": null,
+            "latest_version": { "id": "v1", "openapi_schema": { "openapi": "3.0.2" } }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn upsert_counts_only_new_or_changed_models_and_drops_schemas() {
+        let mut catalog = ModelCatalog::default();
+        assert_eq!(
+            catalog.upsert(vec![
+                model("black-forest-labs", "flux-schnell", "Fast images", 10),
+                model("meta", "llama-3", "Text", 5),
+            ]),
+            2
+        );
+        assert!(catalog.models[0].latest_version.as_ref().unwrap()["openapi_schema"].is_null());
+
+        let mut bumped = model("meta", "llama-3", "Text", 50);
+        assert_eq!(catalog.upsert(vec![bumped.clone()]), 0);
+
+        bumped.latest_version = Some(serde_json::json!({ "id": "v2" }));
+        assert_eq!(catalog.upsert(vec![bumped]), 1);
+        assert_eq!(catalog.models.len(), 2);
+    }
+
+    #[test]
+    fn search_needs_every_term_and_ranks_name_matches_first() {
+        let mut catalog = ModelCatalog::default();
+        catalog.upsert(vec![
+            model("acme", "upscaler", "Image model for flux outputs", 1000),
+            model("black-forest-labs", "flux-schnell", "Fast image model", 10),
+            model("meta", "llama-3", "Text model", 5000),
+        ]);
+
+        let names: Vec<String> = catalog
+            .search("Flux image", 10)
+            .into_iter()
+            .map(|model| model.name)
+            .collect();
+        assert_eq!(names, vec!["flux-schnell", "upscaler"]);
+
+        assert_eq!(catalog.search("", 1)[0].name, "llama-3");
+    }
+
+    #[test]
+    fn stale_until_a_complete_sync_within_the_ttl() {
+        let now = Utc::now();
+        let mut catalog = ModelCatalog {
+            synced_at: Some(now.to_rfc3339()),
+            ..Default::default()
+        };
+        assert!(catalog.is_stale(now));
+
+        catalog.complete = true;
+        assert!(!catalog.is_stale(now));
+        assert!(catalog.is_stale(now + Duration::hours(CATALOG_TTL_HOURS + 1)));
+
+        catalog.next = Some("https://api.replicate.com/v1/models?cursor=abc".to_string());
+        assert!(catalog.is_stale(now));
+    }
+}
@@ -0,0 +1,477 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+use futures_util::lock::Mutex as AsyncMutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::Manager;
+use tokio::io::AsyncReadExt;
+
+use super::ledger::CallContext;
+use super::provider::ProviderClient;
+use super::replicate::{replicate_client, ReplicateFileUpload, REPLICATE_API_BASE};
+use crate::error::CommandError;
+
+const UPLOAD_INDEX_FILE: &str = "replicate-uploads.json";
+const INSTALL_ID_FILE: &str = "replicate-install-id";
+/// Metadata key holding the content hash of every file this app uploads.
+const UPLOAD_MARKER: &str = "noder_sha256";
+/// Metadata key naming the install that uploaded a file. Other machines and other
+/// data directories share the API key but not the index, so cleanup only ever
+/// touches files carrying this install's id.
+const INSTALL_MARKER: &str = "noder_install";
+/// An upload this close to expiring is sent again rather than reused, so it can't
+/// expire while a prediction is still reading it.
+const REUSE_MARGIN_MINUTES: i64 = 60;
+
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+/// Held by the startup cleanup while it runs; uploads wait for it so a new file
+/// can never be mistaken for a leftover.
+static CLEANUP_GATE: LazyLock<AsyncMutex<()>> = LazyLock::new(|| AsyncMutex::new(()));
+
+/// A file this app uploaded to Replicate and has not deleted yet.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TrackedUpload {
+    pub sha256: String,
+    /// The workflow run the file was uploaded for, whose cleanup deletes it.
+    #[serde(default)]
+    pub context: CallContext,
+    pub file: ReplicateFileUpload,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReplicateAccountFile {
+    #[serde(flatten)]
+    pub file: ReplicateFileUpload,
+    /// Whether the local index knows the file, i.e. this app uploaded it and it has
+    /// not been released yet.
+    pub tracked: bool,
+    pub context: Option<CallContext>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReplicateFilesPage {
+    next: Option<String>,
+    results: Vec<ReplicateFileUpload>,
+}
+
+/// What a garbage collection deletes from the account and what stays indexed.
+#[derive(Debug, PartialEq)]
+struct CollectionPlan {
+    delete: Vec<String>,
+    keep: Vec<TrackedUpload>,
+}
+
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Uploads without an expiry never expire.
+fn expires_before(upload: &TrackedUpload, time: DateTime<Utc>) -> bool {
+    upload
+        .file
+        .expires_at
+        .as_deref()
+        .and_then(parse_time)
+        .is_some_and(|expires_at| expires_at <= time)
+}
+
+/// A same-content upload from the same run (or, outside a run, from no run) that
+/// stays valid for a while. Uploads are never shared across runs, because each
+/// run's cleanup deletes its files.
+fn find_reusable<'a>(
+    uploads: &'a [TrackedUpload],
+    sha256: &str,
+    content_type: &str,
+    run_id: Option<&str>,
+    now: DateTime<Utc>,
+) -> Option<&'a TrackedUpload> {
+    let valid_until = now + Duration::minutes(REUSE_MARGIN_MINUTES);
+    uploads.iter().find(|upload| {
+        upload.sha256 == sha256
+            && upload.file.content_type == content_type
+            && upload.context.run_id.as_deref() == run_id
+            && !expires_before(upload, valid_until)
+    })
+}
+
+/// Plans the startup cleanup. When the app starts no workflow is running, so an
+/// upload still owned by a run outlived a run that never cleaned up, and a file
+/// this install marked but no longer indexes was lost by an earlier session. Both
+/// are deleted. Only files created before `started` are considered, and only
+/// account files carrying `install_id`. Expired uploads and ones already gone from
+/// the account are dropped from the index.
+fn plan_collection(
+    uploads: Vec<TrackedUpload>,
+    account_files: &[ReplicateFileUpload],
+    install_id: &str,
+    started: DateTime<Utc>,
+) -> CollectionPlan {
+    let predates_start = |file: &ReplicateFileUpload| {
+        parse_time(&file.created_at).is_some_and(|created_at| created_at < started)
+    };
+    let on_account: HashSet<&str> = account_files.iter().map(|file| file.id.as_str()).collect();
+    let indexed: HashSet<String> = uploads
+        .iter()
+        .map(|upload| upload.file.id.clone())
+        .collect();
+
+    let mut delete: Vec<String> = account_files
+        .iter()
+        .filter(|file| {
+            file.metadata.get(INSTALL_MARKER).and_then(|id| id.as_str()) == Some(install_id)
+                && !indexed.contains(&file.id)
+                && predates_start(file)
+        })
+        .map(|file| file.id.clone())
+        .collect();
+
+    let mut keep = Vec::new();
+    for upload in uploads {
+        if !predates_start(&upload.file) {
+            keep.push(upload);
+            continue;
+        }
+        if !on_account.contains(upload.file.id.as_str()) || expires_before(&upload, started) {
+            continue;
+        }
+        if upload.context.run_id.is_some() {
+            delete.push(upload.file.id.clone());
+        } else {
+            keep.push(upload);
+        }
+    }
+
+    CollectionPlan { delete, keep }
+}
+
+fn index_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let app_data = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+    fs::create_dir_all(&app_data)
+        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    Ok(app_data.join(UPLOAD_INDEX_FILE))
+}
+
+fn read_index(path: &PathBuf) -> Result<Vec<TrackedUpload>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read upload index: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse upload index: {}", e))
+}
+
+fn write_index(path: &PathBuf, uploads: &[TrackedUpload]) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(uploads)
+        .map_err(|e| format!("Failed to serialize upload index: {}", e))?;
+    fs::write(path, content).map_err(|e| format!("Failed to write upload index: {}", e))
+}
+
+/// Applies `change` to the index under the lock. Like the usage ledger, the index
+/// must never fail the upload or delete it describes, so errors are only logged.
+fn update_index(app_handle: &tauri::AppHandle, change: impl FnOnce(&mut Vec<TrackedUpload>)) {
+    let _guard = INDEX_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let updated = index_path(app_handle).and_then(|path| {
+        let mut uploads = read_index(&path)?;
+        change(&mut uploads);
+        write_index(&path, &uploads)
+    });
+    if let Err(e) = updated {
+        eprintln!("Failed to update Replicate upload index: {}", e);
+    }
+}
+
+/// A random id for this install, created on first use and kept in the app data
+/// directory. Read and created under the index lock, so the startup cleanup and a
+/// first upload can never each write a different id.
+fn install_id(app_handle: &tauri::AppHandle) -> Result<String, String> {
+    let _guard = INDEX_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = index_path(app_handle)?.with_file_name(INSTALL_ID_FILE);
+    if let Ok(id) = fs::read_to_string(&path) {
+        if !id.trim().is_empty() {
+            return Ok(id.trim().to_string());
+        }
+    }
+
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_i64(Utc::now().timestamp_micros());
+    let id = format!(
+        "{:016x}{:016x}",
+        hasher.finish(),
+        RandomState::new().build_hasher().finish()
+    );
+    fs::write(&path, &id).map_err(|e| format!("Failed to save install id: {}", e))?;
+    Ok(id)
+}
+
+/// Tags upload metadata with the content hash and this install's id.
+pub fn mark_upload(
+    app_handle: &tauri::AppHandle,
+    metadata: &mut serde_json::Map<String, serde_json::Value>,
+    sha256: &str,
+) -> Result<(), String> {
+    metadata.insert(UPLOAD_MARKER.to_string(), sha256.into());
+    metadata.insert(INSTALL_MARKER.to_string(), install_id(app_handle)?.into());
+    Ok(())
+}
+
+fn load_index(app_handle: &tauri::AppHandle) -> Result<Vec<TrackedUpload>, String> {
+    let _guard = INDEX_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    read_index(&index_path(app_handle)?)
+}
+
+pub async fn file_sha256(file_path: &str) -> Result<String, String> {
+    let mut file = tokio::fs::File::open(file_path)
+        .await
+        .map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0_u8; 64 * 1024];
+
+    loop {
+        let bytes_read = file
+            .read(&mut buffer)
+            .await
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// The still-valid upload of the same content for the same run, if there is one.
+pub fn reusable_upload(
+    app_handle: &tauri::AppHandle,
+    sha256: &str,
+    content_type: &str,
+    run_id: Option<&str>,
+) -> Option<ReplicateFileUpload> {
+    let uploads = load_index(app_handle).unwrap_or_else(|e| {
+        eprintln!("Failed to read Replicate upload index: {}", e);
+        Vec::new()
+    });
+    find_reusable(&uploads, sha256, content_type, run_id, Utc::now())
+        .map(|upload| upload.file.clone())
+}
+
+pub fn track(
+    app_handle: &tauri::AppHandle,
+    file: &ReplicateFileUpload,
+    sha256: &str,
+    context: CallContext,
+) {
+    update_index(app_handle, |uploads| {
+        uploads.retain(|upload| upload.file.id != file.id);
+        uploads.push(TrackedUpload {
+            sha256: sha256.to_string(),
+            context,
+            file: file.clone(),
+        });
+    });
+}
+
+pub fn forget(app_handle: &tauri::AppHandle, file_id: &str) {
+    update_index(app_handle, |uploads| {
+        uploads.retain(|upload| upload.file.id != file_id)
+    });
+}
+
+async fn list_account_files(
+    client: &ProviderClient,
+) -> Result<Vec<ReplicateFileUpload>, CommandError> {
+    let mut files = Vec::new();
+    let mut next_url = Some(format!("{}/files", REPLICATE_API_BASE));
+    while let Some(url) = next_url {
+        let page: ReplicateFilesPage = client.send_json(client.get(&url), "list files").await?;
+        files.extend(page.results);
+        next_url = page.next;
+    }
+    Ok(files)
+}
+
+/// Deletes orphaned uploads (see `plan_collection`) and prunes the index.
+/// Returns how many files were deleted.
+async fn collect_garbage(
+    app_handle: &tauri::AppHandle,
+    started: DateTime<Utc>,
+) -> Result<usize, CommandError> {
+    let client = replicate_client(app_handle.clone(), None).await?;
+    let install_id = install_id(app_handle)?;
+    let account_files = list_account_files(&client).await?;
+    let uploads = load_index(app_handle)?;
+    let loaded: Vec<String> = uploads
+        .iter()
+        .map(|upload| upload.file.id.clone())
+        .collect();
+    let plan = plan_collection(uploads, &account_files, &install_id, started);
+
+    let mut deleted = 0;
+    let mut failed = Vec::new();
+    for file_id in plan.delete {
+        let url = format!("{}/files/{}", REPLICATE_API_BASE, file_id);
+        match client.send(client.delete(&url), "delete file").await {
+            Ok(_) => deleted += 1,
+            Err(e) if e.status() == Some(404) => {}
+            Err(e) => {
+                eprintln!(
+                    "Failed to delete orphaned Replicate file {}: {}",
+                    file_id, e
+                );
+                failed.push(file_id);
+            }
+        }
+    }
+
+    // Files whose delete failed stay indexed so the next startup tries again, and
+    // anything tracked since the index was read is left alone.
+    let released: HashSet<String> = loaded
+        .into_iter()
+        .filter(|id| !failed.contains(id) && !plan.keep.iter().any(|upload| &upload.file.id == id))
+        .collect();
+    update_index(app_handle, |uploads| {
+        uploads.retain(|upload| !released.contains(&upload.file.id))
+    });
+    Ok(deleted)
+}
+
+/// Starts the startup cleanup in the background. Uploads wait for it through
+/// `wait_for_cleanup`; the gate is taken here, before the task is spawned, so no
+/// upload can slip in ahead of it.
+pub fn start_cleanup(app_handle: tauri::AppHandle) {
+    let started = Utc::now();
+    let gate = CLEANUP_GATE.try_lock();
+    tauri::async_runtime::spawn(async move {
+        let _gate = gate;
+        match collect_garbage(&app_handle, started).await {
+            Ok(_) | Err(CommandError::MissingApiKey { .. }) => {}
+            Err(e) => eprintln!("Failed to clean up Replicate uploads: {}", e),
+        }
+    });
+}
+
+pub async fn wait_for_cleanup() {
+    drop(CLEANUP_GATE.lock().await);
+}
+
+/// Every file on the Replicate account, marked with whether this app is still
+/// tracking it.
+#[tauri::command]
+pub async fn replicate_list_files(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<ReplicateAccountFile>, CommandError> {
+    let client = replicate_client(app_handle.clone(), None).await?;
+    let files = list_account_files(&client).await?;
+    let uploads = load_index(&app_handle)?;
+
+    Ok(files
+        .into_iter()
+        .map(|file| {
+            let context = uploads
+                .iter()
+                .find(|upload| upload.file.id == file.id)
+                .map(|upload| upload.context.clone());
+            ReplicateAccountFile {
+                tracked: context.is_some(),
+                context,
+                file,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(id: &str, expires_at: &str, install: Option<&str>) -> ReplicateFileUpload {
+        let metadata = match install {
+            Some(install) => serde_json::json!({ UPLOAD_MARKER: "abc", INSTALL_MARKER: install }),
+            None => serde_json::json!({}),
+        };
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": "input.png",
+            "content_type": "image/png",
+            "size": 10,
+            "urls": { "get": format!("https://api.replicate.com/v1/files/{}", id) },
+            "created_at": "2026-10-17T00:00:00Z",
+            "expires_at": expires_at,
+            "metadata": metadata
+        }))
+        .unwrap()
+    }
+
+    fn tracked(id: &str, expires_at: &str, run_id: Option<&str>) -> TrackedUpload {
+        TrackedUpload {
+            sha256: "abc".to_string(),
+            context: CallContext {
+                run_id: run_id.map(str::to_string),
+                ..Default::default()
+            },
+            file: file(id, expires_at, Some("this-install")),
+        }
+    }
+
+    #[test]
+    fn reuses_only_same_run_uploads_that_stay_valid() {
+        let now = parse_time("2026-10-17T12:00:00Z").unwrap();
+        let uploads = vec![
+            tracked("soon", "2026-10-17T12:30:00Z", Some("run-1")),
+            tracked("later", "2026-10-18T00:00:00Z", Some("run-1")),
+        ];
+
+        let found = find_reusable(&uploads, "abc", "image/png", Some("run-1"), now);
+        assert_eq!(found.map(|upload| upload.file.id.as_str()), Some("later"));
+
+        assert!(find_reusable(&uploads, "abc", "image/png", Some("run-2"), now).is_none());
+        assert!(find_reusable(&uploads, "abc", "image/jpeg", Some("run-1"), now).is_none());
+    }
+
+    #[test]
+    fn collection_deletes_only_this_installs_leftovers_from_before_startup() {
+        let started = parse_time("2026-10-17T12:00:00Z").unwrap();
+        let valid = "2026-10-18T00:00:00Z";
+        let mut new_upload = tracked("new-run-file", valid, Some("run-2"));
+        new_upload.file.created_at = "2026-10-17T12:00:05Z".to_string();
+        let mut new_orphan = file("new-orphan", valid, Some("this-install"));
+        new_orphan.created_at = "2026-10-17T12:00:05Z".to_string();
+        let account = vec![
+            file("lost", valid, Some("this-install")),
+            file("other-machine", valid, Some("other-install")),
+            file("foreign", valid, None),
+            file("run-file", valid, Some("this-install")),
+            file("kept", valid, Some("this-install")),
+            new_orphan,
+        ];
+        let uploads = vec![
+            tracked("run-file", valid, Some("run-1")),
+            tracked("kept", valid, None),
+            tracked("gone", valid, None),
+            tracked("expired", "2026-10-17T00:00:00Z", None),
+            new_upload,
+        ];
+
+        let plan = plan_collection(uploads, &account, "this-install", started);
+
+        assert_eq!(plan.delete, vec!["lost", "run-file"]);
+        let kept: Vec<&str> = plan
+            .keep
+            .iter()
+            .map(|upload| upload.file.id.as_str())
+            .collect();
+        assert_eq!(kept, vec!["kept", "new-run-file"]);
+    }
+}
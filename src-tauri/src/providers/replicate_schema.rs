@@ -0,0 +1,618 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::Manager;
+
+use super::replicate::{replicate_client, REPLICATE_API_BASE};
+use crate::error::CommandError;
+
+const SCHEMA_CACHE_DIR: &str = "replicate-schemas";
+/// How long a model's latest version is trusted before asking Replicate again.
+/// Schemas of a pinned version never change and are kept until cleared.
+const LATEST_VERSION_TTL_HOURS: i64 = 24;
+/// Fields without `x-order` sort after the ordered ones.
+const UNORDERED: i64 = 999;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReplicateModelVersion {
+    pub id: String,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub cog_version: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReplicateVersionsPage {
+    next: Option<String>,
+    results: Vec<ReplicateModelVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReplicateVersionDetails {
+    id: String,
+    #[serde(default)]
+    openapi_schema: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    String,
+    Integer,
+    Number,
+    Boolean,
+    /// A media or other file, sent as an uploaded file's URL or a data URI.
+    File,
+    /// A link the model fetches itself, such as LoRA weights.
+    Url,
+    Object,
+    Unknown,
+}
+
+/// One model input, normalized from the version's OpenAPI schema. For arrays,
+/// `field_type` and the constraints describe a single item.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SchemaField {
+    pub name: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub field_type: FieldType,
+    pub is_array: bool,
+    pub max_items: Option<u64>,
+    pub required: bool,
+    pub default: Option<serde_json::Value>,
+    /// Allowed values; empty when any value of the type is accepted.
+    pub options: Vec<serde_json::Value>,
+    pub minimum: Option<f64>,
+    pub maximum: Option<f64>,
+    pub max_length: Option<u64>,
+    pub format: Option<String>,
+    /// `image`, `video`, `audio` or `mask` for file inputs that carry media.
+    pub media: Option<String>,
+    pub order: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SchemaOutput {
+    pub field_type: FieldType,
+    pub is_array: bool,
+    /// Array output of a language model, whose items are tokens to join.
+    pub concatenate: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ModelSchema {
+    /// `owner/name`.
+    pub model: String,
+    pub version: String,
+    /// Sorted by `order`, then name.
+    pub inputs: Vec<SchemaField>,
+    pub output: Option<SchemaOutput>,
+    pub fetched_at: String,
+    /// Served from disk because Replicate could not be reached.
+    #[serde(default)]
+    pub stale: bool,
+}
+
+/// Follows `$ref`s into `components/schemas` and merges `allOf` parts, which is
+/// how Cog describes enum inputs. For `anyOf`/`oneOf`, the first typed variant
+/// wins; it is usually the non-null half of an optional field.
+fn resolve(property: &serde_json::Value, schemas: &serde_json::Value) -> serde_json::Value {
+    resolve_within(property, schemas, &mut Vec::new())
+}
+
+/// `following` holds the `$ref`s being expanded above this property; a ref that
+/// points back into one of them resolves to an empty object instead of recursing.
+fn resolve_within(
+    property: &serde_json::Value,
+    schemas: &serde_json::Value,
+    following: &mut Vec<String>,
+) -> serde_json::Value {
+    let mut resolved = match property.get("$ref").and_then(|r| r.as_str()) {
+        Some(reference) if following.iter().any(|seen| seen == reference) => {
+            return serde_json::Value::Object(Default::default());
+        }
+        Some(reference) => {
+            following.push(reference.to_string());
+            let target = reference
+                .strip_prefix("#/components/schemas/")
+                .and_then(|name| schemas.get(name))
+                .map(|target| resolve_within(target, schemas, following))
+                .unwrap_or_else(|| serde_json::Value::Object(Default::default()));
+            following.pop();
+            target
+        }
+        None => serde_json::Value::Object(Default::default()),
+    };
+
+    let parts = ["allOf", "anyOf", "oneOf"].iter().find_map(|key| {
+        let variants = property.get(*key)?.as_array()?;
+        Some(if *key == "allOf" {
+            variants
+                .iter()
+                .map(|part| resolve_within(part, schemas, following))
+                .collect()
+        } else {
+            variants
+                .iter()
+                .map(|part| resolve_within(part, schemas, following))
+                .find(|part| part.get("type").is_some_and(|t| t != "null"))
+                .into_iter()
+                .collect::<Vec<_>>()
+        })
+    });
+
+    if let (Some(target), Some(own)) = (resolved.as_object_mut(), property.as_object()) {
+        for part in parts.unwrap_or_default() {
+            if let Some(part) = part.as_object() {
+                target.extend(part.clone());
+            }
+        }
+        // The property's own keywords (title, default, x-order) beat referenced ones.
+        for (key, value) in own {
+            if !matches!(key.as_str(), "$ref" | "allOf" | "anyOf" | "oneOf") {
+                target.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    resolved
+}
+
+fn media_from_content_type(content_type: &str) -> Option<&'static str> {
+    ["image", "video", "audio"]
+        .into_iter()
+        .find(|media| content_type.starts_with(&format!("{}/", media)))
+}
+
+fn media_from_name(name: &str) -> Option<&'static str> {
+    let name = name.to_lowercase();
+    if name.contains("mask") {
+        Some("mask")
+    } else if name.contains("image") || name.contains("img") || name.contains("photo") {
+        Some("image")
+    } else if name.contains("video") {
+        Some("video")
+    } else if name.contains("audio") {
+        Some("audio")
+    } else {
+        None
+    }
+}
+
+/// The type of one value and, for files, what media it holds. A URI input is a
+/// file when its content type or name says what it carries; otherwise it is a
+/// plain link.
+fn value_type(name: &str, property: &serde_json::Value) -> (FieldType, Option<String>) {
+    let format = property.get("format").and_then(|f| f.as_str());
+    let media = property
+        .get("contentMediaType")
+        .and_then(|c| c.as_str())
+        .and_then(media_from_content_type)
+        .or_else(|| media_from_name(name));
+
+    let field_type = match property.get("type").and_then(|t| t.as_str()) {
+        Some("string") if matches!(format, Some("uri" | "data-uri" | "binary")) => {
+            if media.is_some() || name.to_lowercase().contains("file") {
+                FieldType::File
+            } else {
+                FieldType::Url
+            }
+        }
+        // Some models take media as a bare string; the name is the only hint.
+        Some("string") if property.get("enum").is_none() && media_from_name(name).is_some() => {
+            FieldType::File
+        }
+        Some("string") => FieldType::String,
+        Some("integer") => FieldType::Integer,
+        Some("number") => FieldType::Number,
+        Some("boolean") => FieldType::Boolean,
+        Some("object") => FieldType::Object,
+        _ => FieldType::Unknown,
+    };
+
+    let media = (field_type == FieldType::File)
+        .then_some(media)
+        .flatten()
+        .map(str::to_string);
+    (field_type, media)
+}
+
+fn normalize_field(
+    name: &str,
+    property: &serde_json::Value,
+    required: bool,
+    schemas: &serde_json::Value,
+) -> SchemaField {
+    let property = resolve(property, schemas);
+    let is_array = property.get("type").and_then(|t| t.as_str()) == Some("array");
+    let item = if is_array {
+        resolve(
+            property.get("items").unwrap_or(&serde_json::Value::Null),
+            schemas,
+        )
+    } else {
+        property.clone()
+    };
+    let (field_type, media) = value_type(name, &item);
+    let text = |key: &str| {
+        property
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    };
+
+    SchemaField {
+        name: name.to_string(),
+        title: text("title"),
+        description: text("description"),
+        field_type,
+        is_array,
+        max_items: property.get("maxItems").and_then(|v| v.as_u64()),
+        required,
+        default: property.get("default").cloned(),
+        options: item
+            .get("enum")
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default(),
+        minimum: item.get("minimum").and_then(|v| v.as_f64()),
+        maximum: item.get("maximum").and_then(|v| v.as_f64()),
+        max_length: item.get("maxLength").and_then(|v| v.as_u64()),
+        format: item
+            .get("format")
+            .and_then(|v| v.as_str())
+            .map(str::to_string),
+        media,
+        order: property
+            .get("x-order")
+            .and_then(|v| v.as_i64())
+            .unwrap_or(UNORDERED),
+    }
+}
+
+fn normalize_output(output: &serde_json::Value, schemas: &serde_json::Value) -> SchemaOutput {
+    let output = resolve(output, schemas);
+    let is_array = output.get("type").and_then(|t| t.as_str()) == Some("array");
+    let item = if is_array {
+        resolve(
+            output.get("items").unwrap_or(&serde_json::Value::Null),
+            schemas,
+        )
+    } else {
+        output.clone()
+    };
+
+    // Outputs have no name to go by, but a URI output is always a generated file.
+    let field_type = match value_type("output", &item).0 {
+        FieldType::Url => FieldType::File,
+        field_type => field_type,
+    };
+
+    SchemaOutput {
+        field_type,
+        is_array,
+        concatenate: output.get("x-cog-array-display").and_then(|v| v.as_str())
+            == Some("concatenate"),
+    }
+}
+
+/// Turns a version's OpenAPI document into the fields a node form needs.
+pub fn normalize_schema(model: &str, version: &str, openapi: &serde_json::Value) -> ModelSchema {
+    let schemas = openapi
+        .pointer("/components/schemas")
+        .cloned()
+        .unwrap_or_default();
+    let input = schemas.get("Input").cloned().unwrap_or_default();
+    let required: Vec<&str> = input
+        .get("required")
+        .and_then(|r| r.as_array())
+        .map(|names| names.iter().filter_map(|name| name.as_str()).collect())
+        .unwrap_or_default();
+
+    let mut inputs: Vec<SchemaField> = input
+        .get("properties")
+        .and_then(|p| p.as_object())
+        .map(|properties| {
+            properties
+                .iter()
+                .map(|(name, property)| {
+                    normalize_field(name, property, required.contains(&name.as_str()), &schemas)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    inputs.sort_by(|a, b| a.order.cmp(&b.order).then_with(|| a.name.cmp(&b.name)));
+
+    ModelSchema {
+        model: model.to_string(),
+        version: version.to_string(),
+        inputs,
+        output: schemas
+            .get("Output")
+            .map(|output| normalize_output(output, &schemas)),
+        fetched_at: Utc::now().to_rfc3339(),
+        stale: false,
+    }
+}
+
+fn schema_cache_path(app_handle: &tauri::AppHandle, key: &str) -> Result<PathBuf, String> {
+    let dir = app_handle
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to get app cache directory: {}", e))?
+        .join(SCHEMA_CACHE_DIR);
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create schema cache directory: {}", e))?;
+    Ok(dir.join(schema_file_name(key)))
+}
+
+/// Hashed rather than sanitized: `owner/model` and `owner_model` must not share a file.
+fn schema_file_name(key: &str) -> String {
+    format!("{:x}.json", Sha256::digest(key.as_bytes()))
+}
+
+fn read_cached_schema(path: &PathBuf) -> Option<ModelSchema> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_cached_schema(path: &PathBuf, schema: &ModelSchema) {
+    let written = serde_json::to_string(schema)
+        .map_err(|e| e.to_string())
+        .and_then(|content| fs::write(path, content).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        eprintln!("Failed to cache Replicate schema: {}", e);
+    }
+}
+
+fn is_fresh(schema: &ModelSchema, pinned: bool) -> bool {
+    pinned
+        || DateTime::parse_from_rfc3339(&schema.fetched_at)
+            .is_ok_and(|fetched| fetched + Duration::hours(LATEST_VERSION_TTL_HOURS) > Utc::now())
+}
+
+async fn fetch_schema(
+    app_handle: &tauri::AppHandle,
+    model: &str,
+    version: Option<&str>,
+) -> Result<ModelSchema, CommandError> {
+    let client = replicate_client(app_handle.clone(), None).await?;
+    let details: ReplicateVersionDetails = match version {
+        Some(version) => {
+            let url = format!(
+                "{}/models/{}/versions/{}",
+                REPLICATE_API_BASE, model, version
+            );
+            client
+                .send_json(client.get(&url), "get model version")
+                .await?
+        }
+        None => {
+            let url = format!("{}/models/{}", REPLICATE_API_BASE, model);
+            let data: serde_json::Value = client.send_json(client.get(&url), "get model").await?;
+            serde_json::from_value(data["latest_version"].clone())
+                .map_err(|_| format!("Replicate model {} has no published version", model))?
+        }
+    };
+
+    let openapi = details
+        .openapi_schema
+        .ok_or_else(|| format!("No schema found for model {}", model))?;
+    Ok(normalize_schema(model, &details.id, &openapi))
+}
+
+/// Every published version of a model, newest first.
+#[tauri::command]
+pub async fn replicate_list_model_versions(
+    app_handle: tauri::AppHandle,
+    owner: String,
+    model_name: String,
+) -> Result<Vec<ReplicateModelVersion>, CommandError> {
+    let client = replicate_client(app_handle, None).await?;
+    let mut versions = Vec::new();
+    let mut next_url = Some(format!(
+        "{}/models/{}/{}/versions",
+        REPLICATE_API_BASE, owner, model_name
+    ));
+
+    while let Some(url) = next_url {
+        let page: ReplicateVersionsPage = client
+            .send_json(client.get(&url), "list model versions")
+            .await?;
+        versions.extend(page.results);
+        next_url = page.next;
+    }
+
+    Ok(versions)
+}
+
+/// The normalized input and output description of `owner/name` (its latest
+/// version) or `owner/name:version`. Schemas are cached on disk: pinned versions
+/// for good, latest versions for a day. When Replicate cannot be reached, a cached
+/// schema is returned with `stale` set. `refresh` skips the cache.
+#[tauri::command]
+pub async fn replicate_get_model_schema(
+    app_handle: tauri::AppHandle,
+    model: String,
+    refresh: Option<bool>,
+) -> Result<ModelSchema, CommandError> {
+    let (name, version) = match model.split_once(':') {
+        Some((name, version)) => (name, Some(version)),
+        None => (model.as_str(), None),
+    };
+    if name.split('/').count() != 2 {
+        return Err(CommandError::invalid_request(format!(
+            "Invalid model '{}': expected owner/name or owner/name:version",
+            model
+        )));
+    }
+
+    let path = schema_cache_path(&app_handle, version.unwrap_or(name))?;
+    let cached = read_cached_schema(&path);
+    if let Some(schema) = &cached {
+        if !refresh.unwrap_or(false) && is_fresh(schema, version.is_some()) {
+            return Ok(schema.clone());
+        }
+    }
+
+    match fetch_schema(&app_handle, name, version).await {
+        Ok(schema) => {
+            write_cached_schema(&path, &schema);
+            Ok(schema)
+        }
+        Err(error) if error.retryable() => match cached {
+            Some(schema) => Ok(ModelSchema {
+                stale: true,
+                ..schema
+            }),
+            None => Err(error),
+        },
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn openapi() -> serde_json::Value {
+        serde_json::json!({
+            "components": { "schemas": {
+                "Input": {
+                    "type": "object",
+                    "required": ["prompt"],
+                    "properties": {
+                        "prompt": { "type": "string", "title": "Prompt", "x-order": 0 },
+                        "aspect_ratio": {
+                            "allOf": [{ "$ref": "#/components/schemas/aspect_ratio" }],
+                            "default": "1:1",
+                            "x-order": 1
+                        },
+                        "num_outputs": {
+                            "type": "integer", "default": 1, "minimum": 1, "maximum": 4, "x-order": 2
+                        },
+                        "input_images": {
+                            "type": "array",
+                            "items": { "type": "string", "format": "uri" },
+                            "maxItems": 3,
+                            "x-order": 3
+                        },
+                        "lora_weights": {
+                            "anyOf": [{ "type": "string", "format": "uri" }, { "type": "null" }]
+                        }
+                    }
+                },
+                "aspect_ratio": {
+                    "type": "string", "title": "aspect_ratio", "enum": ["1:1", "16:9"]
+                },
+                "Output": {
+                    "type": "array",
+                    "items": { "type": "string", "format": "uri" }
+                }
+            }}
+        })
+    }
+
+    #[test]
+    fn schema_files_do_not_collide_for_similar_model_names() {
+        let a = schema_file_name("owner/model");
+        assert_ne!(a, schema_file_name("owner_model"));
+        assert_ne!(a, schema_file_name("owner/model:v1"));
+        assert_eq!(a, schema_file_name("owner/model"));
+        assert!(a.ends_with(".json"));
+    }
+
+    #[test]
+    fn normalizes_inputs_in_order_with_resolved_refs() {
+        let schema = normalize_schema("black-forest-labs/flux", "abc", &openapi());
+        let names: Vec<&str> = schema.inputs.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "prompt",
+                "aspect_ratio",
+                "num_outputs",
+                "input_images",
+                "lora_weights"
+            ]
+        );
+
+        let prompt = &schema.inputs[0];
+        assert!(prompt.required);
+        assert_eq!(prompt.field_type, FieldType::String);
+
+        let aspect_ratio = &schema.inputs[1];
+        assert_eq!(aspect_ratio.field_type, FieldType::String);
+        assert_eq!(aspect_ratio.options, vec!["1:1", "16:9"]);
+        assert_eq!(aspect_ratio.default, Some(serde_json::json!("1:1")));
+        assert!(!aspect_ratio.required);
+
+        let num_outputs = &schema.inputs[2];
+        assert_eq!(num_outputs.field_type, FieldType::Integer);
+        assert_eq!(
+            (num_outputs.minimum, num_outputs.maximum),
+            (Some(1.0), Some(4.0))
+        );
+
+        let images = &schema.inputs[3];
+        assert!(images.is_array);
+        assert_eq!(images.field_type, FieldType::File);
+        assert_eq!(images.media.as_deref(), Some("image"));
+        assert_eq!(images.max_items, Some(3));
+
+        let lora = &schema.inputs[4];
+        assert_eq!(lora.field_type, FieldType::Url);
+        assert_eq!(lora.order, UNORDERED);
+
+        assert_eq!(
+            schema.output,
+            Some(SchemaOutput {
+                field_type: FieldType::File,
+                is_array: true,
+                concatenate: false,
+            })
+        );
+    }
+
+    #[test]
+    fn self_referencing_schemas_resolve_without_recursing_forever() {
+        let schemas = serde_json::json!({
+            "Node": {
+                "type": "object",
+                "title": "Node",
+                "properties": { "next": { "$ref": "#/components/schemas/Node" } },
+                "allOf": [{ "$ref": "#/components/schemas/Node" }]
+            },
+            "Ping": { "$ref": "#/components/schemas/Pong" },
+            "Pong": { "$ref": "#/components/schemas/Ping" }
+        });
+
+        let node = resolve(
+            &serde_json::json!({ "$ref": "#/components/schemas/Node" }),
+            &schemas,
+        );
+        assert_eq!(node.get("type"), Some(&serde_json::json!("object")));
+        assert_eq!(node.get("title"), Some(&serde_json::json!("Node")));
+
+        let ping = resolve(
+            &serde_json::json!({ "$ref": "#/components/schemas/Ping", "title": "Ping" }),
+            &schemas,
+        );
+        assert_eq!(ping, serde_json::json!({ "title": "Ping" }));
+    }
+
+    #[test]
+    fn pinned_versions_never_expire() {
+        let mut schema = normalize_schema("owner/model", "abc", &serde_json::json!({}));
+        assert!(schema.inputs.is_empty());
+        assert!(is_fresh(&schema, false));
+
+        schema.fetched_at = (Utc::now() - Duration::hours(48)).to_rfc3339();
+        assert!(!is_fresh(&schema, false));
+        assert!(is_fresh(&schema, true));
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+
+use crate::settings::AppSettings;
+
+pub const PROVIDER_RETRY_EVENT: &str = "provider-retry";
+
+/// How often and how patiently a provider call is retried. Configured per provider
+/// id through `AppSettings::retry_policies`; missing fields keep their defaults.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts including the first; `1` disables retries.
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    /// Upper bound for a backoff delay. A `Retry-After` longer than this is not
+    /// waited out; the error is returned instead.
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay_ms: 500,
+            max_delay_ms: 30_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn for_provider(settings: &AppSettings, provider: &str) -> Self {
+        settings
+            .retry_policies
+            .as_ref()
+            .and_then(|policies| policies.get(provider))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Full jitter: a random delay between zero and the exponential backoff cap.
+    fn backoff(&self, retry: u32) -> Duration {
+        let cap = self
+            .base_delay_ms
+            .saturating_mul(1u64 << retry.min(20))
+            .min(self.max_delay_ms);
+        let jitter = RandomState::new().build_hasher().finish();
+        Duration::from_millis(jitter % (cap + 1))
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ProviderRetryEvent {
+    pub provider: String,
+    pub request_id: Option<String>,
+    /// The attempt about to be made, counting the first call as 1.
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub delay_ms: u64,
+    pub reason: String,
+}
+
+/// Whether a request may be sent again after a failure that leaves open whether the
+/// server acted on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Idempotency {
+    /// Reads, and calls whose repeat costs no more than a second answer.
+    Idempotent,
+    /// Calls that start a billed prediction or job. A timeout or 5xx may come after
+    /// the job was created, so they are only retried when the server certainly
+    /// turned them away: connect errors, 429 and 529.
+    CreatesJob,
+}
+
+/// Rate limits, overload and gateway errors clear up on their own; anything else
+/// (bad input, auth, missing models) fails the same way on every attempt.
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(status.as_u16(), 408 | 429 | 500 | 502 | 503 | 504 | 529)
+}
+
+fn should_retry_status(status: StatusCode, idempotency: Idempotency) -> bool {
+    match idempotency {
+        Idempotency::Idempotent => is_retryable_status(status),
+        Idempotency::CreatesJob => matches!(status.as_u16(), 429 | 529),
+    }
+}
+
+fn should_retry_error(error: &reqwest::Error, idempotency: Idempotency) -> bool {
+    match idempotency {
+        Idempotency::Idempotent => error.is_connect() || error.is_timeout(),
+        Idempotency::CreatesJob => error.is_connect(),
+    }
+}
+
+/// Reads `Retry-After` as either delay seconds or an HTTP date.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.signed_duration_since(chrono::Utc::now());
+    Some(wait.to_std().unwrap_or_default())
+}
+
+/// Sends requests under a retry policy, reporting each retry as a
+/// `provider-retry` event when an app handle is attached.
+#[derive(Debug, Clone)]
+pub struct Retry {
+    policy: RetryPolicy,
+    provider: String,
+    app_handle: Option<tauri::AppHandle>,
+    request_id: Option<String>,
+}
+
+impl Retry {
+    pub fn new(provider: &str, policy: RetryPolicy) -> Self {
+        Retry {
+            policy,
+            provider: provider.to_string(),
+            app_handle: None,
+            request_id: None,
+        }
+    }
+
+    pub fn from_settings(settings: &AppSettings, provider: &str) -> Self {
+        Retry::new(provider, RetryPolicy::for_provider(settings, provider))
+    }
+
+    pub fn with_events(mut self, app_handle: &tauri::AppHandle, request_id: Option<&str>) -> Self {
+        self.app_handle = Some(app_handle.clone());
+        self.request_id = request_id.map(str::to_string);
+        self
+    }
+
+    fn notify(&self, attempt: u32, delay: Duration, reason: String) {
+        if let Some(app_handle) = &self.app_handle {
+            let _ = app_handle.emit(
+                PROVIDER_RETRY_EVENT,
+                ProviderRetryEvent {
+                    provider: self.provider.clone(),
+                    request_id: self.request_id.clone(),
+                    attempt,
+                    max_attempts: self.policy.max_attempts,
+                    delay_ms: delay.as_millis() as u64,
+                    reason,
+                },
+            );
+        }
+    }
+
+    /// Sends the request, retrying retryable failures. The last response is returned
+    /// as-is, successful or not, so callers keep their own error formatting. Requests
+    /// with streaming bodies cannot be cloned and are sent exactly once.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, reqwest::Error> {
+        self.send_with(request, Idempotency::Idempotent).await
+    }
+
+    pub async fn send_with(
+        &self,
+        request: RequestBuilder,
+        idempotency: Idempotency,
+    ) -> Result<Response, reqwest::Error> {
+        let mut attempt = 1;
+
+        loop {
+            let retry_request = if attempt < self.policy.max_attempts {
+                request.try_clone()
+            } else {
+                None
+            };
+            let Some(next_request) = retry_request else {
+                return request.send().await;
+            };
+
+            let (delay, reason) = match next_request.send().await {
+                Ok(response) if should_retry_status(response.status(), idempotency) => {
+                    let backoff = self.policy.backoff(attempt - 1);
+                    match retry_after(response.headers()) {
+                        Some(wait) if wait > Duration::from_millis(self.policy.max_delay_ms) => {
+                            return Ok(response);
+                        }
+                        Some(wait) => (wait.max(backoff), response.status().to_string()),
+                        None => (backoff, response.status().to_string()),
+                    }
+                }
+                Err(error) if should_retry_error(&error, idempotency) => {
+                    (self.policy.backoff(attempt - 1), error.to_string())
+                }
+                result => return result,
+            };
+
+            attempt += 1;
+            self.notify(attempt, delay, reason);
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Answers one connection per canned response, in order.
+    fn serve_sequence(responses: Vec<&'static str>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        std::thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        base_url
+    }
+
+    const UNAVAILABLE: &str =
+        "HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const RATE_LIMITED: &str =
+        "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const BAD_REQUEST: &str =
+        "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const OK: &str = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok";
+
+    fn quick_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay_ms: 1,
+            max_delay_ms: 10,
+        }
+    }
+
+    #[test]
+    fn retries_retryable_statuses_until_success() {
+        let base_url = serve_sequence(vec![UNAVAILABLE, UNAVAILABLE, OK]);
+        let retry = Retry::new("test", quick_policy(3));
+
+        let response =
+            tauri::async_runtime::block_on(retry.send(reqwest::Client::new().get(&base_url)))
+                .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn returns_fatal_and_exhausted_responses_unchanged() {
+        let base_url = serve_sequence(vec![BAD_REQUEST]);
+        let retry = Retry::new("test", quick_policy(3));
+        let response =
+            tauri::async_runtime::block_on(retry.send(reqwest::Client::new().get(&base_url)))
+                .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let base_url = serve_sequence(vec![UNAVAILABLE, UNAVAILABLE]);
+        let retry = Retry::new("test", quick_policy(2));
+        let response =
+            tauri::async_runtime::block_on(retry.send(reqwest::Client::new().get(&base_url)))
+                .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[test]
+    fn job_creating_requests_retry_only_rate_limits() {
+        let base_url = serve_sequence(vec![UNAVAILABLE, OK]);
+        let retry = Retry::new("test", quick_policy(3));
+        let response = tauri::async_runtime::block_on(retry.send_with(
+            reqwest::Client::new().post(&base_url),
+            Idempotency::CreatesJob,
+        ))
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let base_url = serve_sequence(vec![RATE_LIMITED, OK]);
+        let response = tauri::async_runtime::block_on(retry.send_with(
+            reqwest::Client::new().post(&base_url),
+            Idempotency::CreatesJob,
+        ))
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn parses_retry_after_seconds_and_dates() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "7".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+
+        headers.insert(
+            RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+        headers.insert(RETRY_AFTER, "soon".parse().unwrap());
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    #[test]
+    fn backoff_stays_within_the_policy_cap() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay_ms: 100,
+            max_delay_ms: 1_000,
+        };
+        for retry in 0..10 {
+            assert!(policy.backoff(retry) <= Duration::from_millis(1_000));
+        }
+        assert!(policy.backoff(0) <= Duration::from_millis(100));
+    }
+}
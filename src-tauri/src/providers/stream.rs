@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+
+use crate::error::CommandError;
+
+pub const CHAT_STREAM_EVENT: &str = "chat-stream";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Incremental parser for `text/event-stream` bodies. Bytes are buffered until a
+/// full line is available so multi-byte characters split across chunks survive.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let raw: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&raw);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                if let Some(event) = self.take_event() {
+                    events.push(event);
+                }
+                continue;
+            }
+
+            if line.starts_with(':') {
+                continue;
+            }
+
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+
+            match field {
+                "event" => self.event = Some(value.to_string()),
+                "data" => self.data.push(value.to_string()),
+                _ => {}
+            }
+        }
+
+        events
+    }
+
+    /// Flushes a trailing event when the stream ends without a blank line.
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        let mut events = if self.buffer.is_empty() {
+            Vec::new()
+        } else {
+            self.push(b"\n")
+        };
+        events.pop().or_else(|| self.take_event())
+    }
+
+    fn take_event(&mut self) -> Option<SseEvent> {
+        if self.data.is_empty() {
+            self.event = None;
+            return None;
+        }
+        Some(SseEvent {
+            event: self.event.take(),
+            data: std::mem::take(&mut self.data).join("\n"),
+        })
+    }
+}
+
+/// Line splitter for newline-delimited JSON bodies such as Ollama's streams.
+#[derive(Debug, Default)]
+pub struct NdjsonParser {
+    buffer: Vec<u8>,
+}
+
+impl NdjsonParser {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut lines = Vec::new();
+
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let raw: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&raw).trim().to_string();
+            if !line.is_empty() {
+                lines.push(line);
+            }
+        }
+
+        lines
+    }
+
+    pub fn finish(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.buffer);
+        let line = String::from_utf8_lossy(&rest).trim().to_string();
+        if line.is_empty() {
+            None
+        } else {
+            Some(line)
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct TokenUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ChatStreamResult {
+    pub text: String,
+    pub stop_reason: Option<String>,
+    pub usage: Option<TokenUsage>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChatStreamEvent {
+    Delta {
+        request_id: String,
+        text: String,
+    },
+    Done {
+        request_id: String,
+        text: String,
+        stop_reason: Option<String>,
+        usage: Option<TokenUsage>,
+    },
+    /// Carries the same fields as the command's rejected error.
+    Error {
+        request_id: String,
+        #[serde(flatten)]
+        error: CommandError,
+    },
+}
+
+pub fn emit_delta(app_handle: &tauri::AppHandle, request_id: &str, text: &str) {
+    let _ = app_handle.emit(
+        CHAT_STREAM_EVENT,
+        ChatStreamEvent::Delta {
+            request_id: request_id.to_string(),
+            text: text.to_string(),
+        },
+    );
+}
+
+/// Emits the terminal `done`/`error` event for a stream and hands the result back
+/// to the command so the invoke promise resolves with the same payload.
+pub fn finish_stream(
+    app_handle: &tauri::AppHandle,
+    request_id: &str,
+    result: Result<ChatStreamResult, CommandError>,
+) -> Result<ChatStreamResult, CommandError> {
+    let event = match &result {
+        Ok(done) => ChatStreamEvent::Done {
+            request_id: request_id.to_string(),
+            text: done.text.clone(),
+            stop_reason: done.stop_reason.clone(),
+            usage: done.usage.clone(),
+        },
+        Err(error) => ChatStreamEvent::Error {
+            request_id: request_id.to_string(),
+            error: error.clone(),
+        },
+    };
+    let _ = app_handle.emit(CHAT_STREAM_EVENT, event);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_events_split_across_chunks() {
+        let mut parser = SseParser::default();
+
+        assert!(parser
+            .push(b"event: message_start\ndata: {\"a\"")
+            .is_empty());
+        let events = parser.push(b":1}\n\ndata: second\r\n\r\n");
+
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: Some("message_start".to_string()),
+                    data: "{\"a\":1}".to_string(),
+                },
+                SseEvent {
+                    event: None,
+                    data: "second".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn joins_multiline_data_and_skips_comments() {
+        let mut parser = SseParser::default();
+
+        let events = parser.push(b": keep-alive\ndata: one\ndata: two\n\n");
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "one\ntwo");
+    }
+
+    #[test]
+    fn keeps_multibyte_characters_split_between_chunks() {
+        let mut parser = SseParser::default();
+        let bytes = "data: héllo\n\n".as_bytes();
+
+        assert!(parser.push(&bytes[..8]).is_empty());
+        let events = parser.push(&bytes[8..]);
+
+        assert_eq!(events[0].data, "héllo");
+    }
+
+    #[test]
+    fn ndjson_parser_splits_lines_across_chunks() {
+        let mut parser = NdjsonParser::default();
+
+        assert_eq!(
+            parser.push(b"{\"a\":1}\n{\"b\""),
+            vec!["{\"a\":1}".to_string()]
+        );
+        assert!(parser.push(b":2}").is_empty());
+        assert_eq!(parser.finish(), Some("{\"b\":2}".to_string()));
+    }
+
+    #[test]
+    fn finish_flushes_unterminated_event() {
+        let mut parser = SseParser::default();
+
+        assert!(parser.push(b"data: [DONE]").is_empty());
+
+        assert_eq!(
+            parser.finish().map(|event| event.data),
+            Some("[DONE]".to_string())
+        );
+    }
+
+    #[test]
+    fn error_events_carry_the_command_error_fields() {
+        let event = ChatStreamEvent::Error {
+            request_id: "req-1".to_string(),
+            error: CommandError::missing_api_key("Anthropic"),
+        };
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "error");
+        assert_eq!(json["request_id"], "req-1");
+        assert_eq!(json["code"], "missing_api_key");
+        assert_eq!(json["retryable"], false);
+        assert!(json["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Anthropic API key not configured"));
+    }
+}
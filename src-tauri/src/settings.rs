@@ -1,8 +1,13 @@
+use std::collections::HashMap;
 use std::fs;
 
 use serde::{Deserialize, Serialize};
 use tauri::Manager;
 
+use crate::error::CommandError;
+use crate::http::{self, HttpClient};
+use crate::providers::retry::RetryPolicy;
+
 const KEYRING_SERVICE: &str = "com.oshtz.noder";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -38,9 +43,32 @@ pub struct AppSettings {
     pub default_video_provider: Option<String>,
     pub default_audio_provider: Option<String>,
     pub default_upscaler_provider: Option<String>,
+    /// Spending limits in USD, checked against the usage ledger before provider calls.
+    pub daily_spend_limit: Option<f64>,
+    pub run_spend_limit: Option<f64>,
+    /// Daily limits keyed by provider id (`replicate`, `anthropic`, ...).
+    pub provider_daily_spend_limits: Option<HashMap<String, f64>>,
+    /// Retry behaviour keyed by provider id; providers not listed use the default.
+    pub retry_policies: Option<HashMap<String, RetryPolicy>>,
+    /// Proxy for all outgoing requests, e.g. `http://proxy.corp:8080`.
+    pub http_proxy: Option<String>,
+    pub http_proxy_username: Option<String>,
+    /// Kept in secure storage like the API keys.
+    pub http_proxy_password: Option<String>,
+    /// Comma-separated hosts that bypass the proxy; localhost always does.
+    pub http_no_proxy: Option<String>,
+    /// PEM file with extra root certificates, for TLS-inspecting proxies.
+    pub http_ca_bundle_path: Option<String>,
+    pub http_connect_timeout_secs: Option<u64>,
+    pub http_read_timeout_secs: Option<u64>,
+    /// Reuses results of seeded or zero-temperature generations with the same input.
+    pub response_cache_enabled: Option<bool>,
+    pub response_cache_ttl_hours: Option<u64>,
+    /// Includes downloaded media; least recently used entries are evicted first.
+    pub response_cache_max_mb: Option<u64>,
 }
 
-fn default_app_settings() -> AppSettings {
+pub fn default_app_settings() -> AppSettings {
     AppSettings {
         replicate_api_key: None,
         fal_api_key: None,
@@ -67,6 +95,20 @@ fn default_app_settings() -> AppSettings {
         default_video_provider: None,
         default_audio_provider: None,
         default_upscaler_provider: None,
+        daily_spend_limit: None,
+        run_spend_limit: None,
+        provider_daily_spend_limits: None,
+        retry_policies: None,
+        http_proxy: None,
+        http_proxy_username: None,
+        http_proxy_password: None,
+        http_no_proxy: None,
+        http_ca_bundle_path: None,
+        http_connect_timeout_secs: None,
+        http_read_timeout_secs: None,
+        response_cache_enabled: None,
+        response_cache_ttl_hours: None,
+        response_cache_max_mb: None,
     }
 }
 
@@ -95,6 +137,10 @@ fn has_plaintext_api_keys(settings: &AppSettings) -> bool {
             .gemini_api_key
             .as_deref()
             .is_some_and(|value| !value.trim().is_empty())
+        || settings
+            .http_proxy_password
+            .as_deref()
+            .is_some_and(|value| !value.trim().is_empty())
 }
 
 fn strip_api_keys(settings: &mut AppSettings) {
@@ -104,6 +150,7 @@ fn strip_api_keys(settings: &mut AppSettings) {
     settings.openrouter_api_key = None;
     settings.anthropic_api_key = None;
     settings.gemini_api_key = None;
+    settings.http_proxy_password = None;
 }
 
 fn keyring_entry(name: &str) -> Result<keyring::Entry, String> {
@@ -147,7 +194,8 @@ fn save_api_keys(settings: &AppSettings) -> Result<(), String> {
     save_api_key("openai_api_key", settings.openai_api_key.as_ref())?;
     save_api_key("openrouter_api_key", settings.openrouter_api_key.as_ref())?;
     save_api_key("anthropic_api_key", settings.anthropic_api_key.as_ref())?;
-    save_api_key("gemini_api_key", settings.gemini_api_key.as_ref())
+    save_api_key("gemini_api_key", settings.gemini_api_key.as_ref())?;
+    save_api_key("http_proxy_password", settings.http_proxy_password.as_ref())
 }
 
 fn load_api_keys(settings: &mut AppSettings) -> Result<(), String> {
@@ -157,6 +205,7 @@ fn load_api_keys(settings: &mut AppSettings) -> Result<(), String> {
     settings.openrouter_api_key = load_api_key("openrouter_api_key")?;
     settings.anthropic_api_key = load_api_key("anthropic_api_key")?;
     settings.gemini_api_key = load_api_key("gemini_api_key")?;
+    settings.http_proxy_password = load_api_key("http_proxy_password")?;
     Ok(())
 }
 
@@ -164,13 +213,16 @@ fn load_api_keys(settings: &mut AppSettings) -> Result<(), String> {
 pub async fn save_settings(
     app_handle: tauri::AppHandle,
     settings: AppSettings,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let app_data = app_handle
         .path()
         .app_data_dir()
         .map_err(|e| format!("Failed to get app data directory: {}", e))?;
 
     let settings_file = app_data.join("settings.json");
+
+    // Reject network settings that cannot produce a client before persisting them.
+    let http_client = HttpClient::from_settings(&settings)?;
     save_api_keys(&settings)?;
 
     let mut persisted_settings = settings;
@@ -181,11 +233,12 @@ pub async fn save_settings(
 
     fs::write(settings_file, json).map_err(|e| format!("Failed to write settings file: {}", e))?;
 
+    http::install(&app_handle, http_client);
     Ok(())
 }
 
 #[tauri::command]
-pub async fn load_settings(app_handle: tauri::AppHandle) -> Result<AppSettings, String> {
+pub async fn load_settings(app_handle: tauri::AppHandle) -> Result<AppSettings, CommandError> {
     let app_data = app_handle
         .path()
         .app_data_dir()
@@ -245,6 +298,7 @@ mod tests {
         settings.openrouter_api_key = Some("openrouter-token".to_string());
         settings.anthropic_api_key = Some("anthropic-token".to_string());
         settings.gemini_api_key = Some("gemini-token".to_string());
+        settings.http_proxy_password = Some("proxy-password".to_string());
 
         strip_api_keys(&mut settings);
 
@@ -254,5 +308,6 @@ mod tests {
         assert!(settings.openrouter_api_key.is_none());
         assert!(settings.anthropic_api_key.is_none());
         assert!(settings.gemini_api_key.is_none());
+        assert!(settings.http_proxy_password.is_none());
     }
 }
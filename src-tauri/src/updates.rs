@@ -7,6 +7,8 @@ use reqwest::header::USER_AGENT;
 use sha2::{Digest, Sha256};
 use tauri::Manager;
 
+use crate::error::CommandError;
+use crate::http;
 use crate::path_utils::{sanitize_component, sanitize_filename};
 
 const MAX_UPDATE_MANIFEST_BYTES: u64 = 1024 * 1024;
@@ -21,22 +23,24 @@ fn escape_bash_literal(value: &str) -> String {
 }
 
 #[tauri::command]
-pub async fn fetch_github_release(repo: String) -> Result<serde_json::Value, String> {
+pub async fn fetch_github_release(
+    app_handle: tauri::AppHandle,
+    repo: String,
+) -> Result<serde_json::Value, CommandError> {
     let url = format!("https://api.github.com/repos/{}/releases/latest", repo);
-    let client = reqwest::Client::new();
+    let http = http::shared_client(&app_handle);
 
-    let response = client
-        .get(&url)
-        .header(USER_AGENT, "noder-updater")
+    let response = http
+        .with_timeout(http.client().get(&url).header(USER_AGENT, "noder-updater"))
         .send()
         .await
-        .map_err(|e| format!("Failed to fetch release: {}", e))?;
+        .map_err(|e| CommandError::network(Some("GitHub"), "fetch release", e))?;
 
     let status = response.status();
     let body = response.text().await.map_err(|e| e.to_string())?;
 
     if !status.is_success() {
-        return Err(format!("GitHub API error ({}): {}", status, body));
+        return Err(CommandError::provider_status("GitHub", status, &body));
     }
 
     let json: serde_json::Value =
@@ -46,11 +50,13 @@ pub async fn fetch_github_release(repo: String) -> Result<serde_json::Value, Str
 }
 
 #[tauri::command]
-pub async fn fetch_update_manifest(url: String) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .header(USER_AGENT, "noder-updater")
+pub async fn fetch_update_manifest(
+    app_handle: tauri::AppHandle,
+    url: String,
+) -> Result<String, CommandError> {
+    let http = http::shared_client(&app_handle);
+    let response = http
+        .with_timeout(http.client().get(&url).header(USER_AGENT, "noder-updater"))
         .send()
         .await
         .map_err(|e| format!("Failed to fetch checksum manifest: {}", e))?;
@@ -61,11 +67,12 @@ pub async fn fetch_update_manifest(url: String) -> Result<String, String> {
         return Err(format!(
             "Checksum manifest download failed ({}): {}",
             status, error_text
-        ));
+        )
+        .into());
     }
 
     if response.content_length().unwrap_or(0) > MAX_UPDATE_MANIFEST_BYTES {
-        return Err("Checksum manifest is too large.".to_string());
+        return Err("Checksum manifest is too large.".into());
     }
 
     let body = response
@@ -74,7 +81,7 @@ pub async fn fetch_update_manifest(url: String) -> Result<String, String> {
         .map_err(|e| format!("Failed to read checksum manifest: {}", e))?;
 
     if body.len() as u64 > MAX_UPDATE_MANIFEST_BYTES {
-        return Err("Checksum manifest is too large.".to_string());
+        return Err("Checksum manifest is too large.".into());
     }
 
     Ok(body)
@@ -86,9 +93,10 @@ pub async fn download_update(
     url: String,
     file_name: Option<String>,
     dir_name: Option<String>,
-) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    let response = client
+) -> Result<String, CommandError> {
+    let http = http::shared_client(&app_handle);
+    let response = http
+        .client()
         .get(&url)
         .header(USER_AGENT, "noder-updater")
         .send()
@@ -98,13 +106,10 @@ pub async fn download_update(
     let status = response.status();
     if !status.is_success() {
         let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("Download failed ({}): {}", status, error_text));
+        return Err(format!("Download failed ({}): {}", status, error_text).into());
     }
 
-    let bytes = response
-        .bytes()
-        .await
-        .map_err(|e| format!("Failed to read update bytes: {}", e))?;
+    let bytes = http.read_body(response).await?;
 
     let app_data_dir = app_handle
         .path()
@@ -160,34 +165,37 @@ fn calculate_sha256(file_path: &Path) -> Result<String, io::Error> {
 }
 
 #[tauri::command]
-pub fn verify_update_sha256(file_path: String, expected_sha256: String) -> Result<(), String> {
+pub fn verify_update_sha256(
+    file_path: String,
+    expected_sha256: String,
+) -> Result<(), CommandError> {
     let expected = normalize_sha256(&expected_sha256).ok_or_else(|| {
         "Expected SHA-256 checksum must be 64 hexadecimal characters.".to_string()
     })?;
 
     let update_file = Path::new(&file_path);
     if !update_file.exists() {
-        return Err("Update file not found.".to_string());
+        return Err("Update file not found.".into());
     }
 
     let actual = calculate_sha256(update_file)
         .map_err(|e| format!("Failed to calculate update checksum: {}", e))?;
     if actual != expected {
-        return Err("Update checksum mismatch.".to_string());
+        return Err("Update checksum mismatch.".into());
     }
 
     Ok(())
 }
 
 #[tauri::command]
-pub fn apply_update(app: tauri::AppHandle, update_path: String) -> Result<(), String> {
+pub fn apply_update(app: tauri::AppHandle, update_path: String) -> Result<(), CommandError> {
     if cfg!(debug_assertions) {
-        return Err("Auto-update is disabled in dev builds.".to_string());
+        return Err("Auto-update is disabled in dev builds.".into());
     }
 
     let update_file = Path::new(&update_path);
     if !update_file.exists() {
-        return Err("Update file not found.".to_string());
+        return Err("Update file not found.".into());
     }
 
     let current_exe = std::env::current_exe().map_err(|e| e.to_string())?;
@@ -248,7 +256,7 @@ open "$target"
 
     #[cfg(not(any(target_os = "windows", target_os = "macos")))]
     {
-        return Err("Auto-update is not supported on this platform.".to_string());
+        return Err("Auto-update is not supported on this platform.".into());
     }
 
     app.exit(0);
@@ -257,7 +265,7 @@ open "$target"
 
 #[cfg(target_os = "macos")]
 #[tauri::command]
-pub fn extract_app_zip(zip_path: String) -> Result<String, String> {
+pub fn extract_app_zip(zip_path: String) -> Result<String, CommandError> {
     let zip_file = Path::new(&zip_path);
     let parent = zip_file.parent().ok_or("Invalid zip path")?;
 
@@ -282,8 +290,8 @@ pub fn extract_app_zip(zip_path: String) -> Result<String, String> {
 
 #[cfg(not(target_os = "macos"))]
 #[tauri::command]
-pub fn extract_app_zip(_zip_path: String) -> Result<String, String> {
-    Err("This command is only available on macOS".to_string())
+pub fn extract_app_zip(_zip_path: String) -> Result<String, CommandError> {
+    Err("This command is only available on macOS".into())
 }
 
 #[cfg(test)]
@@ -326,7 +334,7 @@ mod tests {
         let result = verify_update_sha256(path.to_string_lossy().to_string(), "0".repeat(64));
 
         fs::remove_file(path).ok();
-        assert_eq!(result.unwrap_err(), "Update checksum mismatch.");
+        assert_eq!(result.unwrap_err().to_string(), "Update checksum mismatch.");
     }
 
     #[test]
@@ -338,7 +346,7 @@ mod tests {
 
         fs::remove_file(path).ok();
         assert_eq!(
-            result.unwrap_err(),
+            result.unwrap_err().to_string(),
             "Expected SHA-256 checksum must be 64 hexadecimal characters."
         );
     }
@@ -18,7 +18,7 @@ import {
   getModel,
 } from './replicate';
 import { invoke } from '../types/tauri';
-import type { ReplicatePrediction } from '../types/tauri';
+import type { CommandError, ReplicatePrediction } from '../types/tauri';
 
 // Mock the tauri invoke
 vi.mock('../types/tauri', () => ({
@@ -32,6 +32,14 @@ vi.mock('../utils/errorLogger', () => ({
 
 const mockInvoke = vi.mocked(invoke);
 
+const unavailable: CommandError = {
+  code: 'provider_unavailable',
+  message: 'Replicate API error (503 Service Unavailable): try again',
+  provider: 'Replicate',
+  status: 503,
+  retryable: true,
+};
+
 describe('replicate API', () => {
   beforeEach(() => {
     vi.clearAllMocks();
@@ -301,6 +309,13 @@ describe('replicate API', () => {
 
       await expect(createPrediction('invalid', {})).rejects.toThrow('400 Bad Request');
     });
+
+    it('should not retry a retryable failure', async () => {
+      mockInvoke.mockRejectedValueOnce(unavailable);
+
+      await expect(createPrediction('owner/model', {})).rejects.toEqual(unavailable);
+      expect(mockInvoke).toHaveBeenCalledTimes(1);
+    });
   });
 
   describe('getPrediction', () => {
@@ -319,6 +334,37 @@ describe('replicate API', () => {
       });
       expect(result).toEqual(mockPrediction);
     });
+
+    it('should retry errors the backend marks as retryable', async () => {
+      vi.useFakeTimers();
+      const mockPrediction: ReplicatePrediction = {
+        id: 'pred-123',
+        status: 'processing',
+        output: null,
+      };
+      mockInvoke.mockRejectedValueOnce(unavailable).mockResolvedValueOnce(mockPrediction);
+
+      const pending = getPrediction('pred-123');
+      await vi.advanceTimersByTimeAsync(1000);
+
+      await expect(pending).resolves.toEqual(mockPrediction);
+      expect(mockInvoke).toHaveBeenCalledTimes(2);
+      vi.useRealTimers();
+    });
+
+    it('should rethrow non-retryable errors without retrying', async () => {
+      const notFound: CommandError = {
+        code: 'not_found',
+        message: 'Replicate API error (404 Not Found): Prediction not found',
+        provider: 'Replicate',
+        status: 404,
+        retryable: false,
+      };
+      mockInvoke.mockRejectedValueOnce(notFound);
+
+      await expect(getPrediction('missing')).rejects.toEqual(notFound);
+      expect(mockInvoke).toHaveBeenCalledTimes(1);
+    });
   });
 
   describe('pollPrediction', () => {
@@ -7,6 +7,7 @@
 
 import { invoke } from '../types/tauri';
 import { logApiError } from '../utils/errorLogger';
+import { isCommandError, isRetryableError, toError } from '../utils/commandError';
 import type { ReplicatePrediction, ReplicateModel, ReplicateModelsResponse } from '../types/tauri';
 
 import { logger } from '../utils/logger';
@@ -60,54 +61,32 @@ export type PredictionOutputType = 'text' | 'image' | 'video' | 'audio';
 const delay = (ms: number): Promise<void> => new Promise((resolve) => setTimeout(resolve, ms));
 
 /**
- * Check if an error is a client error (4xx) that shouldn't be retried
- */
-function isClientError(error: Error): boolean {
-  const message = error.message || '';
-  return (
-    message.includes('400') ||
-    message.includes('401') ||
-    message.includes('403') ||
-    message.includes('404')
-  );
-}
-
-/**
- * Generic retry wrapper for API calls
+ * Generic retry wrapper for API calls.
+ *
+ * Only errors the backend marks as retryable are repeated; anything else
+ * (bad input, auth, not found, failed predictions) is thrown straight away.
  */
 async function withRetry<T>(fn: () => Promise<T>, options: RetryOptions = {}): Promise<T> {
   const { maxRetries = MAX_RETRIES, retryDelay = RETRY_DELAY_MS, operation = 'api_call' } = options;
 
-  let lastError: Error | undefined;
-
-  for (let attempt = 1; attempt <= maxRetries; attempt++) {
+  for (let attempt = 1; ; attempt++) {
     try {
       return await fn();
     } catch (error) {
-      lastError = error instanceof Error ? error : new Error(String(error));
-
-      // Don't retry on client errors (4xx)
-      if (isClientError(lastError)) {
-        throw lastError;
+      if (attempt >= maxRetries || !isRetryableError(error)) {
+        logApiError(toError(error), operation, {
+          attempts: attempt,
+          code: isCommandError(error) ? error.code : undefined,
+        });
+        throw error;
       }
 
-      if (attempt < maxRetries) {
-        logger.warn(
-          `[Replicate API] ${operation} failed (attempt ${attempt}/${maxRetries}), retrying in ${retryDelay}ms...`
-        );
-        await delay(retryDelay * attempt); // Exponential backoff
-      }
+      logger.warn(
+        `[Replicate API] ${operation} failed (attempt ${attempt}/${maxRetries}), retrying in ${retryDelay}ms...`
+      );
+      await delay(retryDelay * attempt); // Exponential backoff
     }
   }
-
-  // Log the final error
-  if (lastError) {
-    logApiError(lastError, operation, { attempts: maxRetries });
-    throw lastError;
-  }
-
-  // This shouldn't happen, but TypeScript needs it
-  throw new Error(`${operation} failed after ${maxRetries} attempts`);
 }
 
 // =============================================================================
@@ -125,9 +104,15 @@ export async function createPrediction(
   model: string,
   input: Record<string, unknown>
 ): Promise<ReplicatePrediction> {
-  return withRetry(() => invoke('replicate_create_prediction', { model, input }), {
-    operation: 'replicate_create_prediction',
-  });
+  // Not retried: a create that timed out may still have started a paid prediction.
+  try {
+    return await invoke('replicate_create_prediction', { model, input });
+  } catch (error) {
+    logApiError(toError(error), 'replicate_create_prediction', {
+      code: isCommandError(error) ? error.code : undefined,
+    });
+    throw error;
+  }
 }
 
 /**
@@ -161,6 +161,24 @@ describe('useExecutionStore', () => {
       expect(useExecutionStore.getState().failedNodes[0].error).toBe('String error');
     });
 
+    it('should use the message of a command error', () => {
+      useExecutionStore.getState().addFailedNode(
+        'node-1',
+        {
+          code: 'provider_failed',
+          message: 'Replicate error: prediction failed: CUDA out of memory',
+          provider: 'Replicate',
+          status: null,
+          retryable: false,
+        },
+        mockNode
+      );
+
+      expect(useExecutionStore.getState().failedNodes[0].error).toBe(
+        'Replicate error: prediction failed: CUDA out of memory'
+      );
+    });
+
     it('should not add duplicate failed nodes', () => {
       useExecutionStore.getState().addFailedNode('node-1', 'Error 1', mockNode);
       useExecutionStore.getState().addFailedNode('node-1', 'Error 2', mockNode);
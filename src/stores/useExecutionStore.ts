@@ -2,6 +2,7 @@ import { useMemo } from 'react';
 import { create } from 'zustand';
 import { subscribeWithSelector } from 'zustand/middleware';
 import { Node, Edge } from 'reactflow';
+import { getErrorMessage } from '../utils/commandError';
 
 // ============================================================================
 // Types
@@ -68,7 +69,7 @@ export interface ExecutionStoreActions {
   getNodeDuration: (nodeId: string) => number | null;
 
   // Error recovery
-  addFailedNode: (nodeId: string, error: Error | string, node: Node) => void;
+  addFailedNode: (nodeId: string, error: unknown, node: Node) => void;
   removeFailedNode: (nodeId: string) => void;
   clearFailedNodes: () => void;
   closeErrorRecovery: () => void;
@@ -190,7 +191,7 @@ export const useExecutionStore = create<ExecutionStore>()(
 
     // Error recovery
     addFailedNode: (nodeId, error, node) => {
-      const errorMessage = error instanceof Error ? error.message : String(error);
+      const errorMessage = getErrorMessage(error);
       set((state) => {
         // Avoid duplicates
         if (state.failedNodes.some((fn) => fn.id === nodeId)) {
@@ -10,7 +10,12 @@ vi.mock('../types/tauri', () => ({
   invoke: vi.fn(),
 }));
 
+vi.mock('../utils/appFeedback', () => ({
+  notifyWarning: vi.fn(),
+}));
+
 import { invoke } from '../types/tauri';
+import { notifyWarning } from '../utils/appFeedback';
 const mockInvoke = vi.mocked(invoke);
 
 // Mock localStorage
@@ -323,6 +328,20 @@ describe('useSettingsStore', () => {
       // Should still mark as loaded so app can proceed
       expect(useSettingsStore.getState().isLoaded).toBe(true);
     });
+
+    it('should warn when the network settings could not be applied', async () => {
+      mockInvoke
+        .mockResolvedValueOnce({})
+        .mockResolvedValueOnce('Failed to read CA bundle /missing.pem: not found');
+
+      await useSettingsStore.getState().loadFromTauri();
+
+      expect(mockInvoke).toHaveBeenCalledWith('network_config_error');
+      expect(vi.mocked(notifyWarning)).toHaveBeenCalledWith(
+        expect.stringContaining('Failed to read CA bundle'),
+        'Network settings'
+      );
+    });
   });
 
   describe('saveToTauri', () => {
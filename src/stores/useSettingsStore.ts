@@ -2,6 +2,7 @@ import { create } from 'zustand';
 import { persist, createJSONStorage } from 'zustand/middleware';
 import { invoke } from '../types/tauri';
 import { isTauriRuntime } from '../utils/runtime';
+import { notifyWarning } from '../utils/appFeedback';
 
 import { logger } from '../utils/logger';
 // ============================================================================
@@ -448,6 +449,20 @@ export const useSettingsStore = create<SettingsStore>()(
           logger.error('Failed to load settings from Tauri:', error);
           set({ isLoaded: true }); // Still mark as loaded so app can proceed with defaults
         }
+
+        // Startup falls back to a default HTTP client when the saved network
+        // settings cannot be applied; tell the user so they can fix them.
+        try {
+          const networkError = await invoke('network_config_error');
+          if (networkError) {
+            notifyWarning(
+              `Requests are using default network settings: ${networkError}`,
+              'Network settings'
+            );
+          }
+        } catch (error) {
+          logger.error('Failed to check network settings:', error);
+        }
       },
 
       // Save settings to Tauri backend (debounced internally)
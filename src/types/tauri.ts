@@ -134,6 +134,144 @@ export interface OpenAIModel {
   owned_by?: string | null;
 }
 
+/** Where OpenAI-style requests go; omitted means api.openai.com */
+export type OpenAIEndpoint =
+  | { kind: 'openai' }
+  | { kind: 'lm_studio' }
+  | {
+      kind: 'custom';
+      base_url: string;
+      api_key?: string | null;
+      headers?: Record<string, string>;
+    };
+
+// =============================================================================
+// Chat Types
+// =============================================================================
+
+/** Inline or remote media in a content block */
+export type MediaSource =
+  | { type: 'base64'; media_type: string; data: string }
+  | { type: 'url'; url: string };
+
+/** Typed content block, following Anthropic's wire format */
+export type ContentBlock =
+  | { type: 'text'; text: string }
+  | { type: 'tool_use'; id: string; name: string; input: unknown }
+  | {
+      type: 'tool_result';
+      tool_use_id: string;
+      content?: MessageContent | null;
+      is_error?: boolean | null;
+    }
+  | { type: 'image'; source: MediaSource }
+  | { type: 'document'; source: MediaSource; title?: string | null };
+
+/** Plain text or a list of content blocks */
+export type MessageContent = string | ContentBlock[];
+
+/** One turn of a conversation sent to a chat command */
+export interface ChatMessage {
+  role: 'system' | 'user' | 'assistant';
+  content: MessageContent;
+}
+
+/** A file sent alongside the newest user turn */
+export type ChatAttachment =
+  | { source: 'path'; path: string; media_type?: string | null }
+  | { source: 'url'; url: string; media_type?: string | null };
+
+/** Workflow, run and node a provider call is recorded against in the usage ledger */
+export interface CallContext {
+  workflow_id?: string | null;
+  run_id?: string | null;
+  node_id?: string | null;
+}
+
+// =============================================================================
+// Anthropic API Types
+// =============================================================================
+
+/** Tool the model may call */
+export interface AnthropicTool {
+  name: string;
+  description?: string | null;
+  input_schema: Record<string, unknown>;
+}
+
+/** How the model should pick among the tools */
+export type AnthropicToolChoice =
+  | { type: 'auto' }
+  | { type: 'any' }
+  | { type: 'tool'; name: string }
+  | { type: 'none' };
+
+/** Optional sampling and request parameters */
+export interface AnthropicOptions {
+  max_tokens?: number | null;
+  top_p?: number | null;
+  top_k?: number | null;
+  stop_sequences?: string[] | null;
+  metadata?: { user_id?: string | null } | null;
+}
+
+/** Token usage reported by the Messages API */
+export interface AnthropicUsage {
+  input_tokens: number;
+  output_tokens: number;
+  cache_creation_input_tokens?: number;
+  cache_read_input_tokens?: number;
+}
+
+/** Result of anthropic_request; `stop_reason` is `max_tokens` when truncated */
+export interface AnthropicMessageResult {
+  id: string;
+  model: string;
+  text: string;
+  content: ContentBlock[];
+  stop_reason?: string | null;
+  stop_sequence?: string | null;
+  usage: AnthropicUsage;
+}
+
+// =============================================================================
+// Error Types
+// =============================================================================
+
+/** Stable error category; switch on this rather than on the message text */
+export type CommandErrorCode =
+  | 'missing_api_key'
+  | 'unauthorized'
+  | 'not_found'
+  | 'rate_limited'
+  | 'quota_exceeded'
+  | 'invalid_request'
+  | 'provider_failed'
+  | 'provider_unavailable'
+  | 'timeout'
+  | 'network'
+  | 'budget_exceeded'
+  | 'cancelled'
+  | 'internal';
+
+/** Machine-readable parts of a provider's error body */
+export interface ProviderErrorDetails {
+  error_type?: string | null;
+  error_code?: string | null;
+  param?: string | null;
+  body: unknown;
+}
+
+/** Error value a provider command rejects with */
+export interface CommandError {
+  code: CommandErrorCode;
+  message: string;
+  provider?: string | null;
+  status?: number | null;
+  retryable: boolean;
+  details?: ProviderErrorDetails | null;
+}
+
 // =============================================================================
 // WhatsApp Types
 // =============================================================================
@@ -269,21 +407,32 @@ export interface SaveSettingsArgs {
   settings: AppSettings;
 }
 
-/** Arguments for anthropic_request command */
+/** Arguments for anthropic_request command; the API key comes from settings */
 export interface AnthropicRequestArgs {
-  apiKey: string;
   model: string;
-  systemPrompt: string;
-  userContent: string;
+  systemPrompt?: string | null;
+  userContent?: string | null;
   temperature: number;
+  messages?: ChatMessage[] | null;
+  tools?: AnthropicTool[] | null;
+  toolChoice?: AnthropicToolChoice | null;
+  attachments?: ChatAttachment[] | null;
+  options?: AnthropicOptions | null;
+  callContext?: CallContext | null;
+  requestId?: string | null;
 }
 
 /** Arguments for openai_chat_completion command */
 export interface OpenAIChatCompletionArgs {
   model: string;
-  systemPrompt: string;
-  userContent: string;
+  systemPrompt?: string | null;
+  userContent?: string | null;
   temperature?: number | null;
+  messages?: ChatMessage[] | null;
+  attachments?: ChatAttachment[] | null;
+  endpoint?: OpenAIEndpoint | null;
+  callContext?: CallContext | null;
+  requestId?: string | null;
 }
 
 /** Arguments for send_whatsapp_message command */
@@ -354,6 +503,7 @@ export interface TauriCommands {
   // Settings commands
   save_settings: { args: SaveSettingsArgs; return: void };
   load_settings: { args: never; return: AppSettings };
+  network_config_error: { args: never; return: string | null };
 
   // Replicate commands
   replicate_create_prediction: { args: ReplicateCreatePredictionArgs; return: ReplicatePrediction };
@@ -374,7 +524,7 @@ export interface TauriCommands {
   openai_chat_completion: { args: OpenAIChatCompletionArgs; return: string };
 
   // Anthropic commands
-  anthropic_request: { args: AnthropicRequestArgs; return: string };
+  anthropic_request: { args: AnthropicRequestArgs; return: AnthropicMessageResult };
 
   // WhatsApp commands
   send_whatsapp_message: { args: SendWhatsAppMessageArgs; return: void };
@@ -0,0 +1,72 @@
+/**
+ * Tests for commandError utility
+ */
+
+import { describe, it, expect } from 'vitest';
+import { isCommandError, getErrorMessage, isRetryableError, toError } from './commandError';
+import type { CommandError } from '../types/tauri';
+
+const rateLimited: CommandError = {
+  code: 'rate_limited',
+  message: 'Replicate API error (429 Too Many Requests): slow down',
+  provider: 'Replicate',
+  status: 429,
+  retryable: true,
+  details: null,
+};
+
+const invalidRequest: CommandError = {
+  code: 'invalid_request',
+  message: 'Model input must be a JSON object',
+  provider: null,
+  status: null,
+  retryable: false,
+  details: null,
+};
+
+describe('commandError', () => {
+  describe('isCommandError', () => {
+    it('recognizes serialized command errors', () => {
+      expect(isCommandError(rateLimited)).toBe(true);
+    });
+
+    it('rejects plain errors, strings and partial objects', () => {
+      expect(isCommandError(new Error('boom'))).toBe(false);
+      expect(isCommandError('boom')).toBe(false);
+      expect(isCommandError(null)).toBe(false);
+      expect(isCommandError({ message: 'boom' })).toBe(false);
+    });
+  });
+
+  describe('getErrorMessage', () => {
+    it('uses the message of a command error instead of [object Object]', () => {
+      expect(getErrorMessage(invalidRequest)).toBe('Model input must be a JSON object');
+    });
+
+    it('handles errors, strings and other values', () => {
+      expect(getErrorMessage(new Error('boom'))).toBe('boom');
+      expect(getErrorMessage('plain text')).toBe('plain text');
+      expect(getErrorMessage({ message: 'shaped' })).toBe('shaped');
+      expect(getErrorMessage(42)).toBe('42');
+    });
+  });
+
+  describe('isRetryableError', () => {
+    it('follows the retryable flag of a command error', () => {
+      expect(isRetryableError(rateLimited)).toBe(true);
+      expect(isRetryableError(invalidRequest)).toBe(false);
+    });
+
+    it('does not retry errors without a classification', () => {
+      expect(isRetryableError(new Error('503 Service Unavailable'))).toBe(false);
+    });
+  });
+
+  describe('toError', () => {
+    it('keeps Error instances and wraps everything else', () => {
+      const error = new Error('boom');
+      expect(toError(error)).toBe(error);
+      expect(toError(invalidRequest).message).toBe('Model input must be a JSON object');
+    });
+  });
+});
@@ -0,0 +1,52 @@
+/**
+ * Helpers for errors rejected by Tauri commands.
+ *
+ * Provider commands reject with a serialized CommandError object rather than an
+ * Error, so `error.message` and `String(error)` need care. Branch on `code` and
+ * `retryable` instead of matching message text.
+ */
+
+import type { CommandError } from '../types/tauri';
+
+/**
+ * Check whether a rejected value is a serialized CommandError
+ */
+export function isCommandError(error: unknown): error is CommandError {
+  return (
+    typeof error === 'object' &&
+    error !== null &&
+    typeof (error as CommandError).code === 'string' &&
+    typeof (error as CommandError).message === 'string' &&
+    typeof (error as CommandError).retryable === 'boolean'
+  );
+}
+
+/**
+ * Human-readable message for any rejected value
+ */
+export function getErrorMessage(error: unknown): string {
+  if (error instanceof Error || isCommandError(error)) {
+    return error.message;
+  }
+  if (typeof error === 'string') {
+    return error;
+  }
+  if (typeof error === 'object' && error !== null && 'message' in error) {
+    return String((error as { message: unknown }).message);
+  }
+  return String(error);
+}
+
+/**
+ * Whether the command says the same call may succeed if repeated
+ */
+export function isRetryableError(error: unknown): boolean {
+  return isCommandError(error) && error.retryable;
+}
+
+/**
+ * Convert a rejected value into an Error, keeping the message readable
+ */
+export function toError(error: unknown): Error {
+  return error instanceof Error ? error : new Error(getErrorMessage(error));
+}